@@ -0,0 +1,588 @@
+use super::buffer_list::{BufferList, BufferListRef};
+use super::stream_format::StreamFormat;
+use coreaudio_sys::{
+    kAudioConverterChannelMap, kAudioConverterCompressionMagicCookie,
+    kAudioConverterCurrentInputStreamDescription, kAudioConverterCurrentOutputStreamDescription,
+    kAudioConverterDecompressionMagicCookie, kAudioConverterEncodeBitRate,
+    kAudioConverterPropertyMaximumInputPacketSize, kAudioConverterPropertyMaximumOutputPacketSize,
+    kAudioConverterQuality_High, kAudioConverterQuality_Low, kAudioConverterQuality_Max,
+    kAudioConverterQuality_Medium, kAudioConverterQuality_Min,
+    kAudioConverterSampleRateConverterComplexity,
+    kAudioConverterSampleRateConverterComplexity_Linear,
+    kAudioConverterSampleRateConverterComplexity_Mastering,
+    kAudioConverterSampleRateConverterComplexity_Normal, kAudioConverterSampleRateConverterQuality,
+    kAudio_ParamError, noErr, AudioBuffer, AudioBufferList, AudioConverterDispose,
+    AudioConverterFillComplexBuffer, AudioConverterGetProperty, AudioConverterGetPropertyInfo,
+    AudioConverterNew, AudioConverterPropertyID, AudioConverterRef, AudioConverterReset,
+    AudioConverterSetProperty, AudioStreamBasicDescription, AudioStreamPacketDescription, OSStatus,
+};
+use std::mem;
+use std::os::raw::c_void;
+use std::panic;
+use std::ptr;
+use std::slice;
+
+// The status the input proc returns when the input has no packets for now. It stops the
+// conversion without ending the stream. 'calx'.
+const NO_INPUT_NOW: OSStatus = 0x6361_6c78;
+
+// The status the input proc returns when the input callback panicked. 'pnic'.
+const INPUT_PANICKED: OSStatus = 0x706e_6963;
+
+// The quality of sample rate conversion, `kAudioConverterQuality_*`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConverterQuality {
+    Min,
+    Low,
+    Medium,
+    High,
+    Max,
+}
+
+const QUALITIES: &[(ConverterQuality, u32)] = &[
+    (ConverterQuality::Min, kAudioConverterQuality_Min),
+    (ConverterQuality::Low, kAudioConverterQuality_Low),
+    (ConverterQuality::Medium, kAudioConverterQuality_Medium),
+    (ConverterQuality::High, kAudioConverterQuality_High),
+    (ConverterQuality::Max, kAudioConverterQuality_Max),
+];
+
+// The sample rate conversion algorithm, `kAudioConverterSampleRateConverterComplexity_*`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConverterComplexity {
+    Linear,
+    Normal,
+    Mastering,
+}
+
+const COMPLEXITIES: &[(ConverterComplexity, u32)] = &[
+    (
+        ConverterComplexity::Linear,
+        kAudioConverterSampleRateConverterComplexity_Linear,
+    ),
+    (
+        ConverterComplexity::Normal,
+        kAudioConverterSampleRateConverterComplexity_Normal,
+    ),
+    (
+        ConverterComplexity::Mastering,
+        kAudioConverterSampleRateConverterComplexity_Mastering,
+    ),
+];
+
+// A safe mirror of `AudioStreamPacketDescription`, which describes one packet of a format whose
+// packets vary in size, e.g., AAC.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PacketDescription {
+    // The packet's offset in its buffer, in bytes.
+    pub start_offset: i64,
+    // The packet's number of frames, or 0 if the format's number of frames per packet is fixed.
+    pub variable_frames: u32,
+    pub data_byte_size: u32,
+}
+
+// What the input callback of `AudioConverter::fill` hands to the converter.
+pub enum Input<'a> {
+    Packets {
+        // One buffer per buffer of the source format, with `packets` packets.
+        buffers: BufferListRef<'a>,
+        packets: u32,
+        // Required by formats whose packets vary in size.
+        descriptions: Option<&'a [PacketDescription]>,
+    },
+    // There are no packets for now. `fill` returns what it converted so far, and converting
+    // can continue later.
+    Pending,
+    // There are no more packets. The converter flushes what it holds.
+    EndOfStream,
+}
+
+// A wrapper of AudioToolbox's `AudioConverter`, which converts between linear PCM formats,
+// sample rates and channel counts, and encodes and decodes Apple's codecs, e.g., AAC and ALAC.
+pub struct AudioConverter {
+    converter: AudioConverterRef,
+    input: InputCopy,
+}
+
+// The alignment of the copied input data, enough for any sample format.
+type DataUnit = u64;
+
+// A copy of the last input handed to the converter. The converter can read it until it asks for
+// more input, even in a later `fill`, when the data `Input` borrowed may be gone.
+#[derive(Default)]
+struct InputCopy {
+    data: Vec<Vec<DataUnit>>,
+    descriptions: Vec<PacketDescription>,
+}
+
+impl AudioConverter {
+    pub fn new(source: &StreamFormat, destination: &StreamFormat) -> Result<Self, OSStatus> {
+        let source = AudioStreamBasicDescription::from(*source);
+        let destination = AudioStreamBasicDescription::from(*destination);
+        let mut converter: AudioConverterRef = ptr::null_mut();
+        let status = unsafe { AudioConverterNew(&source, &destination, &mut converter) };
+        if status == noErr as OSStatus {
+            Ok(Self {
+                converter,
+                input: InputCopy::default(),
+            })
+        } else {
+            Err(status)
+        }
+    }
+
+    // The source format, with the fields the converter fills in, e.g., for compressed formats.
+    pub fn source(&self) -> Result<StreamFormat, OSStatus> {
+        self.get_property::<AudioStreamBasicDescription>(
+            kAudioConverterCurrentInputStreamDescription,
+        )
+        .map(StreamFormat::from)
+    }
+
+    pub fn destination(&self) -> Result<StreamFormat, OSStatus> {
+        self.get_property::<AudioStreamBasicDescription>(
+            kAudioConverterCurrentOutputStreamDescription,
+        )
+        .map(StreamFormat::from)
+    }
+
+    // Convert up to `packets` packets into `output`, pulling input packets from `input` as
+    // needed. `input` is called with the number of packets the converter wants. Returns the
+    // number of packets converted, which is less than `packets` once the input is pending or
+    // has ended. Formats whose packets vary in size also fill in `descriptions`. The converter
+    // reads a copy of the input, so the input only has to live until `input` returns.
+    pub fn fill<'a, F>(
+        &mut self,
+        output: &mut BufferList,
+        packets: u32,
+        descriptions: Option<&mut [PacketDescription]>,
+        input: F,
+    ) -> Result<u32, OSStatus>
+    where
+        F: FnMut(u32) -> Input<'a>,
+    {
+        let descriptions = match descriptions {
+            Some(descriptions) => {
+                if descriptions.len() < packets as usize {
+                    return Err(kAudio_ParamError as OSStatus);
+                }
+                descriptions.as_mut_ptr() as *mut AudioStreamPacketDescription
+            }
+            None => ptr::null_mut(),
+        };
+        output.reset_data_sizes();
+        let mut context = InputContext {
+            input,
+            copy: &mut self.input,
+            panic: None,
+        };
+        let mut packets = packets;
+        let status = unsafe {
+            AudioConverterFillComplexBuffer(
+                self.converter,
+                Some(input_proc::<'a, F>),
+                &mut context as *mut InputContext<'_, F> as *mut c_void,
+                &mut packets,
+                output.as_mut_ptr().cast(),
+                descriptions,
+            )
+        };
+        if let Some(panic) = context.panic {
+            panic::resume_unwind(panic);
+        }
+        if status == noErr as OSStatus || status == NO_INPUT_NOW {
+            Ok(packets)
+        } else {
+            Err(status)
+        }
+    }
+
+    // Drop the data the converter holds, e.g., after a discontinuity in the input.
+    pub fn reset(&mut self) -> Result<(), OSStatus> {
+        let status = unsafe { AudioConverterReset(self.converter) };
+        if status == noErr as OSStatus {
+            Ok(())
+        } else {
+            Err(status)
+        }
+    }
+
+    pub fn sample_rate_converter_quality(&self) -> Result<ConverterQuality, OSStatus> {
+        let quality = self.get_property::<u32>(kAudioConverterSampleRateConverterQuality)?;
+        // Qualities between the named ones round down.
+        Ok(QUALITIES
+            .iter()
+            .rev()
+            .find(|(_, q)| *q <= quality)
+            .map_or(ConverterQuality::Min, |(q, _)| *q))
+    }
+
+    pub fn set_sample_rate_converter_quality(
+        &mut self,
+        quality: ConverterQuality,
+    ) -> Result<(), OSStatus> {
+        let (_, quality) = QUALITIES
+            .iter()
+            .find(|(q, _)| *q == quality)
+            .expect("every quality is listed in QUALITIES");
+        self.set_property(kAudioConverterSampleRateConverterQuality, quality)
+    }
+
+    pub fn sample_rate_converter_complexity(&self) -> Result<ConverterComplexity, OSStatus> {
+        let complexity = self.get_property::<u32>(kAudioConverterSampleRateConverterComplexity)?;
+        COMPLEXITIES
+            .iter()
+            .find(|(_, c)| *c == complexity)
+            .map(|(c, _)| *c)
+            .ok_or(kAudio_ParamError as OSStatus)
+    }
+
+    pub fn set_sample_rate_converter_complexity(
+        &mut self,
+        complexity: ConverterComplexity,
+    ) -> Result<(), OSStatus> {
+        let (_, complexity) = COMPLEXITIES
+            .iter()
+            .find(|(c, _)| *c == complexity)
+            .expect("every complexity is listed in COMPLEXITIES");
+        self.set_property(kAudioConverterSampleRateConverterComplexity, complexity)
+    }
+
+    // The source channel of each destination channel, or -1 for silence.
+    pub fn channel_map(&self) -> Result<Vec<i32>, OSStatus> {
+        self.get_property_array::<i32>(kAudioConverterChannelMap)
+    }
+
+    pub fn set_channel_map(&mut self, map: &[i32]) -> Result<(), OSStatus> {
+        self.set_property_array(kAudioConverterChannelMap, map)
+    }
+
+    // The codec configuration of an encoder, to store with the encoded packets.
+    pub fn compression_magic_cookie(&self) -> Result<Vec<u8>, OSStatus> {
+        self.get_property_array::<u8>(kAudioConverterCompressionMagicCookie)
+    }
+
+    // The codec configuration stored with the packets a decoder decodes, e.g., an ALAC file's.
+    pub fn set_decompression_magic_cookie(&mut self, cookie: &[u8]) -> Result<(), OSStatus> {
+        self.set_property_array(kAudioConverterDecompressionMagicCookie, cookie)
+    }
+
+    // In bits per second.
+    pub fn bit_rate(&self) -> Result<u32, OSStatus> {
+        self.get_property::<u32>(kAudioConverterEncodeBitRate)
+    }
+
+    pub fn set_bit_rate(&mut self, bit_rate: u32) -> Result<(), OSStatus> {
+        self.set_property(kAudioConverterEncodeBitRate, &bit_rate)
+    }
+
+    // In bytes.
+    pub fn maximum_input_packet_size(&self) -> Result<u32, OSStatus> {
+        self.get_property::<u32>(kAudioConverterPropertyMaximumInputPacketSize)
+    }
+
+    pub fn maximum_output_packet_size(&self) -> Result<u32, OSStatus> {
+        self.get_property::<u32>(kAudioConverterPropertyMaximumOutputPacketSize)
+    }
+
+    // A buffer list that can hold `packets` packets of the destination format.
+    pub fn allocate_output(&self, packets: usize) -> Result<BufferList, OSStatus> {
+        let destination = self.destination()?;
+        if destination.is_linear_pcm() {
+            return Ok(BufferList::allocate(&destination, packets));
+        }
+        let size = self.maximum_output_packet_size()? as usize;
+        Ok(BufferList::allocate_bytes(
+            1,
+            destination.channels_per_frame,
+            size * packets,
+        ))
+    }
+
+    fn get_property<T: Copy + Default>(&self, id: AudioConverterPropertyID) -> Result<T, OSStatus> {
+        let mut data = T::default();
+        let mut size = mem::size_of::<T>() as u32;
+        let status = unsafe {
+            AudioConverterGetProperty(
+                self.converter,
+                id,
+                &mut size,
+                &mut data as *mut T as *mut c_void,
+            )
+        };
+        if status == noErr as OSStatus {
+            Ok(data)
+        } else {
+            Err(status)
+        }
+    }
+
+    fn get_property_array<T: Copy + Default>(
+        &self,
+        id: AudioConverterPropertyID,
+    ) -> Result<Vec<T>, OSStatus> {
+        let mut size = 0;
+        let status = unsafe {
+            AudioConverterGetPropertyInfo(self.converter, id, &mut size, ptr::null_mut())
+        };
+        if status != noErr as OSStatus {
+            return Err(status);
+        }
+        let mut data = vec![T::default(); size as usize / mem::size_of::<T>()];
+        let status = unsafe {
+            AudioConverterGetProperty(
+                self.converter,
+                id,
+                &mut size,
+                data.as_mut_ptr() as *mut c_void,
+            )
+        };
+        if status == noErr as OSStatus {
+            data.truncate(size as usize / mem::size_of::<T>());
+            Ok(data)
+        } else {
+            Err(status)
+        }
+    }
+
+    fn set_property<T>(&mut self, id: AudioConverterPropertyID, data: &T) -> Result<(), OSStatus> {
+        self.set_property_array(id, slice::from_ref(data))
+    }
+
+    fn set_property_array<T>(
+        &mut self,
+        id: AudioConverterPropertyID,
+        data: &[T],
+    ) -> Result<(), OSStatus> {
+        let status = unsafe {
+            AudioConverterSetProperty(
+                self.converter,
+                id,
+                mem::size_of_val(data) as u32,
+                data.as_ptr() as *const c_void,
+            )
+        };
+        if status == noErr as OSStatus {
+            Ok(())
+        } else {
+            Err(status)
+        }
+    }
+}
+
+impl Drop for AudioConverter {
+    fn drop(&mut self) {
+        unsafe { AudioConverterDispose(self.converter) };
+    }
+}
+
+struct InputContext<'c, F> {
+    input: F,
+    copy: &'c mut InputCopy,
+    // A panic of `input`, resumed once the converter returns.
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+extern "C" fn input_proc<'a, F>(
+    _converter: AudioConverterRef,
+    packets: *mut u32,
+    data: *mut AudioBufferList,
+    descriptions: *mut *mut AudioStreamPacketDescription,
+    context: *mut c_void,
+) -> OSStatus
+where
+    F: FnMut(u32) -> Input<'a>,
+{
+    let context = unsafe { &mut *(context as *mut InputContext<'_, F>) };
+    let requested = unsafe { *packets };
+    let input = match panic::catch_unwind(panic::AssertUnwindSafe(|| (context.input)(requested))) {
+        Ok(input) => input,
+        Err(panic) => {
+            context.panic = Some(panic);
+            unsafe { *packets = 0 };
+            return INPUT_PANICKED;
+        }
+    };
+    match input {
+        Input::Packets {
+            buffers,
+            packets: count,
+            descriptions: packet_descriptions,
+        } => {
+            let list = unsafe { &mut *data };
+            if buffers.len() != list.mNumberBuffers as usize {
+                unsafe { *packets = 0 };
+                return kAudio_ParamError as OSStatus;
+            }
+            let targets = unsafe {
+                slice::from_raw_parts_mut(
+                    ptr::addr_of_mut!(list.mBuffers) as *mut AudioBuffer,
+                    buffers.len(),
+                )
+            };
+            let copy = &mut *context.copy;
+            copy.data.resize_with(buffers.len(), Vec::new);
+            for ((target, buffer), data) in targets
+                .iter_mut()
+                .zip(buffers.buffers())
+                .zip(copy.data.iter_mut())
+            {
+                let size = buffer.mDataByteSize as usize;
+                data.clear();
+                data.resize(size.div_ceil(mem::size_of::<DataUnit>()), 0);
+                if size > 0 && !buffer.mData.is_null() {
+                    unsafe {
+                        ptr::copy_nonoverlapping(
+                            buffer.mData as *const u8,
+                            data.as_mut_ptr() as *mut u8,
+                            size,
+                        )
+                    };
+                }
+                *target = AudioBuffer::from(*buffer);
+                target.mData = data.as_mut_ptr() as *mut c_void;
+            }
+            copy.descriptions.clear();
+            if let Some(packet_descriptions) = packet_descriptions {
+                copy.descriptions.extend_from_slice(packet_descriptions);
+            }
+            if !descriptions.is_null() {
+                unsafe {
+                    *descriptions = if packet_descriptions.is_some() {
+                        copy.descriptions.as_mut_ptr() as *mut AudioStreamPacketDescription
+                    } else {
+                        ptr::null_mut()
+                    };
+                }
+            }
+            unsafe { *packets = count };
+            noErr as OSStatus
+        }
+        Input::Pending => {
+            unsafe { *packets = 0 };
+            NO_INPUT_NOW
+        }
+        Input::EndOfStream => {
+            unsafe { *packets = 0 };
+            noErr as OSStatus
+        }
+    }
+}
+
+#[test]
+fn test_packet_description_layout() {
+    assert_eq!(
+        mem::size_of::<PacketDescription>(),
+        mem::size_of::<AudioStreamPacketDescription>()
+    );
+    assert_eq!(
+        mem::align_of::<PacketDescription>(),
+        mem::align_of::<AudioStreamPacketDescription>()
+    );
+    let description = PacketDescription {
+        start_offset: 1,
+        variable_frames: 2,
+        data_byte_size: 3,
+    };
+    let raw = unsafe {
+        *(&description as *const PacketDescription as *const AudioStreamPacketDescription)
+    };
+    assert_eq!(
+        (
+            raw.mStartOffset,
+            raw.mVariableFramesInPacket,
+            raw.mDataByteSize
+        ),
+        (1, 2, 3)
+    );
+}
+
+#[test]
+fn test_convert_int16_to_float32() {
+    const FRAMES: usize = 256;
+    let source = StreamFormat::int16(44100.0, 2, true);
+    let destination = StreamFormat::float32(44100.0, 2, true);
+    let samples: Vec<i16> = (0..FRAMES * 2)
+        .map(|i| (i as i32 * 128 - 32768) as i16)
+        .collect();
+    let mut input = BufferList::allocate(&source, FRAMES);
+    input.data_mut::<i16>(0).unwrap().copy_from_slice(&samples);
+
+    let mut converter = AudioConverter::new(&source, &destination).unwrap();
+    assert_eq!(
+        converter.destination().unwrap().format_id,
+        destination.format_id
+    );
+    let mut output = converter.allocate_output(FRAMES).unwrap();
+    let mut fed = false;
+    let frames = converter
+        .fill(&mut output, FRAMES as u32, None, |_| {
+            if fed {
+                return Input::EndOfStream;
+            }
+            fed = true;
+            Input::Packets {
+                buffers: input.view(),
+                packets: FRAMES as u32,
+                descriptions: None,
+            }
+        })
+        .unwrap();
+    assert_eq!(frames as usize, FRAMES);
+    let converted = output.data::<f32>(0).unwrap();
+    assert_eq!(converted.len(), samples.len());
+    for (converted, sample) in converted.iter().zip(samples.iter()) {
+        assert_eq!(*converted, f32::from(*sample) / 32768.0);
+    }
+}
+
+#[test]
+fn test_convert_sample_rate() {
+    // 100 ms of a 1 kHz sine at half scale, in chunks of 10 ms.
+    const CHUNK: usize = 441;
+    const CHUNKS: usize = 10;
+    let source = StreamFormat::float32(44100.0, 1, true);
+    let destination = StreamFormat::float32(48000.0, 1, true);
+    let chunks: Vec<BufferList> = (0..CHUNKS)
+        .map(|c| {
+            let mut chunk = BufferList::allocate(&source, CHUNK);
+            for (i, sample) in chunk.data_mut::<f32>(0).unwrap().iter_mut().enumerate() {
+                let t = (c * CHUNK + i) as f32 / 44100.0;
+                *sample = 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin();
+            }
+            chunk
+        })
+        .collect();
+
+    let mut converter = AudioConverter::new(&source, &destination).unwrap();
+    let mut output = converter.allocate_output(512).unwrap();
+    let mut converted = Vec::new();
+    let mut next = 0;
+    loop {
+        // Each chunk is dropped from the converter's view once `fill` returns.
+        let frames = converter
+            .fill(&mut output, 512, None, |_| match chunks.get(next) {
+                Some(chunk) => {
+                    next += 1;
+                    Input::Packets {
+                        buffers: chunk.view(),
+                        packets: CHUNK as u32,
+                        descriptions: None,
+                    }
+                }
+                None => Input::EndOfStream,
+            })
+            .unwrap() as usize;
+        if frames == 0 {
+            break;
+        }
+        converted.extend_from_slice(&output.data::<f32>(0).unwrap()[..frames]);
+    }
+
+    // The converter may hold back or pad a few frames, e.g., for its filter.
+    assert!((4700..=4900).contains(&converted.len()));
+    let steady = &converted[1000..4000];
+    let peak = steady.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+    assert!((0.45..=0.55).contains(&peak));
+}
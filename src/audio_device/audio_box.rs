@@ -0,0 +1,209 @@
+use super::super::sys::{kAudioObjectUnknown, AudioObjectID, OSStatus};
+use super::audio_object::AudioObject;
+use super::clock::ClockDevice;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, SystemDevice};
+
+// A box, i.e., a piece of hardware that may host devices. A box's devices are only published
+// while the box is acquired.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioBox(AudioObject);
+
+impl AudioBox {
+    pub(crate) fn new(id: AudioObjectID) -> Self {
+        Self(AudioObject::new(id))
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
+    pub fn uid(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::BoxUID, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn name(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::DeviceName, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn manufacturer(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::DeviceManufacturer, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn transport_type(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::BoxTransportType, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    pub fn has_audio(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::BoxHasAudio)
+    }
+
+    pub fn has_video(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::BoxHasVideo)
+    }
+
+    pub fn has_midi(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::BoxHasMIDI)
+    }
+
+    // Whether the box requires authentication to use.
+    pub fn is_protected(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::BoxIsProtected)
+    }
+
+    pub fn is_acquired(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::BoxAcquired)
+    }
+
+    // Acquiring a box publishes its devices to every process.
+    pub fn set_acquired(&self, acquired: bool) -> Result<(), OSStatus> {
+        let address = get_property_address(Property::BoxAcquired, Scope::Global);
+        self.0
+            .set_property_data_common(&address, &u32::from(acquired))
+    }
+
+    // Whether the last attempt to acquire the box failed.
+    pub fn acquisition_failed(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::BoxAcquisitionFailed)
+    }
+
+    pub fn devices(&self) -> Result<Vec<Device>, OSStatus> {
+        let address = get_property_address(Property::BoxDeviceList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(Device::new).collect())
+    }
+
+    pub fn clock_devices(&self) -> Result<Vec<ClockDevice>, OSStatus> {
+        let address = get_property_address(Property::BoxClockDeviceList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(ClockDevice::new).collect())
+    }
+
+    fn get_bool(&self, property: Property) -> Result<bool, OSStatus> {
+        let address = get_property_address(property, Scope::Global);
+        self.0
+            .get_property_data_common::<u32>(&address)
+            .map(|value| value != 0)
+    }
+}
+
+impl SystemDevice {
+    pub fn boxes(&self) -> Result<Vec<AudioBox>, OSStatus> {
+        let address = get_property_address(Property::BoxList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(AudioBox::new).collect())
+    }
+
+    pub fn box_for_uid(&self, uid: &str) -> Result<Option<AudioBox>, OSStatus> {
+        let address = get_property_address(Property::TranslateUIDToBox, Scope::Global);
+        self.0
+            .get_property_data_with_string_qualifier::<AudioObjectID>(&address, uid)
+            .map(|id| {
+                if id == kAudioObjectUnknown {
+                    None
+                } else {
+                    Some(AudioBox::new(id))
+                }
+            })
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_plug_ins_and_boxes() {
+    use super::super::sys::kAudioObjectSystemObject;
+    use super::fixture::Fixture;
+    use super::PlugIn;
+
+    const PLUG_IN: u32 = 100;
+    const BOX: u32 = 101;
+    const DEVICE: u32 = 102;
+    const SYSTEM: u32 = kAudioObjectSystemObject;
+
+    let mut fixture = Fixture::default();
+    let global = |property| get_property_address(property, Scope::Global);
+    fixture.insert_data(SYSTEM, &global(Property::PlugInList), &[PLUG_IN]);
+    fixture.insert_data(SYSTEM, &global(Property::BoxList), &[BOX]);
+    fixture.insert_string_qualified_data(
+        SYSTEM,
+        &global(Property::TranslateBundleIDToPlugIn),
+        "com.example.VirtualDriver",
+        &[PLUG_IN],
+    );
+    fixture.insert_string_qualified_data(
+        SYSTEM,
+        &global(Property::TranslateBundleIDToPlugIn),
+        "com.example.Missing",
+        &[kAudioObjectUnknown],
+    );
+    fixture.insert_string_qualified_data(
+        SYSTEM,
+        &global(Property::TranslateUIDToBox),
+        "box",
+        &[BOX],
+    );
+    fixture.insert_string_qualified_data(
+        SYSTEM,
+        &global(Property::TranslateUIDToDevice),
+        "device",
+        &[DEVICE],
+    );
+    fixture.insert_string(
+        PLUG_IN,
+        &global(Property::PlugInBundleID),
+        "com.example.VirtualDriver",
+    );
+    fixture.insert_data(PLUG_IN, &global(Property::PlugInBoxList), &[BOX]);
+    fixture.insert_data(BOX, &global(Property::BoxDeviceList), &[DEVICE]);
+    for (property, value) in [
+        (Property::BoxHasAudio, 1_u32),
+        (Property::BoxHasVideo, 0),
+        (Property::BoxHasMIDI, 1),
+        (Property::BoxAcquired, 0),
+    ]
+    .iter()
+    {
+        fixture.insert_data(BOX, &global(*property), &[*value]);
+    }
+
+    fixture.replay(|| {
+        let system = SystemDevice::default();
+        let plug_ins = system.plug_ins().unwrap();
+        assert_eq!(plug_ins, vec![PlugIn::new(PLUG_IN)]);
+        assert_eq!(
+            plug_ins[0].bundle_id().unwrap(),
+            "com.example.VirtualDriver"
+        );
+        assert_eq!(
+            system
+                .plug_in_for_bundle_id("com.example.VirtualDriver")
+                .unwrap(),
+            Some(PlugIn::new(PLUG_IN))
+        );
+        assert_eq!(
+            system.plug_in_for_bundle_id("com.example.Missing").unwrap(),
+            None
+        );
+
+        let audio_box = system.box_for_uid("box").unwrap().unwrap();
+        assert_eq!(system.boxes().unwrap(), vec![audio_box]);
+        assert_eq!(plug_ins[0].boxes().unwrap(), vec![audio_box]);
+        assert!(audio_box.has_audio().unwrap());
+        assert!(!audio_box.has_video().unwrap());
+        assert!(audio_box.has_midi().unwrap());
+        assert!(!audio_box.is_acquired().unwrap());
+        audio_box.set_acquired(true).unwrap();
+        assert!(audio_box.is_acquired().unwrap());
+        assert_eq!(
+            audio_box.devices().unwrap(),
+            vec![system.device_for_uid("device").unwrap().unwrap()]
+        );
+    });
+}
@@ -1,12 +1,25 @@
-use coreaudio_sys::{
-    noErr, AudioObjectGetPropertyData, AudioObjectGetPropertyDataSize, AudioObjectID,
-    AudioObjectPropertyAddress, OSStatus, UInt32,
+use super::super::string::StringRef;
+use super::super::sys::CFStringRef;
+use super::super::sys::{
+    noErr, AudioObjectAddPropertyListener, AudioObjectGetPropertyData,
+    AudioObjectGetPropertyDataSize, AudioObjectID, AudioObjectPropertyAddress,
+    AudioObjectPropertyListenerProc, AudioObjectRemovePropertyListener, AudioObjectSetPropertyData,
+    AudioValueTranslation, OSStatus, UInt32,
 };
+#[cfg(feature = "fixture")]
+use super::fixture;
 use std::mem;
 use std::os::raw::c_void;
+use std::ptr;
 
 const NO_ERR: OSStatus = noErr as OSStatus;
 
+// A property value that is plain data, e.g., a number or a `repr(C)` struct without pointers.
+pub struct Plain<D>(pub D);
+
+// Only the ID of an object. It's `Send` and `Sync` because the HAL's property API can be called
+// from any thread, and objects that go away fail their queries rather than being freed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct AudioObject(AudioObjectID);
 impl AudioObject {
     pub fn new(id: AudioObjectID) -> Self {
@@ -17,7 +30,7 @@ impl AudioObject {
         self.0
     }
 
-    pub fn get_property_data<Q, D>(
+    pub(crate) fn get_property_data<Q, D>(
         &self,
         address: &AudioObjectPropertyAddress,
         in_qualifier_data_size: usize,
@@ -35,7 +48,7 @@ impl AudioObject {
         )
     }
 
-    pub fn get_property_data_size<Q>(
+    pub(crate) fn get_property_data_size<Q>(
         &self,
         address: &AudioObjectPropertyAddress,
         in_qualifier_data_size: usize,
@@ -51,9 +64,53 @@ impl AudioObject {
         )
     }
 
+    pub(crate) fn set_property_data<Q, D>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        in_qualifier_data_size: usize,
+        in_qualifier_data: *const Q,
+        in_data_size: usize,
+        in_data: *const D,
+    ) -> OSStatus {
+        audio_object_set_property_data(
+            self.0,
+            address,
+            in_qualifier_data_size,
+            in_qualifier_data,
+            in_data_size,
+            in_data,
+        )
+    }
+
+    pub(crate) fn add_property_listener(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        listener: AudioObjectPropertyListenerProc,
+        client_data: *mut c_void,
+    ) -> OSStatus {
+        #[cfg(feature = "fixture")]
+        if let Some(status) = fixture::add_listener(self.0, address, listener, client_data) {
+            return status;
+        }
+        unsafe { AudioObjectAddPropertyListener(self.0, address, listener, client_data) }
+    }
+
+    pub(crate) fn remove_property_listener(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        listener: AudioObjectPropertyListenerProc,
+        client_data: *mut c_void,
+    ) -> OSStatus {
+        #[cfg(feature = "fixture")]
+        if let Some(status) = fixture::remove_listener(self.0, address, listener, client_data) {
+            return status;
+        }
+        unsafe { AudioObjectRemovePropertyListener(self.0, address, listener, client_data) }
+    }
+
     // Frequently used utils:
 
-    pub fn get_property_data_without_qualifier<D>(
+    pub(crate) fn get_property_data_without_qualifier<D>(
         &self,
         address: &AudioObjectPropertyAddress,
         io_data_size: *mut usize,
@@ -76,43 +133,261 @@ impl AudioObject {
         self.get_property_data_size(address, 0, std::ptr::null_mut::<c_void>(), out_data_size)
     }
 
-    pub fn get_property_data_common<D: Default + Sized>(
+    // The `Copy` bound restricts these to plain data, so the values can be recorded to
+    // and replayed from a fixture as raw bytes.
+    pub(crate) fn get_property_data_common<D: Copy + Default>(
         &self,
         address: &AudioObjectPropertyAddress,
     ) -> Result<D, OSStatus> {
-        let mut data = D::default();
-        let mut size = mem::size_of::<D>();
-        let status = self.get_property_data_without_qualifier(address, &mut size, &mut data);
-        if status == NO_ERR {
-            Ok(data)
-        } else {
-            Err(status)
-        }
+        self.intercept(address, &[], || {
+            let mut data = D::default();
+            let mut size = mem::size_of::<D>();
+            let status = self.get_property_data_without_qualifier(address, &mut size, &mut data);
+            if status == NO_ERR {
+                Ok(Plain(data))
+            } else {
+                Err(status)
+            }
+        })
+        .map(|Plain(data)| data)
+    }
+
+    // For properties that convert a value in place, e.g., a scalar volume into decibels.
+    pub(crate) fn get_property_data_in_place<D: Copy + Default>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        input: D,
+    ) -> Result<D, OSStatus> {
+        let qualifier = as_bytes(&input).to_vec();
+        self.intercept(address, &qualifier, || {
+            let mut data = input;
+            let mut size = mem::size_of::<D>();
+            let status = self.get_property_data_without_qualifier(address, &mut size, &mut data);
+            if status == NO_ERR {
+                Ok(Plain(data))
+            } else {
+                Err(status)
+            }
+        })
+        .map(|Plain(data)| data)
     }
 
-    pub fn get_property_array_common<D: Clone + Default + Sized>(
+    pub(crate) fn get_property_array_common<D: Copy + Default>(
         &self,
         address: &AudioObjectPropertyAddress,
     ) -> Result<Vec<D>, OSStatus> {
-        let mut size = 0;
-        let status = self.get_property_data_size_without_qualifier(address, &mut size);
-        if status != NO_ERR {
-            return Err(status);
-        }
+        self.intercept(address, &[], || {
+            let mut size = 0;
+            let status = self.get_property_data_size_without_qualifier(address, &mut size);
+            if status != NO_ERR {
+                return Err(status);
+            }
+
+            let element_size = mem::size_of::<D>();
+            assert_eq!(size % element_size, 0);
+            let elements = size / element_size;
+            let mut buffer = vec![D::default(); elements];
 
-        let element_size = mem::size_of::<D>();
-        assert_eq!(size % element_size, 0);
-        let elements = size / element_size;
-        let mut buffer = vec![D::default(); elements];
+            let status =
+                self.get_property_data_without_qualifier(address, &mut size, buffer.as_mut_ptr());
+            if status == NO_ERR {
+                Ok(buffer)
+            } else {
+                Err(status)
+            }
+        })
+    }
 
-        let status =
-            self.get_property_data_without_qualifier(address, &mut size, buffer.as_mut_ptr());
-        if status == NO_ERR {
-            Ok(buffer)
+    // Like `get_property_array_common`, for properties whose value depends on a qualifier.
+    pub(crate) fn get_property_array_with_qualifier<Q: Copy, D: Copy + Default>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[Q],
+    ) -> Result<Vec<D>, OSStatus> {
+        let qualifier_size = mem::size_of_val(qualifier);
+        let qualifier_data = if qualifier.is_empty() {
+            ptr::null_mut()
         } else {
-            Err(status)
-        }
+            qualifier.as_ptr() as *mut Q
+        };
+        let key =
+            unsafe { std::slice::from_raw_parts(qualifier.as_ptr() as *const u8, qualifier_size) };
+        self.intercept(address, key, || {
+            let mut size = 0;
+            let status =
+                self.get_property_data_size(address, qualifier_size, qualifier_data, &mut size);
+            if status != NO_ERR {
+                return Err(status);
+            }
+
+            let element_size = mem::size_of::<D>();
+            assert_eq!(size % element_size, 0);
+            let mut buffer = vec![D::default(); size / element_size];
+            if buffer.is_empty() {
+                return Ok(buffer);
+            }
+
+            let status = self.get_property_data(
+                address,
+                qualifier_size,
+                qualifier_data,
+                &mut size,
+                buffer.as_mut_ptr(),
+            );
+            if status == NO_ERR {
+                buffer.truncate(size / element_size);
+                Ok(buffer)
+            } else {
+                Err(status)
+            }
+        })
     }
+
+    pub(crate) fn get_property_string(
+        &self,
+        address: &AudioObjectPropertyAddress,
+    ) -> Result<String, OSStatus> {
+        self.intercept(address, &[], || {
+            let mut string: CFStringRef = ptr::null();
+            let mut size = mem::size_of::<CFStringRef>();
+            let status = self.get_property_data_without_qualifier(address, &mut size, &mut string);
+            // The HAL hands over a reference to an immutable string, or leaves it null.
+            let string = unsafe { StringRef::new(string) };
+            if status == NO_ERR {
+                Ok(String::from_utf8_lossy(&string.to_utf8()).to_string())
+            } else {
+                Err(status)
+            }
+        })
+    }
+
+    // Translate `input` into a string via an `AudioValueTranslation`, e.g., a data source ID
+    // into its name.
+    pub(crate) fn get_property_translated_string<I: Copy>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        input: I,
+    ) -> Result<String, OSStatus> {
+        let mut input = input;
+        let qualifier = as_bytes(&input).to_vec();
+        self.intercept(address, &qualifier, || {
+            let mut string: CFStringRef = ptr::null();
+            let mut translation = AudioValueTranslation {
+                mInputData: &mut input as *mut I as *mut c_void,
+                mInputDataSize: mem::size_of::<I>() as u32,
+                mOutputData: &mut string as *mut CFStringRef as *mut c_void,
+                mOutputDataSize: mem::size_of::<CFStringRef>() as u32,
+            };
+            let mut size = mem::size_of::<AudioValueTranslation>();
+            let status =
+                self.get_property_data_without_qualifier(address, &mut size, &mut translation);
+            // The HAL hands over a reference to an immutable string, or leaves it null.
+            let string = unsafe { StringRef::new(string) };
+            if status == NO_ERR {
+                Ok(String::from_utf8_lossy(&string.to_utf8()).to_string())
+            } else {
+                Err(status)
+            }
+        })
+    }
+
+    // For properties that translate a string into a value, e.g., a UID into a device. The HAL
+    // takes the string as a `CFString` qualifier, which fixtures key by its UTF-8 bytes.
+    pub(crate) fn get_property_data_with_string_qualifier<D: Copy + Default>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &str,
+    ) -> Result<D, OSStatus> {
+        self.intercept(address, qualifier.as_bytes(), || {
+            let string = StringRef::from(qualifier);
+            let mut string_ref = string.as_concrete_type_ref();
+            let mut data = D::default();
+            let mut size = mem::size_of::<D>();
+            let status = self.get_property_data(
+                address,
+                mem::size_of::<CFStringRef>(),
+                &mut string_ref,
+                &mut size,
+                &mut data,
+            );
+            if status == NO_ERR {
+                Ok(Plain(data))
+            } else {
+                Err(status)
+            }
+        })
+        .map(|Plain(data)| data)
+    }
+
+    pub(crate) fn set_property_data_common<D: Copy>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        data: &D,
+    ) -> Result<(), OSStatus> {
+        self.set_property_array_common(address, std::slice::from_ref(data))
+    }
+
+    pub(crate) fn set_property_array_common<D: Copy>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        data: &[D],
+    ) -> Result<(), OSStatus> {
+        let size = mem::size_of_val(data);
+        let bytes = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, size) };
+        self.intercept_set(address, bytes, || {
+            let status =
+                self.set_property_data(address, 0, ptr::null::<c_void>(), size, data.as_ptr());
+            if status == NO_ERR {
+                Ok(())
+            } else {
+                Err(status)
+            }
+        })
+    }
+
+    #[cfg(feature = "fixture")]
+    fn intercept<T: fixture::Recordable>(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[u8],
+        live: impl FnOnce() -> Result<T, OSStatus>,
+    ) -> Result<T, OSStatus> {
+        fixture::intercept(self.0, address, qualifier, live)
+    }
+
+    #[cfg(not(feature = "fixture"))]
+    fn intercept<T>(
+        &self,
+        _address: &AudioObjectPropertyAddress,
+        _qualifier: &[u8],
+        live: impl FnOnce() -> Result<T, OSStatus>,
+    ) -> Result<T, OSStatus> {
+        live()
+    }
+
+    #[cfg(feature = "fixture")]
+    fn intercept_set(
+        &self,
+        address: &AudioObjectPropertyAddress,
+        data: &[u8],
+        live: impl FnOnce() -> Result<(), OSStatus>,
+    ) -> Result<(), OSStatus> {
+        fixture::intercept_set(self.0, address, data, live)
+    }
+
+    #[cfg(not(feature = "fixture"))]
+    fn intercept_set(
+        &self,
+        _address: &AudioObjectPropertyAddress,
+        _data: &[u8],
+        live: impl FnOnce() -> Result<(), OSStatus>,
+    ) -> Result<(), OSStatus> {
+        live()
+    }
+}
+
+fn as_bytes<T: Copy>(data: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data as *const T as *const u8, mem::size_of::<T>()) }
 }
 
 fn audio_object_get_property_data<Q, D>(
@@ -163,3 +438,29 @@ fn audio_object_get_property_data_size<Q>(
         )
     }
 }
+
+fn audio_object_set_property_data<Q, D>(
+    in_object_id: AudioObjectID,
+    in_address: &AudioObjectPropertyAddress,
+    in_qualifier_data_size: usize,
+    in_qualifier_data: *const Q,
+    in_data_size: usize,
+    in_data: *const D,
+) -> OSStatus {
+    assert!(
+        (in_qualifier_data.is_null() && in_qualifier_data_size == 0)
+            || (!in_qualifier_data.is_null() && in_qualifier_data_size >= mem::size_of::<Q>())
+    );
+    assert!(!in_data.is_null());
+    assert!(in_data_size >= mem::size_of::<D>());
+    unsafe {
+        AudioObjectSetPropertyData(
+            in_object_id,
+            in_address,
+            in_qualifier_data_size as UInt32,
+            in_qualifier_data as *const c_void,
+            in_data_size as UInt32,
+            in_data as *const c_void,
+        )
+    }
+}
@@ -0,0 +1,163 @@
+use super::super::sys::OSStatus;
+use super::audio_object::AudioObject;
+use super::property_address::{
+    get_element_property_address, get_property_address, Property, Scope,
+};
+use super::{Device, Side, StreamId};
+
+// A channel of a device in one scope.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    // The 1-based channel number in the device, i.e., the element of the channel's properties.
+    pub index: u32,
+    // The name of the channel, e.g., "Mic 1", if the device provides one.
+    pub name: Option<String>,
+    // The name of the channel's category, e.g., "ADAT", if the device provides one.
+    pub category_name: Option<String>,
+    // The name of the channel's number within its category, e.g., "3", if the device provides one.
+    pub number_name: Option<String>,
+    // The stream carrying the channel.
+    pub stream: StreamId,
+    // The 1-based channel number in the device of the stream's first channel.
+    pub starting_channel: u32,
+}
+
+impl Channel {
+    // The 0-based position of the channel in its stream.
+    pub fn channel_in_stream(&self) -> u32 {
+        self.index - self.starting_channel
+    }
+}
+
+impl Device {
+    pub fn channels(&self, s: &Side) -> Result<Vec<Channel>, OSStatus> {
+        let streams = self.streams(s)?;
+        let buffers = self.stream_configuration(s)?;
+
+        // Each stream of the device in this scope has one buffer in the stream configuration, in
+        // the same order.
+        let mut channels = Vec::new();
+        let mut next_channel = 1;
+        for (stream, buffer) in streams.iter().zip(buffers.buffers()) {
+            let address = get_property_address(Property::StreamStartingChannel, Scope::Global);
+            let starting_channel = AudioObject::new(*stream)
+                .get_property_data_common::<u32>(&address)
+                .unwrap_or(next_channel);
+            for index in starting_channel..starting_channel + buffer.mNumberChannels {
+                channels.push(Channel {
+                    index,
+                    name: self.element_name(Property::ElementName, s, index),
+                    category_name: self.element_name(Property::ElementCategoryName, s, index),
+                    number_name: self.element_name(Property::ElementNumberName, s, index),
+                    stream: StreamId(*stream),
+                    starting_channel,
+                });
+            }
+            next_channel = starting_channel + buffer.mNumberChannels;
+        }
+        Ok(channels)
+    }
+
+    // Devices aren't required to name their channels, so a missing name is not an error.
+    fn element_name(&self, property: Property, s: &Side, element: u32) -> Option<String> {
+        let address = get_element_property_address(property, Scope::from(s), element);
+        self.0
+            .get_property_string(&address)
+            .ok()
+            .filter(|name| !name.is_empty())
+    }
+}
+
+// Needs the HAL.
+#[cfg(target_os = "macos")]
+#[test]
+fn test_channels_match_channel_count() {
+    use super::SystemDevice;
+    let system_device = SystemDevice::default();
+    for s in &[Side::Input, Side::Output] {
+        if let Ok(device) = system_device.get_default_device(s) {
+            if device.is_valid() {
+                let channels = device.channels(s).unwrap();
+                assert_eq!(channels.len() as u32, device.channel_count(s).unwrap());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_channels() {
+    use super::super::sys::kAudioHardwareUnknownPropertyError;
+    use super::fixture::Fixture;
+    use super::SystemDevice;
+
+    const DEVICE: u32 = 50;
+    const STREAMS: [u32; 2] = [51, 52];
+    let mut fixture = Fixture::default();
+    fixture.insert_data(
+        1,
+        &get_property_address(Property::DefaultInputDevice, Scope::Global),
+        &[DEVICE],
+    );
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::DeviceStreams, Scope::Input),
+        &STREAMS,
+    );
+    fixture.insert_buffer_list(
+        DEVICE,
+        &get_property_address(Property::DeviceStreamConfiguration, Scope::Input),
+        &[2, 1],
+    );
+    fixture.insert_data(
+        STREAMS[0],
+        &get_property_address(Property::StreamStartingChannel, Scope::Global),
+        &[1_u32],
+    );
+    // The second stream doesn't report its starting channel, so it follows the first one.
+    fixture.insert_error(
+        STREAMS[1],
+        &get_property_address(Property::StreamStartingChannel, Scope::Global),
+        kAudioHardwareUnknownPropertyError as OSStatus,
+    );
+    for (channel, name) in [(1, "Mic 1"), (3, "ADAT 1")].iter() {
+        fixture.insert_string(
+            DEVICE,
+            &get_element_property_address(Property::ElementName, Scope::Input, *channel),
+            name,
+        );
+    }
+    fixture.insert_string(
+        DEVICE,
+        &get_element_property_address(Property::ElementCategoryName, Scope::Input, 3),
+        "ADAT",
+    );
+    fixture.insert_string(
+        DEVICE,
+        &get_element_property_address(Property::ElementNumberName, Scope::Input, 3),
+        "1",
+    );
+
+    fixture.replay(|| {
+        let device = SystemDevice::default()
+            .get_default_device(&Side::Input)
+            .unwrap();
+        let channels = device.channels(&Side::Input).unwrap();
+        assert_eq!(channels.len(), 3);
+
+        assert_eq!(channels[0].index, 1);
+        assert_eq!(channels[0].name.as_deref(), Some("Mic 1"));
+        assert_eq!(channels[0].stream, StreamId(STREAMS[0]));
+        assert_eq!(channels[1].index, 2);
+        assert_eq!(channels[1].name, None);
+        assert_eq!(channels[1].channel_in_stream(), 1);
+
+        assert_eq!(channels[2].index, 3);
+        assert_eq!(channels[2].name.as_deref(), Some("ADAT 1"));
+        assert_eq!(channels[2].category_name.as_deref(), Some("ADAT"));
+        assert_eq!(channels[2].number_name.as_deref(), Some("1"));
+        assert_eq!(channels[2].stream, StreamId(STREAMS[1]));
+        assert_eq!(channels[2].starting_channel, 3);
+        assert_eq!(channels[2].channel_in_stream(), 0);
+    });
+}
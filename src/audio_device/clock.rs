@@ -0,0 +1,350 @@
+use super::super::sys::{kAudioObjectUnknown, AudioObjectID, AudioValueRange, OSStatus};
+use super::audio_object::AudioObject;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, DeviceId, Side, SystemDevice};
+
+// A clock device, i.e., a clock source that isn't an audio device, e.g., a word clock input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockDevice(AudioObject);
+
+impl ClockDevice {
+    pub(crate) fn new(id: AudioObjectID) -> Self {
+        Self(AudioObject::new(id))
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
+    pub fn uid(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceUID, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn name(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::DeviceName, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn transport_type(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceTransportType, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    // Devices and clocks with the same non-zero domain are driven by the same clock.
+    pub fn clock_domain(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceClockDomain, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    pub fn is_alive(&self) -> Result<bool, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceIsAlive, Scope::Global);
+        self.0
+            .get_property_data_common::<u32>(&address)
+            .map(|value| value != 0)
+    }
+
+    pub fn is_running(&self) -> Result<bool, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceIsRunning, Scope::Global);
+        self.0
+            .get_property_data_common::<u32>(&address)
+            .map(|value| value != 0)
+    }
+
+    pub fn latency(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceLatency, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    pub fn sample_rate(&self) -> Result<f64, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceSampleRate, Scope::Global);
+        self.0.get_property_data_common::<f64>(&address)
+    }
+
+    pub fn sample_rate_ranges(&self) -> Result<Vec<(f64, f64)>, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceSampleRates, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioValueRange>(&address)
+            .map(|ranges| {
+                ranges
+                    .into_iter()
+                    .map(|r| (r.mMinimum, r.mMaximum))
+                    .collect()
+            })
+    }
+}
+
+// How the clocks of an input and an output device relate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockRelation {
+    // Both sides of one device, which run from the device's clock.
+    SameDevice,
+    // Two devices in the same clock domain.
+    SharedClock(u32),
+    // Two devices in different clock domains, which drift apart.
+    Independent { input: u32, output: u32 },
+    // At least one device doesn't report its clock domain, so it must be assumed independent.
+    Unknown,
+}
+
+impl ClockRelation {
+    pub fn new(input: (DeviceId, u32), output: (DeviceId, u32)) -> Self {
+        match (input, output) {
+            ((i, _), (o, _)) if i == o => ClockRelation::SameDevice,
+            ((_, 0), _) | (_, (_, 0)) => ClockRelation::Unknown,
+            ((_, i), (_, o)) if i == o => ClockRelation::SharedClock(i),
+            ((_, i), (_, o)) => ClockRelation::Independent {
+                input: i,
+                output: o,
+            },
+        }
+    }
+
+    // Whether an aggregate of the two devices needs drift compensation on one of them.
+    pub fn needs_drift_compensation(&self) -> bool {
+        match self {
+            ClockRelation::SameDevice | ClockRelation::SharedClock(_) => false,
+            ClockRelation::Independent { .. } | ClockRelation::Unknown => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClockDomainGroup {
+    pub domain: u32,
+    pub devices: Vec<Device>,
+}
+
+// The devices grouped by the clock that drives them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClockDomains {
+    // The groups sorted by domain.
+    pub groups: Vec<ClockDomainGroup>,
+    // The devices in domain 0, each of which may have a clock of its own.
+    pub unknown: Vec<Device>,
+}
+
+impl ClockDomains {
+    fn new(devices: Vec<(Device, u32)>) -> Self {
+        let mut domains = Self::default();
+        for (device, domain) in devices {
+            if domain == 0 {
+                domains.unknown.push(device);
+                continue;
+            }
+            match domains
+                .groups
+                .binary_search_by_key(&domain, |group| group.domain)
+            {
+                Ok(i) => domains.groups[i].devices.push(device),
+                Err(i) => domains.groups.insert(
+                    i,
+                    ClockDomainGroup {
+                        domain,
+                        devices: vec![device],
+                    },
+                ),
+            }
+        }
+        domains
+    }
+
+    pub fn group(&self, domain: u32) -> Option<&ClockDomainGroup> {
+        self.groups.iter().find(|group| group.domain == domain)
+    }
+}
+
+impl SystemDevice {
+    // A device whose domain can't be read, e.g., one being unplugged, is in the unknown domain.
+    pub fn devices_by_clock_domain(&self) -> Result<ClockDomains, OSStatus> {
+        let devices = self
+            .get_all_devices()?
+            .into_iter()
+            .map(|device| (device, device.domain().unwrap_or(0)))
+            .collect();
+        Ok(ClockDomains::new(devices))
+    }
+
+    pub fn clock_relation(
+        &self,
+        input: &Device,
+        output: &Device,
+    ) -> Result<ClockRelation, OSStatus> {
+        Ok(ClockRelation::new(
+            (input.id(), input.clock_domain(&Side::Input)?),
+            (output.id(), output.clock_domain(&Side::Output)?),
+        ))
+    }
+
+    pub fn clock_devices(&self) -> Result<Vec<ClockDevice>, OSStatus> {
+        let address = get_property_address(Property::ClockDeviceList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(ClockDevice::new).collect())
+    }
+
+    pub fn clock_device_for_uid(&self, uid: &str) -> Result<Option<ClockDevice>, OSStatus> {
+        let address = get_property_address(Property::TranslateUIDToClockDevice, Scope::Global);
+        self.0
+            .get_property_data_with_string_qualifier::<AudioObjectID>(&address, uid)
+            .map(|id| {
+                if id == kAudioObjectUnknown {
+                    None
+                } else {
+                    Some(ClockDevice::new(id))
+                }
+            })
+    }
+}
+
+impl Device {
+    // The clock devices in the same clock domain as this side of the device. A device whose
+    // domain is 0 doesn't say which clock it follows, so it shares a clock with none of them,
+    // and neither does a clock device whose domain can't be read.
+    pub fn clock_devices(&self, s: &Side) -> Result<Vec<ClockDevice>, OSStatus> {
+        let domain = self.clock_domain(s)?;
+        if domain == 0 {
+            return Ok(Vec::new());
+        }
+        let clocks = SystemDevice::default().clock_devices()?;
+        Ok(clocks
+            .into_iter()
+            .filter(|clock| clock.clock_domain() == Ok(domain))
+            .collect())
+    }
+
+    // The clock domain of the device, read from the first side it has streams on.
+    fn domain(&self) -> Result<u32, OSStatus> {
+        for side in &[Side::Output, Side::Input] {
+            if self.in_scope(side)? {
+                return self.clock_domain(side);
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[test]
+fn test_clock_relation() {
+    let (a, b) = (DeviceId(1), DeviceId(2));
+    assert_eq!(
+        ClockRelation::new((a, 0), (a, 0)),
+        ClockRelation::SameDevice
+    );
+    assert_eq!(
+        ClockRelation::new((a, 5), (b, 5)),
+        ClockRelation::SharedClock(5)
+    );
+    assert_eq!(
+        ClockRelation::new((a, 5), (b, 6)),
+        ClockRelation::Independent {
+            input: 5,
+            output: 6
+        }
+    );
+    assert_eq!(ClockRelation::new((a, 0), (b, 0)), ClockRelation::Unknown);
+    assert!(!ClockRelation::SharedClock(5).needs_drift_compensation());
+    assert!(ClockRelation::Unknown.needs_drift_compensation());
+
+    let devices = [1, 2, 3, 4, 5]
+        .iter()
+        .map(|id| Device::new(*id))
+        .collect::<Vec<_>>();
+    let domains = ClockDomains::new(
+        devices
+            .iter()
+            .copied()
+            .zip([9_u32, 0, 3, 9, 0].iter().copied())
+            .collect(),
+    );
+    assert_eq!(
+        domains.groups.iter().map(|g| g.domain).collect::<Vec<_>>(),
+        vec![3, 9]
+    );
+    assert_eq!(
+        domains.group(9).unwrap().devices,
+        vec![devices[0], devices[3]]
+    );
+    assert_eq!(domains.unknown, vec![devices[1], devices[4]]);
+    assert!(domains.group(1).is_none());
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_clock_devices() {
+    use super::super::sys::kAudioObjectSystemObject;
+    use super::fixture::Fixture;
+
+    const DEVICE: u32 = 110;
+    // The domain of the last clock can't be read.
+    const CLOCKS: [u32; 3] = [111, 112, 113];
+    // Nothing of this device can be read.
+    const GONE: u32 = 120;
+    let mut fixture = Fixture::default();
+    let global = |property| get_property_address(property, Scope::Global);
+    fixture.insert_data(
+        kAudioObjectSystemObject,
+        &global(Property::ClockDeviceList),
+        &CLOCKS,
+    );
+    fixture.insert_string_qualified_data(
+        kAudioObjectSystemObject,
+        &global(Property::TranslateUIDToClockDevice),
+        "word-clock",
+        &[CLOCKS[0]],
+    );
+    fixture.insert_string(CLOCKS[0], &global(Property::ClockDeviceUID), "word-clock");
+    fixture.insert_data(
+        CLOCKS[0],
+        &global(Property::ClockDeviceClockDomain),
+        &[7_u32],
+    );
+    fixture.insert_data(
+        CLOCKS[1],
+        &global(Property::ClockDeviceClockDomain),
+        &[8_u32],
+    );
+    fixture.insert_data(CLOCKS[0], &global(Property::ClockDeviceIsAlive), &[1_u32]);
+    fixture.insert_data(
+        CLOCKS[0],
+        &global(Property::ClockDeviceSampleRate),
+        &[48000.0_f64],
+    );
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::ClockDomain, Scope::Input),
+        &[7_u32],
+    );
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::ClockDomain, Scope::Output),
+        &[0_u32],
+    );
+    fixture.insert_data(
+        kAudioObjectSystemObject,
+        &global(Property::Devices),
+        &[DEVICE, GONE],
+    );
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::DeviceStreams, Scope::Output),
+        &[130_u32],
+    );
+
+    fixture.replay(|| {
+        let system = SystemDevice::default();
+        let clock = system.clock_device_for_uid("word-clock").unwrap().unwrap();
+        assert_eq!(clock.uid().unwrap(), "word-clock");
+        assert!(clock.is_alive().unwrap());
+        assert_eq!(clock.sample_rate().unwrap(), 48000.0);
+        assert_eq!(system.clock_devices().unwrap().len(), 3);
+
+        let device = Device::new(DEVICE);
+        assert_eq!(device.clock_devices(&Side::Input).unwrap(), vec![clock]);
+        assert!(device.clock_devices(&Side::Output).unwrap().is_empty());
+
+        let domains = system.devices_by_clock_domain().unwrap();
+        assert!(domains.groups.is_empty());
+        assert_eq!(domains.unknown, vec![device, Device::new(GONE)]);
+    });
+}
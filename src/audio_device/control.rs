@@ -0,0 +1,384 @@
+use super::super::sys::{
+    kAudioObjectPropertyScopeInput, kAudioObjectPropertyScopeOutput, AudioObjectID,
+    AudioObjectPropertyScope, AudioValueRange, OSStatus,
+};
+use super::audio_object::AudioObject;
+use super::object::Class;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, Side};
+
+// A control of a device, e.g., a volume or a mute switch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Control(AudioObject);
+
+// A control downcast by the kind of value it controls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ControlKind {
+    Level(LevelControl),
+    Boolean(BooleanControl),
+    Selector(SelectorControl),
+    Slider(SliderControl),
+    StereoPan(StereoPanControl),
+    Other(Control),
+}
+
+impl Control {
+    pub(crate) fn new(id: AudioObjectID) -> Self {
+        Self(AudioObject::new(id))
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
+    // Controls aren't required to have a name.
+    pub fn name(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::DeviceName, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    // The side of the device the control applies to, or `None` for the whole device.
+    pub fn side(&self) -> Result<Option<Side>, OSStatus> {
+        let address = get_property_address(Property::ControlScope, Scope::Global);
+        self.0
+            .get_property_data_common::<AudioObjectPropertyScope>(&address)
+            .map(|scope| match scope {
+                s if s == kAudioObjectPropertyScopeInput => Some(Side::Input),
+                s if s == kAudioObjectPropertyScopeOutput => Some(Side::Output),
+                _ => None,
+            })
+    }
+
+    // The channel the control applies to, or 0 for the main element, i.e., all channels.
+    pub fn element(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::ControlElement, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    pub fn kind(&self) -> Result<ControlKind, OSStatus> {
+        // Concrete controls, e.g., volumes or mutes, are derived from the generic ones.
+        let class = match self.0.class()? {
+            class @ Class::LevelControl
+            | class @ Class::BooleanControl
+            | class @ Class::SelectorControl
+            | class @ Class::SliderControl
+            | class @ Class::StereoPanControl => class,
+            _ => self.0.base_class()?,
+        };
+        let control = *self;
+        Ok(match class {
+            Class::LevelControl => ControlKind::Level(LevelControl(control)),
+            Class::BooleanControl => ControlKind::Boolean(BooleanControl(control)),
+            Class::SelectorControl => ControlKind::Selector(SelectorControl(control)),
+            Class::SliderControl => ControlKind::Slider(SliderControl(control)),
+            Class::StereoPanControl => ControlKind::StereoPan(StereoPanControl(control)),
+            _ => ControlKind::Other(control),
+        })
+    }
+
+    fn get<D: Copy + Default>(&self, property: Property) -> Result<D, OSStatus> {
+        let address = get_property_address(property, Scope::Global);
+        self.0.get_property_data_common::<D>(&address)
+    }
+
+    fn set<D: Copy>(&self, property: Property, value: &D) -> Result<(), OSStatus> {
+        let address = get_property_address(property, Scope::Global);
+        self.0.set_property_data_common(&address, value)
+    }
+}
+
+// A control with a continuous value, e.g., a volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelControl(Control);
+
+impl LevelControl {
+    pub fn control(&self) -> Control {
+        self.0
+    }
+
+    // The value in [0, 1], which is how a fader should present it.
+    pub fn scalar(&self) -> Result<f32, OSStatus> {
+        self.0.get(Property::LevelControlScalarValue)
+    }
+
+    pub fn set_scalar(&self, value: f32) -> Result<(), OSStatus> {
+        self.0.set(Property::LevelControlScalarValue, &value)
+    }
+
+    pub fn decibels(&self) -> Result<f32, OSStatus> {
+        self.0.get(Property::LevelControlDecibelValue)
+    }
+
+    pub fn set_decibels(&self, value: f32) -> Result<(), OSStatus> {
+        self.0.set(Property::LevelControlDecibelValue, &value)
+    }
+
+    pub fn decibel_range(&self) -> Result<(f64, f64), OSStatus> {
+        self.0
+            .get::<AudioValueRange>(Property::LevelControlDecibelRange)
+            .map(|r| (r.mMinimum, r.mMaximum))
+    }
+
+    pub fn scalar_to_decibels(&self, scalar: f32) -> Result<f32, OSStatus> {
+        let address = get_property_address(Property::LevelControlScalarToDecibels, Scope::Global);
+        self.0.object().get_property_data_in_place(&address, scalar)
+    }
+
+    pub fn decibels_to_scalar(&self, decibels: f32) -> Result<f32, OSStatus> {
+        let address = get_property_address(Property::LevelControlDecibelsToScalar, Scope::Global);
+        self.0
+            .object()
+            .get_property_data_in_place(&address, decibels)
+    }
+}
+
+// A control that is on or off, e.g., a mute or phantom power switch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BooleanControl(Control);
+
+impl BooleanControl {
+    pub fn control(&self) -> Control {
+        self.0
+    }
+
+    pub fn value(&self) -> Result<bool, OSStatus> {
+        self.0
+            .get::<u32>(Property::BooleanControlValue)
+            .map(|value| value != 0)
+    }
+
+    pub fn set_value(&self, value: bool) -> Result<(), OSStatus> {
+        self.0.set(Property::BooleanControlValue, &u32::from(value))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectorItem {
+    pub id: u32,
+    pub name: Option<String>,
+}
+
+// A control that selects some of a list of items, e.g., a data source or a clock source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelectorControl(Control);
+
+impl SelectorControl {
+    pub fn control(&self) -> Control {
+        self.0
+    }
+
+    pub fn available_items(&self) -> Result<Vec<u32>, OSStatus> {
+        let address = get_property_address(Property::SelectorControlAvailableItems, Scope::Global);
+        self.0.object().get_property_array_common::<u32>(&address)
+    }
+
+    // The available items with their names, for presenting a menu.
+    pub fn items(&self) -> Result<Vec<SelectorItem>, OSStatus> {
+        Ok(self
+            .available_items()?
+            .into_iter()
+            .map(|id| SelectorItem {
+                id,
+                name: self.item_name(id).ok(),
+            })
+            .collect())
+    }
+
+    pub fn item_name(&self, item: u32) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::SelectorControlItemName, Scope::Global);
+        self.0
+            .object()
+            .get_property_translated_string(&address, item)
+    }
+
+    // Most selectors select a single item, but some allow several.
+    pub fn current_items(&self) -> Result<Vec<u32>, OSStatus> {
+        let address = get_property_address(Property::SelectorControlCurrentItem, Scope::Global);
+        self.0.object().get_property_array_common::<u32>(&address)
+    }
+
+    pub fn set_current_items(&self, items: &[u32]) -> Result<(), OSStatus> {
+        let address = get_property_address(Property::SelectorControlCurrentItem, Scope::Global);
+        self.0.object().set_property_array_common(&address, items)
+    }
+}
+
+// A control with an integer value in a range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SliderControl(Control);
+
+impl SliderControl {
+    pub fn control(&self) -> Control {
+        self.0
+    }
+
+    pub fn value(&self) -> Result<u32, OSStatus> {
+        self.0.get(Property::SliderControlValue)
+    }
+
+    pub fn set_value(&self, value: u32) -> Result<(), OSStatus> {
+        self.0.set(Property::SliderControlValue, &value)
+    }
+
+    pub fn range(&self) -> Result<(u32, u32), OSStatus> {
+        self.0
+            .get::<[u32; 2]>(Property::SliderControlRange)
+            .map(|[min, max]| (min, max))
+    }
+}
+
+// A control that pans a mono signal between two channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StereoPanControl(Control);
+
+impl StereoPanControl {
+    pub fn control(&self) -> Control {
+        self.0
+    }
+
+    // 0 is fully left, 0.5 is center and 1 is fully right.
+    pub fn value(&self) -> Result<f32, OSStatus> {
+        self.0.get(Property::StereoPanControlValue)
+    }
+
+    pub fn set_value(&self, value: f32) -> Result<(), OSStatus> {
+        self.0.set(Property::StereoPanControlValue, &value)
+    }
+
+    // The 1-based channel numbers of the left and right channels.
+    pub fn panning_channels(&self) -> Result<(u32, u32), OSStatus> {
+        self.0
+            .get::<[u32; 2]>(Property::StereoPanControlPanningChannels)
+            .map(|[left, right]| (left, right))
+    }
+}
+
+impl Device {
+    pub fn controls(&self) -> Result<Vec<Control>, OSStatus> {
+        let address = get_property_address(Property::ObjectControlList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(Control::new).collect())
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_controls() {
+    use super::super::sys::{
+        kAudioBooleanControlClassID, kAudioLevelControlClassID, kAudioSelectorControlClassID,
+    };
+    use super::fixture::Fixture;
+
+    const DEVICE: u32 = 90;
+    const VOLUME: u32 = 91;
+    const MUTE: u32 = 92;
+    const SOURCE: u32 = 93;
+    // 'vlme', 'mute' and 'dsrc', concrete control classes.
+    const CLASSES: [u32; 3] = [0x766c_6d65, 0x6d75_7465, 0x6473_7263];
+
+    let mut fixture = Fixture::default();
+    let global = |property| get_property_address(property, Scope::Global);
+    fixture.insert_data(
+        DEVICE,
+        &global(Property::ObjectControlList),
+        &[VOLUME, MUTE, SOURCE],
+    );
+    let bases = [
+        kAudioLevelControlClassID,
+        kAudioBooleanControlClassID,
+        kAudioSelectorControlClassID,
+    ];
+    let scopes = [
+        kAudioObjectPropertyScopeOutput,
+        kAudioObjectPropertyScopeOutput,
+        kAudioObjectPropertyScopeInput,
+    ];
+    for (i, control) in [VOLUME, MUTE, SOURCE].iter().enumerate() {
+        fixture.insert_data(*control, &global(Property::ObjectClass), &[CLASSES[i]]);
+        fixture.insert_data(*control, &global(Property::ObjectBaseClass), &[bases[i]]);
+        fixture.insert_data(*control, &global(Property::ControlScope), &[scopes[i]]);
+        fixture.insert_data(*control, &global(Property::ControlElement), &[0_u32]);
+    }
+    fixture.insert_data(
+        VOLUME,
+        &global(Property::LevelControlScalarValue),
+        &[0.5_f32],
+    );
+    fixture.insert_data(
+        VOLUME,
+        &global(Property::LevelControlDecibelRange),
+        &[AudioValueRange {
+            mMinimum: -96.0,
+            mMaximum: 0.0,
+        }],
+    );
+    fixture.insert_qualified_data(
+        VOLUME,
+        &global(Property::LevelControlScalarToDecibels),
+        &[0.5_f32],
+        &[-12.0_f32],
+    );
+    fixture.insert_data(MUTE, &global(Property::BooleanControlValue), &[0_u32]);
+    fixture.insert_data(
+        SOURCE,
+        &global(Property::SelectorControlAvailableItems),
+        &[1_u32, 2],
+    );
+    fixture.insert_data(
+        SOURCE,
+        &global(Property::SelectorControlCurrentItem),
+        &[2_u32],
+    );
+    // Only the first item is named.
+    fixture.insert_qualified_string(
+        SOURCE,
+        &global(Property::SelectorControlItemName),
+        &[1_u32],
+        "Line",
+    );
+
+    fixture.replay(|| {
+        let controls = Device::new(DEVICE).controls().unwrap();
+        assert_eq!(controls.len(), 3);
+        assert_eq!(controls[0].side().unwrap(), Some(Side::Output));
+        assert_eq!(controls[2].side().unwrap(), Some(Side::Input));
+        assert_eq!(controls[0].element().unwrap(), 0);
+
+        let volume = match controls[0].kind().unwrap() {
+            ControlKind::Level(volume) => volume,
+            kind => panic!("unexpected kind {:?}", kind),
+        };
+        assert_eq!(volume.scalar().unwrap(), 0.5);
+        assert_eq!(volume.decibel_range().unwrap(), (-96.0, 0.0));
+        assert_eq!(volume.scalar_to_decibels(0.5).unwrap(), -12.0);
+        volume.set_scalar(0.25).unwrap();
+        assert_eq!(volume.scalar().unwrap(), 0.25);
+
+        let mute = match controls[1].kind().unwrap() {
+            ControlKind::Boolean(mute) => mute,
+            kind => panic!("unexpected kind {:?}", kind),
+        };
+        assert!(!mute.value().unwrap());
+        mute.set_value(true).unwrap();
+        assert!(mute.value().unwrap());
+
+        let source = match controls[2].kind().unwrap() {
+            ControlKind::Selector(source) => source,
+            kind => panic!("unexpected kind {:?}", kind),
+        };
+        assert_eq!(source.current_items().unwrap(), vec![2]);
+        assert_eq!(
+            source.items().unwrap(),
+            vec![
+                SelectorItem {
+                    id: 1,
+                    name: Some("Line".to_string()),
+                },
+                SelectorItem { id: 2, name: None },
+            ]
+        );
+        source.set_current_items(&[1]).unwrap();
+        assert_eq!(source.current_items().unwrap(), vec![1]);
+    });
+}
@@ -0,0 +1,398 @@
+use super::super::buffer_list::{BufferListMut, BufferListRef};
+use super::super::resampler::{DriftTracker, Quality, Resampler};
+use super::super::ring_buffer::{ring_buffer, Consumer, Producer};
+use super::super::sys::{kAudioHardwareIllegalOperationError, OSStatus};
+use super::io_proc::{frame_count, read_interleaved, write_interleaved, IoProc};
+use super::listener::PropertyListener;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, Side};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// The number of frames copied between the device buffers and the ring buffer at once, so the IO
+// procs don't allocate.
+const CHUNK_FRAMES: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DuplexOptions {
+    // The audio kept buffered between capturing and rendering it, on top of the devices' own
+    // latencies. It's raised to what the devices' buffer sizes need.
+    pub target_latency_ms: f64,
+    // Used when the devices have different clocks or sample rates.
+    pub quality: Quality,
+}
+
+impl Default for DuplexOptions {
+    fn default() -> Self {
+        Self {
+            target_latency_ms: 10.0,
+            quality: Quality::Medium,
+        }
+    }
+}
+
+// What a `DuplexStream` has done since it was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DuplexStats {
+    // The input frames buffered at the start of the last output cycle.
+    pub buffered_frames: usize,
+    // The output frames rendered per input frame in the last output cycle.
+    pub ratio: f64,
+    // Input cycles that didn't fit in the buffer, and whose excess frames were dropped.
+    pub overruns: usize,
+    // Output cycles that ran out of input, after which the buffer is refilled to its target
+    // before rendering resumes.
+    pub underruns: usize,
+    // Processor overloads reported by either device, i.e., IO cycles that missed their deadline.
+    pub overloads: usize,
+}
+
+impl DuplexStats {
+    pub fn xruns(&self) -> usize {
+        self.overruns + self.underruns + self.overloads
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    buffered_frames: AtomicUsize,
+    ratio: AtomicU64,
+    overruns: AtomicUsize,
+    underruns: AtomicUsize,
+    overloads: AtomicUsize,
+}
+
+impl Counters {
+    fn stats(&self) -> DuplexStats {
+        DuplexStats {
+            buffered_frames: self.buffered_frames.load(Ordering::Relaxed),
+            ratio: f64::from_bits(self.ratio.load(Ordering::Relaxed)),
+            overruns: self.overruns.load(Ordering::Relaxed),
+            underruns: self.underruns.load(Ordering::Relaxed),
+            overloads: self.overloads.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Plays what an input device captures on an output device, e.g., to monitor a microphone. The
+// two can be the same device or different ones, whose drifting clocks are followed by resampling
+// the input at a ratio that holds the buffered audio at its target.
+pub struct DuplexStream {
+    // Dropped first, so the IO procs stop before anything else goes away.
+    io_procs: Vec<IoProc>,
+    _overload_listeners: Vec<PropertyListener>,
+    counters: Arc<Counters>,
+    channels: usize,
+    target_frames: usize,
+}
+
+impl DuplexStream {
+    // Bridge the first channels of `input`'s input streams to the first ones of `output`'s
+    // output streams. The other output channels are silent. Call `start` to start the IO.
+    pub fn new(input: &Device, output: &Device, options: &DuplexOptions) -> Result<Self, OSStatus> {
+        let channels = input
+            .channel_count(&Side::Input)?
+            .min(output.channel_count(&Side::Output)?) as usize;
+        if channels == 0 {
+            return Err(kAudioHardwareIllegalOperationError as OSStatus);
+        }
+        let input_rate = input.sample_rate(&Side::Input)?;
+        let output_rate = output.sample_rate(&Side::Output)?;
+        let same_device = input.id() == output.id();
+
+        // Both buffers have to fit between the writes and reads of the ring buffer.
+        let input_buffer = input.buffer_frame_size(&Side::Input)? as usize;
+        let output_buffer = output.buffer_frame_size(&Side::Output)? as usize;
+        let minimum =
+            input_buffer + (output_buffer as f64 * input_rate / output_rate).ceil() as usize;
+        let target_frames =
+            ((options.target_latency_ms * input_rate / 1000.0).ceil() as usize).max(minimum);
+
+        let counters = Arc::new(Counters::default());
+        let (mut capture, mut render) = bridge(
+            channels,
+            input_rate,
+            output_rate,
+            target_frames,
+            // The same device's input and output run on one clock.
+            if same_device && input_rate == output_rate {
+                None
+            } else {
+                Some(options.quality)
+            },
+            counters.clone(),
+        );
+
+        let mut io_procs = vec![];
+        if same_device {
+            io_procs.push(input.create_io_proc(move |cycle| {
+                if let Some(data) = cycle.input.as_ref() {
+                    capture.capture(data);
+                }
+                if let Some(data) = cycle.output.as_mut() {
+                    render.render(data);
+                }
+            })?);
+        } else {
+            io_procs.push(input.create_io_proc(move |cycle| {
+                if let Some(data) = cycle.input.as_ref() {
+                    capture.capture(data);
+                }
+            })?);
+            io_procs.push(output.create_io_proc(move |cycle| {
+                if let Some(data) = cycle.output.as_mut() {
+                    render.render(data);
+                }
+            })?);
+        }
+
+        let mut overload_listeners = vec![];
+        let devices = if same_device {
+            vec![input]
+        } else {
+            vec![input, output]
+        };
+        for device in devices {
+            let counters = counters.clone();
+            let address = get_property_address(Property::DeviceProcessorOverload, Scope::Global);
+            overload_listeners.push(PropertyListener::new(
+                device.0.id(),
+                vec![address],
+                move |_| {
+                    counters.overloads.fetch_add(1, Ordering::Relaxed);
+                },
+            )?);
+        }
+
+        Ok(Self {
+            io_procs,
+            _overload_listeners: overload_listeners,
+            counters,
+            channels,
+            target_frames,
+        })
+    }
+
+    // The number of channels bridged from the input to the output.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    // The number of input frames kept buffered.
+    pub fn target_frames(&self) -> usize {
+        self.target_frames
+    }
+
+    // If either IO proc fails to start, the ones already started are stopped again, so the
+    // stream isn't left half-running.
+    pub fn start(&mut self) -> Result<(), OSStatus> {
+        let started = self
+            .io_procs
+            .iter_mut()
+            .try_for_each(|io_proc| io_proc.start());
+        if started.is_err() {
+            let _ = self.stop();
+        }
+        started
+    }
+
+    pub fn stop(&mut self) -> Result<(), OSStatus> {
+        // Stop the output first, so it doesn't underrun.
+        for io_proc in self.io_procs.iter_mut().rev() {
+            io_proc.stop()?;
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> DuplexStats {
+        self.counters.stats()
+    }
+}
+
+// Creates the halves of the bridge that run on the input and output IO procs. Without a
+// `quality`, the input is rendered as it is, which is only right when both sides share a clock
+// and a sample rate.
+fn bridge(
+    channels: usize,
+    input_rate: f64,
+    output_rate: f64,
+    target_frames: usize,
+    quality: Option<Quality>,
+    counters: Arc<Counters>,
+) -> (Capture, Render) {
+    // Room for the target plus a few cycles of jitter either way.
+    let capacity = (4 * target_frames).max(target_frames + 4 * CHUNK_FRAMES);
+    let (producer, consumer) = ring_buffer(channels, capacity);
+    let resampler = quality.map(|quality| {
+        let resampler = Resampler::new(channels, input_rate, output_rate, quality);
+        let drift = DriftTracker::new(resampler.nominal_ratio(), target_frames);
+        (resampler, drift)
+    });
+    counters
+        .ratio
+        .store((output_rate / input_rate).to_bits(), Ordering::Relaxed);
+    let capture = Capture {
+        producer,
+        scratch: vec![0.0; CHUNK_FRAMES * channels],
+        counters: counters.clone(),
+    };
+    let render = Render {
+        consumer,
+        resampler,
+        target_frames,
+        priming: true,
+        scratch: vec![0.0; CHUNK_FRAMES * channels],
+        counters,
+    };
+    (capture, render)
+}
+
+struct Capture {
+    producer: Producer<f32>,
+    scratch: Vec<f32>,
+    counters: Arc<Counters>,
+}
+
+impl Capture {
+    fn capture(&mut self, data: &BufferListRef) {
+        let channels = self.producer.channels();
+        let frames = frame_count(data.buffers());
+        let mut start = 0;
+        while start < frames {
+            let count = (frames - start).min(CHUNK_FRAMES);
+            let scratch = &mut self.scratch[..count * channels];
+            read_interleaved(data, start, scratch, channels);
+            if self.producer.push_interleaved(scratch) < count {
+                self.counters.overruns.fetch_add(1, Ordering::Relaxed);
+                // Drop the rest of the cycle rather than counting it again.
+                break;
+            }
+            start += count;
+        }
+    }
+}
+
+struct Render {
+    consumer: Consumer<f32>,
+    resampler: Option<(Resampler, DriftTracker)>,
+    target_frames: usize,
+    // Set until the buffer holds its target, during which silence is rendered.
+    priming: bool,
+    scratch: Vec<f32>,
+    counters: Arc<Counters>,
+}
+
+impl Render {
+    fn render(&mut self, data: &mut BufferListMut) {
+        let channels = self.consumer.channels();
+        let frames = frame_count(data.buffers());
+
+        let buffered = self.consumer.available();
+        self.counters
+            .buffered_frames
+            .store(buffered, Ordering::Relaxed);
+        if self.priming && buffered >= self.target_frames {
+            self.priming = false;
+        }
+        if !self.priming {
+            if let Some((resampler, drift)) = self.resampler.as_mut() {
+                let ratio = drift.update(buffered);
+                resampler.set_ratio(ratio);
+                self.counters
+                    .ratio
+                    .store(ratio.to_bits(), Ordering::Relaxed);
+            }
+        }
+
+        let mut start = 0;
+        while start < frames {
+            let count = (frames - start).min(CHUNK_FRAMES);
+            let scratch = &mut self.scratch[..count * channels];
+            let produced = if self.priming {
+                0
+            } else {
+                match self.resampler.as_mut() {
+                    Some((resampler, _)) => resampler.process_from(&mut self.consumer, scratch),
+                    None => {
+                        let frames = self.consumer.available().min(count);
+                        self.consumer
+                            .pop_interleaved(&mut scratch[..frames * channels])
+                    }
+                }
+            };
+            for sample in scratch[produced * channels..].iter_mut() {
+                *sample = 0.0;
+            }
+            if !self.priming && produced < count {
+                self.counters.underruns.fetch_add(1, Ordering::Relaxed);
+                self.priming = true;
+                // Start over from the next input, rather than from what's left of the filter.
+                if let Some((resampler, _)) = self.resampler.as_mut() {
+                    resampler.reset();
+                }
+            }
+
+            write_interleaved(data, start, scratch, channels);
+            start += count;
+        }
+    }
+}
+
+#[test]
+fn test_duplex_bridge() {
+    use super::super::buffer_list::BufferList;
+    use super::super::stream_format::StreamFormat;
+    use std::f64::consts::PI;
+
+    // A stereo input at 48 kHz played on three channels at 44.1 kHz, whose clock runs 0.01%
+    // fast.
+    const TARGET: usize = 1024;
+    let counters = Arc::new(Counters::default());
+    let (mut capture, mut render) = bridge(
+        2,
+        48000.0,
+        44100.0,
+        TARGET,
+        Some(Quality::Low),
+        counters.clone(),
+    );
+    let mut input = BufferList::allocate(&StreamFormat::float32(48000.0, 2, true), 256);
+    let mut output = BufferList::allocate(&StreamFormat::float32(44100.0, 3, false), 512);
+
+    let (mut input_time, mut output_time) = (0.0, 0.0);
+    let mut captured = 0;
+    let mut rendered = vec![];
+    while input_time < 10.0 {
+        if input_time <= output_time {
+            let samples = input.data_mut::<f32>(0).unwrap();
+            for (frame, samples) in samples.chunks_mut(2).enumerate() {
+                let t = (captured + frame) as f64 / 48000.0;
+                samples[0] = (2.0 * PI * 1000.0 * t).sin() as f32 * 0.5;
+                samples[1] = 0.25;
+            }
+            capture.capture(&input.view());
+            captured += 256;
+            input_time += 256.0 / 48000.0;
+        } else {
+            render.render(&mut output.view_mut());
+            let stats = counters.stats();
+            rendered.push((stats, output.data::<f32>(1).unwrap()[511]));
+            assert!(output.data::<f32>(2).unwrap().iter().all(|s| *s == 0.0));
+            output_time += 512.0 / 44100.0 / 1.0001;
+        }
+    }
+
+    let stats = counters.stats();
+    assert_eq!(stats.overruns, 0);
+    assert_eq!(stats.underruns, 0);
+    assert_eq!(stats.xruns(), 0);
+    // Silence until the target is buffered, then the input.
+    assert_eq!(rendered[0].1, 0.0);
+    let settled = &rendered[rendered.len() / 2..];
+    for (stats, sample) in settled {
+        assert!((sample - 0.25).abs() < 1e-2);
+        assert!(stats.buffered_frames > TARGET / 2 && stats.buffered_frames < TARGET * 2);
+    }
+    let ratio = settled.iter().map(|(s, _)| s.ratio).sum::<f64>() / settled.len() as f64;
+    assert!((ratio - 44100.0 / 48000.0 * 1.0001).abs() < 1e-4);
+}
@@ -0,0 +1,249 @@
+use super::super::sys::OSStatus;
+use super::audio_object::AudioObject;
+use super::listener::PropertyListener;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, Side, SystemDevice};
+use futures_channel::mpsc::{self, UnboundedReceiver};
+use futures_core::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// The values a HAL listener reports, as a stream. The listener is registered as long as the
+// stream is alive, so dropping it, e.g., when a task is cancelled, unregisters it.
+pub struct PropertyStream<T> {
+    receiver: UnboundedReceiver<T>,
+    _listener: PropertyListener,
+}
+
+impl<T: Send + 'static> PropertyStream<T> {
+    // Call `read` on a HAL thread whenever one of `properties` of `object` changes, and yield
+    // what it returns, if anything. The properties are in the scope of `s`, or global.
+    fn new<F>(
+        object: &AudioObject,
+        properties: &[Property],
+        s: Option<&Side>,
+        mut read: F,
+    ) -> Result<Self, OSStatus>
+    where
+        F: FnMut() -> Option<T> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::unbounded();
+        let addresses = properties
+            .iter()
+            .map(|property| get_property_address(*property, s.map_or(Scope::Global, Scope::from)))
+            .collect();
+        let listener = PropertyListener::new(object.id(), addresses, move |_| {
+            if let Some(value) = read() {
+                // The receiver only goes away with the listener.
+                let _ = sender.unbounded_send(value);
+            }
+        })?;
+        Ok(Self {
+            receiver,
+            _listener: listener,
+        })
+    }
+}
+
+impl<T> Stream for PropertyStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Added(Device),
+    Removed(Device),
+    DefaultChanged(Side, Device),
+}
+
+impl SystemDevice {
+    // The new default device of this side, whenever it changes.
+    pub fn default_device_changes(&self, s: &Side) -> Result<PropertyStream<Device>, OSStatus> {
+        let property = match s {
+            Side::Input => Property::DefaultInputDevice,
+            Side::Output => Property::DefaultOutputDevice,
+        };
+        let s = *s;
+        PropertyStream::new(&self.0, &[property], None, move || {
+            SystemDevice::default().get_default_device(&s).ok()
+        })
+    }
+
+    pub fn default_input_changes(&self) -> Result<PropertyStream<Device>, OSStatus> {
+        self.default_device_changes(&Side::Input)
+    }
+
+    pub fn default_output_changes(&self) -> Result<PropertyStream<Device>, OSStatus> {
+        self.default_device_changes(&Side::Output)
+    }
+
+    // Devices being added or removed, and the default devices changing. Each change of the device
+    // list is compared with the previous one, starting from the list when this is called.
+    pub fn device_events(&self) -> Result<PropertyStream<Vec<DeviceEvent>>, OSStatus> {
+        let mut devices = self.get_all_devices()?;
+        let mut defaults = [Side::Input, Side::Output].map(|s| self.get_default_device(&s).ok());
+        let properties = [
+            Property::Devices,
+            Property::DefaultInputDevice,
+            Property::DefaultOutputDevice,
+        ];
+        PropertyStream::new(&self.0, &properties, None, move || {
+            let system = SystemDevice::default();
+            let mut events = vec![];
+            if let Ok(current) = system.get_all_devices() {
+                for device in current.iter().filter(|d| !devices.contains(d)) {
+                    events.push(DeviceEvent::Added(*device));
+                }
+                for device in devices.iter().filter(|d| !current.contains(d)) {
+                    events.push(DeviceEvent::Removed(*device));
+                }
+                devices = current;
+            }
+            for (s, default) in [Side::Input, Side::Output].iter().zip(defaults.iter_mut()) {
+                let current = system.get_default_device(s).ok();
+                if current != *default {
+                    *default = current;
+                    if let Some(device) = current {
+                        events.push(DeviceEvent::DefaultChanged(*s, device));
+                    }
+                }
+            }
+            // The listener is called for each changed property, so most calls after the first
+            // find nothing new.
+            Some(events).filter(|events| !events.is_empty())
+        })
+    }
+}
+
+// Resolves once a device's nominal sample rate is `rate`, e.g., after `set_sample_rate`, which
+// applies asynchronously.
+pub struct WaitForSampleRate {
+    changes: Option<PropertyStream<f64>>,
+    rate: f64,
+}
+
+impl Future for WaitForSampleRate {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let rate = self.rate;
+        while let Some(changes) = self.changes.as_mut() {
+            match Pin::new(changes).poll_next(cx) {
+                Poll::Ready(Some(current)) if current != rate => {}
+                Poll::Ready(_) => self.changes = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+impl Device {
+    // The device's nominal sample rate on this side, whenever it changes.
+    pub fn sample_rate_changes(&self, s: &Side) -> Result<PropertyStream<f64>, OSStatus> {
+        let (device, s) = (*self, *s);
+        PropertyStream::new(
+            &self.0,
+            &[Property::DeviceSampleRate],
+            Some(&s),
+            move || device.sample_rate(&s).ok(),
+        )
+    }
+
+    // Both sides share the nominal sample rate, so either is waited for.
+    pub fn wait_for_sample_rate(&self, rate: f64) -> Result<WaitForSampleRate, OSStatus> {
+        let s = if self.in_scope(&Side::Output).unwrap_or(true) {
+            Side::Output
+        } else {
+            Side::Input
+        };
+        // Listen before reading the rate, so a change in between isn't missed.
+        let changes = self.sample_rate_changes(&s)?;
+        let current = self.sample_rate(&s)?;
+        Ok(WaitForSampleRate {
+            changes: Some(changes).filter(|_| current != rate),
+            rate,
+        })
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_device_events() {
+    use super::super::sys::kAudioObjectSystemObject;
+    use super::fixture::{self, Fixture};
+    use std::task::Waker;
+
+    let devices = get_property_address(Property::Devices, Scope::Global);
+    let default_output = get_property_address(Property::DefaultOutputDevice, Scope::Global);
+    let default_input = get_property_address(Property::DefaultInputDevice, Scope::Global);
+    let rate = get_property_address(Property::DeviceSampleRate, Scope::Output);
+    let mut fixture = Fixture::with_devices(&[70, 71]);
+    fixture.insert_data(kAudioObjectSystemObject, &default_input, &[71_u32]);
+
+    fixture.replay(|| {
+        let mut cx = Context::from_waker(Waker::noop());
+        let system = SystemDevice::default();
+        let device = system.get_default_device(&Side::Output).unwrap();
+        let mut changes = system.default_output_changes().unwrap();
+        let mut events = system.device_events().unwrap();
+        let mut wait = device.wait_for_sample_rate(48000.0).unwrap();
+        assert!(Pin::new(&mut changes).poll_next(&mut cx).is_pending());
+        assert!(Pin::new(&mut wait).poll(&mut cx).is_pending());
+
+        let system_object = system.object();
+        system_object
+            .set_property_data_common(&devices, &[71_u32, 72])
+            .unwrap();
+        system_object
+            .set_property_data_common(&default_output, &72_u32)
+            .unwrap();
+        fixture::notify(kAudioObjectSystemObject, &devices);
+        fixture::notify(kAudioObjectSystemObject, &default_output);
+        assert_eq!(
+            Pin::new(&mut changes).poll_next(&mut cx),
+            Poll::Ready(Some(Device::new(72)))
+        );
+        assert_eq!(
+            Pin::new(&mut events).poll_next(&mut cx),
+            Poll::Ready(Some(vec![
+                DeviceEvent::Added(Device::new(72)),
+                DeviceEvent::Removed(Device::new(70)),
+                DeviceEvent::DefaultChanged(Side::Output, Device::new(72)),
+            ]))
+        );
+        // Nothing was left for the second notification.
+        assert!(Pin::new(&mut events).poll_next(&mut cx).is_pending());
+
+        device
+            .0
+            .set_property_data_common(&rate, &48000.0_f64)
+            .unwrap();
+        fixture::notify(70, &rate);
+        assert!(Pin::new(&mut wait).poll(&mut cx).is_ready());
+        assert!(device
+            .wait_for_sample_rate(48000.0)
+            .unwrap()
+            .changes
+            .is_none());
+
+        // The other streams keep going after one is dropped.
+        drop(changes);
+        system_object
+            .set_property_data_common(&default_output, &71_u32)
+            .unwrap();
+        fixture::notify(kAudioObjectSystemObject, &default_output);
+        assert_eq!(
+            Pin::new(&mut events).poll_next(&mut cx),
+            Poll::Ready(Some(vec![DeviceEvent::DefaultChanged(
+                Side::Output,
+                Device::new(71)
+            )]))
+        );
+    });
+}
@@ -0,0 +1,880 @@
+// Record and replay the HAL property data read through `AudioObject`.
+//
+// While recording, every property query made on the current thread is forwarded to the HAL and
+// its result, including any error status, is stored in a `Fixture`. While replaying, queries are
+// answered from the `Fixture` alone and never reach the HAL, so a property tree captured on one
+// machine can be used to reproduce its behavior in a deterministic test.
+use super::super::sys::{
+    kAudioHardwareBadPropertySizeError, kAudioHardwareUnknownPropertyError, noErr, AudioObjectID,
+    AudioObjectPropertyAddress, AudioObjectPropertyListenerProc, OSStatus,
+};
+use super::audio_object::Plain;
+use super::{Control, ControlKind, Device, Side, SystemDevice};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::mem;
+use std::os::raw::c_void;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    object: AudioObjectID,
+    selector: u32,
+    scope: u32,
+    element: u32,
+    qualifier: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Data(Vec<u8>),
+    String(String),
+}
+
+type Entry = Result<Value, OSStatus>;
+
+// A value that can be stored in a fixture. Plain data is stored as its raw bytes in native
+// endianness, so fixtures are portable between little-endian machines.
+pub(crate) trait Recordable: Sized {
+    fn to_value(&self) -> Value;
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl Recordable for String {
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Data(_) => None,
+        }
+    }
+}
+
+impl<D: Copy + Default> Recordable for Plain<D> {
+    fn to_value(&self) -> Value {
+        Value::Data(slice_to_bytes(std::slice::from_ref(&self.0)))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let elements = bytes_to_vec::<D>(value)?;
+        if elements.len() == 1 {
+            Some(Plain(elements[0]))
+        } else {
+            None
+        }
+    }
+}
+
+impl<D: Copy + Default> Recordable for Vec<D> {
+    fn to_value(&self) -> Value {
+        Value::Data(slice_to_bytes(self))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        bytes_to_vec::<D>(value)
+    }
+}
+
+fn slice_to_bytes<D: Copy>(data: &[D]) -> Vec<u8> {
+    let size = mem::size_of_val(data);
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, size) }.to_vec()
+}
+
+fn bytes_to_vec<D: Copy + Default>(value: &Value) -> Option<Vec<D>> {
+    let bytes = match value {
+        Value::Data(bytes) => bytes,
+        Value::String(_) => return None,
+    };
+    let element_size = mem::size_of::<D>();
+    if element_size == 0 || bytes.len() % element_size != 0 {
+        return None;
+    }
+    let mut elements = vec![D::default(); bytes.len() / element_size];
+    // The destination is properly aligned for `D`, so copy the bytes into it instead of
+    // reinterpreting the (possibly unaligned) source buffer.
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            elements.as_mut_ptr() as *mut u8,
+            bytes.len(),
+        );
+    }
+    Some(elements)
+}
+
+enum Mode {
+    Record(Fixture),
+    Replay(Fixture),
+}
+
+struct Listener {
+    object: AudioObjectID,
+    address: AudioObjectPropertyAddress,
+    listener: AudioObjectPropertyListenerProc,
+    client_data: *mut c_void,
+}
+
+thread_local! {
+    static MODE: RefCell<Option<Mode>> = const { RefCell::new(None) };
+    // The listeners registered while replaying. They are only called by `notify`.
+    static LISTENERS: RefCell<Vec<Listener>> = const { RefCell::new(Vec::new()) };
+}
+
+// Restore the previous mode when leaving `Fixture::record` or `Fixture::replay`, even on panic.
+struct ModeGuard(Option<Mode>);
+
+impl ModeGuard {
+    fn enter(mode: Mode) -> Self {
+        Self(MODE.with(|m| m.replace(Some(mode))))
+    }
+
+    fn exit(mut self) -> Option<Mode> {
+        let previous = self.0.take();
+        mem::forget(self);
+        MODE.with(|m| m.replace(previous))
+    }
+}
+
+impl Drop for ModeGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        MODE.with(|m| {
+            if let Ok(mut m) = m.try_borrow_mut() {
+                *m = previous;
+            }
+        });
+    }
+}
+
+pub(crate) fn intercept<T: Recordable>(
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+    qualifier: &[u8],
+    live: impl FnOnce() -> Result<T, OSStatus>,
+) -> Result<T, OSStatus> {
+    let key = Key {
+        object,
+        selector: address.mSelector,
+        scope: address.mScope,
+        element: address.mElement,
+        qualifier: qualifier.to_vec(),
+    };
+
+    let replayed = MODE.with(|m| match &*m.borrow() {
+        Some(Mode::Replay(fixture)) => Some(fixture.entries.get(&key).cloned()),
+        _ => None,
+    });
+    if let Some(entry) = replayed {
+        return match entry {
+            Some(Ok(value)) => {
+                T::from_value(&value).ok_or(kAudioHardwareBadPropertySizeError as OSStatus)
+            }
+            Some(Err(status)) => Err(status),
+            None => Err(kAudioHardwareUnknownPropertyError as OSStatus),
+        };
+    }
+
+    // Don't hold the borrow while calling into the HAL.
+    let result = live();
+    MODE.with(|m| {
+        if let Some(Mode::Record(fixture)) = &mut *m.borrow_mut() {
+            let entry = match &result {
+                Ok(data) => Ok(data.to_value()),
+                Err(status) => Err(*status),
+            };
+            fixture.entries.insert(key, entry);
+        }
+    });
+    result
+}
+
+// While replaying, a property set on the current thread replaces the value in the fixture, so it
+// is returned by the following queries. Otherwise the data is passed on to the HAL.
+pub(crate) fn intercept_set(
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+    data: &[u8],
+    live: impl FnOnce() -> Result<(), OSStatus>,
+) -> Result<(), OSStatus> {
+    let key = Key {
+        object,
+        selector: address.mSelector,
+        scope: address.mScope,
+        element: address.mElement,
+        qualifier: Vec::new(),
+    };
+    let replayed = MODE.with(|m| match &mut *m.borrow_mut() {
+        Some(Mode::Replay(fixture)) => {
+            fixture.entries.insert(key, Ok(Value::Data(data.to_vec())));
+            true
+        }
+        _ => false,
+    });
+    if replayed {
+        Ok(())
+    } else {
+        live()
+    }
+}
+
+pub(crate) fn is_replaying() -> bool {
+    MODE.with(|m| matches!(&*m.borrow(), Some(Mode::Replay(_))))
+}
+
+// While replaying, listeners are kept away from the HAL. Returns `None` otherwise.
+pub(crate) fn add_listener(
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+    listener: AudioObjectPropertyListenerProc,
+    client_data: *mut c_void,
+) -> Option<OSStatus> {
+    if !is_replaying() {
+        return None;
+    }
+    LISTENERS.with(|l| {
+        l.borrow_mut().push(Listener {
+            object,
+            address: *address,
+            listener,
+            client_data,
+        })
+    });
+    Some(noErr as OSStatus)
+}
+
+pub(crate) fn remove_listener(
+    object: AudioObjectID,
+    address: &AudioObjectPropertyAddress,
+    listener: AudioObjectPropertyListenerProc,
+    client_data: *mut c_void,
+) -> Option<OSStatus> {
+    let removed = LISTENERS.with(|l| {
+        let mut listeners = l.borrow_mut();
+        let position = listeners.iter().position(|l| {
+            l.object == object
+                && same_address(&l.address, address)
+                && l.listener.map(|f| f as usize) == listener.map(|f| f as usize)
+                && l.client_data == client_data
+        })?;
+        Some(listeners.remove(position))
+    });
+    // A listener added before replaying started belongs to the HAL.
+    removed.map(|_| noErr as OSStatus)
+}
+
+fn same_address(a: &AudioObjectPropertyAddress, b: &AudioObjectPropertyAddress) -> bool {
+    a.mSelector == b.mSelector && a.mScope == b.mScope && a.mElement == b.mElement
+}
+
+// Call the listeners registered on this thread while replaying for `address` of `object`, as
+// the HAL would when the property changes.
+#[cfg(test)]
+pub(crate) fn notify(object: AudioObjectID, address: &AudioObjectPropertyAddress) {
+    let matching: Vec<_> = LISTENERS.with(|l| {
+        l.borrow()
+            .iter()
+            .filter(|l| l.object == object && same_address(&l.address, address))
+            .map(|l| (l.listener, l.client_data))
+            .collect()
+    });
+    for (listener, client_data) in matching {
+        if let Some(listener) = listener {
+            unsafe { listener(object, 1, address, client_data) };
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fixture {
+    entries: BTreeMap<Key, Entry>,
+}
+
+impl Fixture {
+    // Run `f` and record every property queried from the HAL on this thread while it runs.
+    pub fn record<R, F: FnOnce() -> R>(f: F) -> (R, Self) {
+        let guard = ModeGuard::enter(Mode::Record(Self::default()));
+        let result = f();
+        let fixture = match guard.exit() {
+            Some(Mode::Record(fixture)) => fixture,
+            _ => unreachable!("the recording mode was replaced"),
+        };
+        (result, fixture)
+    }
+
+    // Record the properties of the system object, its plug-ins, processes, clocks and boxes,
+    // and of all its devices in both scopes.
+    pub fn capture(system: &SystemDevice) -> Self {
+        Self::record(|| {
+            for side in &[Side::Input, Side::Output] {
+                let _ = system.get_default_device(side);
+            }
+            if let Ok(devices) = system.get_all_devices() {
+                for device in devices.iter() {
+                    capture_device(device);
+                }
+            }
+            for plug_in in system.plug_ins().unwrap_or_default() {
+                let _ = plug_in.bundle_id();
+                let _ = plug_in.name();
+                let _ = plug_in.manufacturer();
+                let _ = plug_in.devices();
+                let _ = plug_in.boxes();
+                let _ = plug_in.clock_devices();
+            }
+            for process in system.processes().unwrap_or_default() {
+                let _ = process.pid();
+                let _ = process.bundle_id();
+                let _ = process.is_running();
+                let _ = process.is_running_input();
+                let _ = process.is_running_output();
+                for side in &[Side::Input, Side::Output] {
+                    let _ = process.devices(side);
+                }
+            }
+            for clock in system.clock_devices().unwrap_or_default() {
+                let _ = clock.uid();
+                let _ = clock.name();
+                let _ = clock.transport_type();
+                let _ = clock.clock_domain();
+                let _ = clock.is_alive();
+                let _ = clock.is_running();
+                let _ = clock.latency();
+                let _ = clock.sample_rate();
+                let _ = clock.sample_rate_ranges();
+            }
+            for audio_box in system.boxes().unwrap_or_default() {
+                let _ = audio_box.uid();
+                let _ = audio_box.name();
+                let _ = audio_box.manufacturer();
+                let _ = audio_box.transport_type();
+                let _ = audio_box.has_audio();
+                let _ = audio_box.has_video();
+                let _ = audio_box.has_midi();
+                let _ = audio_box.is_protected();
+                let _ = audio_box.is_acquired();
+                let _ = audio_box.acquisition_failed();
+                let _ = audio_box.devices();
+                let _ = audio_box.clock_devices();
+            }
+        })
+        .1
+    }
+
+    // Run `f` with every property query on this thread answered from this fixture. Properties
+    // missing from the fixture fail with `kAudioHardwareUnknownPropertyError`.
+    pub fn replay<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let _guard = ModeGuard::enter(Mode::Replay(self.clone()));
+        f()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        let records: Vec<Record> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| Record::new(key, entry))
+            .collect();
+        serde_json::to_string_pretty(&FixtureFile {
+            properties: records,
+        })
+        .expect("fixture records are always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        use serde::de::Error;
+        let file: FixtureFile = serde_json::from_str(json)?;
+        let mut entries = BTreeMap::new();
+        for record in file.properties {
+            let (key, entry) = record.into_entry().map_err(serde_json::Error::custom)?;
+            entries.insert(key, entry);
+        }
+        Ok(Self { entries })
+    }
+}
+
+// Helpers to build fixtures by hand in tests.
+#[cfg(test)]
+impl Fixture {
+    // A system with `devices`, each with the UID `uid-<id>`. The first one is the default output
+    // and runs at 44.1 kHz.
+    pub(crate) fn with_devices(devices: &[AudioObjectID]) -> Self {
+        use super::super::sys::kAudioObjectSystemObject;
+        use super::property_address::{get_property_address, Property, Scope};
+
+        let global = |property| get_property_address(property, Scope::Global);
+        let mut fixture = Self::default();
+        fixture.insert_data(
+            kAudioObjectSystemObject,
+            &global(Property::Devices),
+            devices,
+        );
+        fixture.insert_data(
+            kAudioObjectSystemObject,
+            &global(Property::DefaultOutputDevice),
+            &devices[..1],
+        );
+        for device in devices {
+            let uid = format!("uid-{}", device);
+            fixture.insert_string(*device, &global(Property::DeviceUID), &uid);
+        }
+        fixture.insert_data(
+            devices[0],
+            &get_property_address(Property::DeviceSampleRate, Scope::Output),
+            &[44100.0_f64],
+        );
+        fixture
+    }
+
+    fn insert(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        entry: Result<Value, OSStatus>,
+    ) {
+        self.insert_qualified(object, address, Vec::new(), entry);
+    }
+
+    fn insert_qualified(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: Vec<u8>,
+        entry: Result<Value, OSStatus>,
+    ) {
+        let key = Key {
+            object,
+            selector: address.mSelector,
+            scope: address.mScope,
+            element: address.mElement,
+            qualifier,
+        };
+        self.entries.insert(key, entry);
+    }
+
+    pub(crate) fn insert_data<D: Copy>(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        data: &[D],
+    ) {
+        self.insert(object, address, Ok(Value::Data(slice_to_bytes(data))));
+    }
+
+    pub(crate) fn insert_qualified_data<Q: Copy, D: Copy>(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[Q],
+        data: &[D],
+    ) {
+        let qualifier = slice_to_bytes(qualifier);
+        self.insert_qualified(
+            object,
+            address,
+            qualifier,
+            Ok(Value::Data(slice_to_bytes(data))),
+        );
+    }
+
+    // For properties qualified by a `CFString`, e.g., a UID to translate into a device.
+    pub(crate) fn insert_string_qualified_data<D: Copy>(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &str,
+        data: &[D],
+    ) {
+        self.insert_qualified(
+            object,
+            address,
+            qualifier.as_bytes().to_vec(),
+            Ok(Value::Data(slice_to_bytes(data))),
+        );
+    }
+
+    pub(crate) fn insert_string(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        string: &str,
+    ) {
+        self.insert(object, address, Ok(Value::String(string.to_string())));
+    }
+
+    pub(crate) fn insert_qualified_string<Q: Copy>(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        qualifier: &[Q],
+        string: &str,
+    ) {
+        let qualifier = slice_to_bytes(qualifier);
+        self.insert_qualified(
+            object,
+            address,
+            qualifier,
+            Ok(Value::String(string.to_string())),
+        );
+    }
+
+    pub(crate) fn insert_error(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        status: OSStatus,
+    ) {
+        self.insert(object, address, Err(status));
+    }
+
+    // An `AudioBufferList` with one buffer of `channels[i]` channels per stream.
+    pub(crate) fn insert_buffer_list(
+        &mut self,
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+        channels: &[u32],
+    ) {
+        use crate::buffer_list::{AudioBuffer, AudioBufferList};
+        let buffers: Vec<AudioBuffer> = channels
+            .iter()
+            .map(|c| AudioBuffer {
+                mNumberChannels: *c,
+                mDataByteSize: 0,
+                mData: std::ptr::null_mut(),
+            })
+            .collect();
+        let mut list = (buffers.len() as u32).to_ne_bytes().to_vec();
+        list.resize(
+            mem::size_of::<AudioBufferList>() - mem::size_of::<AudioBuffer>(),
+            0,
+        );
+        list.extend(slice_to_bytes(&buffers));
+        self.insert(object, address, Ok(Value::Data(list)));
+    }
+}
+
+fn capture_device(device: &Device) {
+    let _ = device.name(None);
+    let _ = device.model_uid(None);
+    let _ = device.uid(None);
+    let _ = device.hog_mode_owner();
+    let _ = device.state();
+    let _ = device.can_be_default_system();
+    let _ = device.io_cycle_usage();
+    for side in &[Side::Input, Side::Output] {
+        let _ = device.in_scope(side);
+        let _ = device.buffer_frame_size_range(side);
+        let _ = device.can_be_default(side);
+        let _ = device.channel_count(side);
+        let _ = device.channels(side);
+        let _ = device.clock_domain(side);
+        let _ = device.latency(side);
+        let _ = device.latency_report(side);
+        let _ = device.uses_variable_buffer_frame_sizes(side);
+        let _ = device.manufacturer(side);
+        let _ = device.model_uid(Some(side));
+        let _ = device.name(Some(side));
+        let _ = device.preferred_channel_layout(side);
+        let _ = device.preferred_channels_for_stereo(side);
+        let _ = device.sample_rate(side);
+        let _ = device.sample_rate_ranges(side);
+        let _ = device.source_name(side);
+        let _ = device.transport_type(side);
+        let _ = device.uid(Some(side));
+    }
+    for control in device.controls().unwrap_or_default() {
+        capture_control(&control);
+    }
+}
+
+fn capture_control(control: &Control) {
+    let _ = control.name();
+    let _ = control.side();
+    let _ = control.element();
+    match control.kind() {
+        Ok(ControlKind::Level(level)) => {
+            let _ = level.scalar();
+            let _ = level.decibels();
+            let _ = level.decibel_range();
+        }
+        Ok(ControlKind::Boolean(boolean)) => {
+            let _ = boolean.value();
+        }
+        Ok(ControlKind::Selector(selector)) => {
+            let _ = selector.items();
+            let _ = selector.current_items();
+        }
+        Ok(ControlKind::Slider(slider)) => {
+            let _ = slider.value();
+            let _ = slider.range();
+        }
+        Ok(ControlKind::StereoPan(pan)) => {
+            let _ = pan.value();
+            let _ = pan.panning_channels();
+        }
+        Ok(ControlKind::Other(_)) | Err(_) => {}
+    }
+}
+
+// The on-disk format. Selectors and scopes are written as their four-char codes, e.g., "nsrt",
+// and plain data as a hex string, so fixtures stay readable and can be edited by hand.
+#[derive(Serialize, Deserialize)]
+struct FixtureFile {
+    properties: Vec<Record>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    object: AudioObjectID,
+    selector: String,
+    scope: String,
+    element: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    qualifier: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    string: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<OSStatus>,
+}
+
+impl Record {
+    fn new(key: &Key, entry: &Entry) -> Self {
+        let (data, string, error) = match entry {
+            Ok(Value::Data(bytes)) => (Some(to_hex(bytes)), None, None),
+            Ok(Value::String(s)) => (None, Some(s.clone()), None),
+            Err(status) => (None, None, Some(*status)),
+        };
+        Self {
+            object: key.object,
+            selector: to_four_char_code(key.selector),
+            scope: to_four_char_code(key.scope),
+            element: key.element,
+            qualifier: to_hex(&key.qualifier),
+            data,
+            string,
+            error,
+        }
+    }
+
+    fn into_entry(self) -> Result<(Key, Entry), String> {
+        let key = Key {
+            object: self.object,
+            selector: from_four_char_code(&self.selector)?,
+            scope: from_four_char_code(&self.scope)?,
+            element: self.element,
+            qualifier: from_hex(&self.qualifier)?,
+        };
+        let entry = match (self.data, self.string, self.error) {
+            (Some(data), None, None) => Ok(Value::Data(from_hex(&data)?)),
+            (None, Some(string), None) => Ok(Value::String(string)),
+            (None, None, Some(status)) => Err(status),
+            _ => {
+                return Err(format!(
+                    "property {} of object {} needs exactly one of data, string or error",
+                    self.selector, self.object
+                ))
+            }
+        };
+        Ok((key, entry))
+    }
+}
+
+fn to_four_char_code(code: u32) -> String {
+    let bytes = code.to_be_bytes();
+    if bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        String::from_utf8_lossy(&bytes).to_string()
+    } else {
+        format!("0x{:08x}", code)
+    }
+}
+
+fn from_four_char_code(code: &str) -> Result<u32, String> {
+    if let Some(hex) = code.strip_prefix("0x") {
+        return u32::from_str_radix(hex, 16).map_err(|e| format!("{}: {}", code, e));
+    }
+    let bytes = code.as_bytes();
+    if bytes.len() != 4 {
+        return Err(format!("{:?} is not a four-char code", code));
+    }
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 == 1 {
+        return Err(format!("{:?} has an odd number of hex digits", hex));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("{:?} is not a hex string", hex))
+        })
+        .collect()
+}
+
+#[test]
+fn test_replay_device_properties() {
+    use super::super::sys::AudioValueRange;
+    use super::property_address::{get_property_address, Property, Scope};
+
+    const DEVICE: AudioObjectID = 42;
+    let mut fixture = Fixture::default();
+    fixture.insert_data(
+        1,
+        &get_property_address(Property::Devices, Scope::Global),
+        &[DEVICE],
+    );
+    fixture.insert_string(
+        DEVICE,
+        &get_property_address(Property::DeviceName, Scope::Global),
+        "Scarlett 2i2 USB",
+    );
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::DeviceSampleRate, Scope::Input),
+        &[48000.0_f64],
+    );
+    let range = AudioValueRange {
+        mMinimum: 15.0,
+        mMaximum: 4096.0,
+    };
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::DeviceBufferFrameSizeRange, Scope::Input),
+        &[range],
+    );
+    fixture.insert_buffer_list(
+        DEVICE,
+        &get_property_address(Property::DeviceStreamConfiguration, Scope::Input),
+        &[2, 2],
+    );
+    fixture.insert_error(
+        DEVICE,
+        &get_property_address(Property::DeviceLatency, Scope::Input),
+        kAudioHardwareUnknownPropertyError as OSStatus,
+    );
+
+    let fixture = Fixture::from_json(&fixture.to_json()).unwrap();
+    fixture.replay(|| {
+        let devices = SystemDevice::default().get_all_devices().unwrap();
+        assert_eq!(devices.len(), 1);
+        let device = &devices[0];
+        assert_eq!(device.name(None).unwrap(), "Scarlett 2i2 USB");
+        assert_eq!(device.sample_rate(&Side::Input).unwrap(), 48000.0);
+        assert_eq!(
+            device.buffer_frame_size_range(&Side::Input).unwrap(),
+            (15.0, 4096.0)
+        );
+        assert_eq!(device.channel_count(&Side::Input).unwrap(), 4);
+        assert_eq!(
+            device.latency(&Side::Input).unwrap_err(),
+            kAudioHardwareUnknownPropertyError as OSStatus
+        );
+        assert_eq!(
+            device.sample_rate(&Side::Output).unwrap_err(),
+            kAudioHardwareUnknownPropertyError as OSStatus
+        );
+    });
+}
+
+#[test]
+fn test_fixture_json_format() {
+    let json = r#"{
+        "properties": [
+            { "object": 1, "selector": "dOut", "scope": "glob", "element": 0, "data": "2a000000" },
+            { "object": 42, "selector": "lnam", "scope": "glob", "element": 0, "string": "Speakers" },
+            { "object": 42, "selector": "ltnc", "scope": "outp", "element": 0, "error": 2003332927 }
+        ]
+    }"#;
+    let fixture = Fixture::from_json(json).unwrap();
+    assert_eq!(fixture.len(), 3);
+    assert_eq!(Fixture::from_json(&fixture.to_json()).unwrap(), fixture);
+
+    fixture.replay(|| {
+        let device = SystemDevice::default()
+            .get_default_device(&Side::Output)
+            .unwrap();
+        assert_eq!(device.id().to_string(), "42");
+        assert_eq!(device.name(None).unwrap(), "Speakers");
+        assert_eq!(device.latency(&Side::Output).unwrap_err(), 2003332927);
+    });
+
+    let invalid = r#"{ "properties": [
+        { "object": 1, "selector": "dOut", "scope": "glob", "element": 0, "data": "2a", "error": 1 }
+    ] }"#;
+    assert!(Fixture::from_json(invalid).is_err());
+}
+
+#[test]
+fn test_replay_set_property() {
+    use super::property_address::{get_property_address, Property, Scope};
+    use crate::channel_layout::{ChannelLabel, ChannelLayout};
+
+    const DEVICE: AudioObjectID = 7;
+    let mut fixture = Fixture::default();
+    fixture.insert_data(
+        1,
+        &get_property_address(Property::DefaultOutputDevice, Scope::Global),
+        &[DEVICE],
+    );
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::DevicePreferredChannelsForStereo, Scope::Output),
+        &[1_u32, 2],
+    );
+
+    fixture.replay(|| {
+        let device = SystemDevice::default()
+            .get_default_device(&Side::Output)
+            .unwrap();
+        assert_eq!(
+            device.preferred_channels_for_stereo(&Side::Output).unwrap(),
+            (1, 2)
+        );
+        device
+            .set_preferred_channels_for_stereo(&Side::Output, (3, 4))
+            .unwrap();
+        assert_eq!(
+            device.preferred_channels_for_stereo(&Side::Output).unwrap(),
+            (3, 4)
+        );
+
+        let layout = ChannelLayout::from_labels(&[
+            ChannelLabel::Discrete(0),
+            ChannelLabel::Discrete(1),
+            ChannelLabel::Left,
+            ChannelLabel::Right,
+        ]);
+        device
+            .set_preferred_channel_layout(&Side::Output, &layout)
+            .unwrap();
+        let layout = device.preferred_channel_layout(&Side::Output).unwrap();
+        assert_eq!(layout.channel_of(ChannelLabel::Left), Some(2));
+    });
+    // Replaying doesn't modify the fixture itself.
+    fixture.replay(|| {
+        let device = SystemDevice::default()
+            .get_default_device(&Side::Output)
+            .unwrap();
+        assert_eq!(
+            device.preferred_channels_for_stereo(&Side::Output).unwrap(),
+            (1, 2)
+        );
+    });
+}
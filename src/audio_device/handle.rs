@@ -0,0 +1,193 @@
+use super::super::sys::OSStatus;
+use super::registry::DeviceRegistry;
+use super::{Device, Side, SystemDevice};
+
+// The metadata of a device, as a `DeviceHandle` reads it through its registry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceInfo {
+    pub uid: String,
+    pub name: String,
+    pub manufacturer: String,
+    pub input_channels: u32,
+    pub output_channels: u32,
+    pub sample_rate: f64,
+}
+
+impl DeviceInfo {
+    fn read(registry: &DeviceRegistry, device: &Device) -> Result<Self, OSStatus> {
+        Ok(Self {
+            uid: registry.uid(device, None)?,
+            name: registry.name(device, None)?,
+            manufacturer: either_side(|s| registry.manufacturer(device, s)).unwrap_or_default(),
+            input_channels: registry.channel_count(device, &Side::Input).unwrap_or(0),
+            output_channels: registry.channel_count(device, &Side::Output).unwrap_or(0),
+            sample_rate: either_side(|s| registry.sample_rate(device, s)).unwrap_or(0.0),
+        })
+    }
+}
+
+// A property of the output side, or of the input side for devices without output.
+fn either_side<T, F>(read: F) -> Result<T, OSStatus>
+where
+    F: Fn(&Side) -> Result<T, OSStatus>,
+{
+    read(&Side::Output).or_else(|_| read(&Side::Input))
+}
+
+// A cheaply cloneable reference to a device that reads its `DeviceInfo` through a
+// `DeviceRegistry`, so the info is cached until the HAL reports a change. Clones can be shared
+// between threads, e.g., with a UI that polls the info.
+#[derive(Clone)]
+pub struct DeviceHandle {
+    registry: DeviceRegistry,
+    device: Device,
+}
+
+impl DeviceHandle {
+    pub(crate) fn new(registry: DeviceRegistry, device: Device) -> Self {
+        Self { registry, device }
+    }
+
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    pub fn registry(&self) -> &DeviceRegistry {
+        &self.registry
+    }
+
+    pub fn info(&self) -> Result<DeviceInfo, OSStatus> {
+        DeviceInfo::read(&self.registry, &self.device)
+    }
+
+    pub fn invalidate(&self) {
+        self.registry.invalidate(&self.device.object());
+    }
+}
+
+impl SystemDevice {
+    // A handle for each device, sharing the cache of this `SystemDevice` and its clones. The list
+    // is cached until a device is added or removed.
+    pub fn device_handles(&self) -> Result<Vec<DeviceHandle>, OSStatus> {
+        let registry = self.registry();
+        Ok(registry
+            .devices()?
+            .into_iter()
+            .map(|device| registry.handle(device))
+            .collect())
+    }
+
+    pub fn invalidate_device_handles(&self) {
+        self.registry().invalidate(&self.object());
+    }
+}
+
+// `AudioObject`s and everything built on them are IDs, and the HAL's API can be called from any
+// thread. The listeners and caches are behind mutexes.
+#[test]
+fn test_thread_safety() {
+    use super::super::string::StringRef;
+    use super::{AudioObject, IoProc, PropertyListener, Stream};
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<AudioObject>();
+    assert_send_sync::<Device>();
+    assert_send_sync::<Stream>();
+    assert_send_sync::<SystemDevice>();
+    assert_send_sync::<DeviceHandle>();
+    assert_send_sync::<DeviceRegistry>();
+    assert_send_sync::<PropertyListener>();
+    assert_send_sync::<IoProc>();
+    assert_send_sync::<StringRef>();
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_device_handle() {
+    use super::super::sys::kAudioObjectSystemObject;
+    use super::fixture::{self, Fixture};
+    use super::property_address::{get_property_address, Property, Scope};
+
+    let devices = get_property_address(Property::Devices, Scope::Global);
+    let rate = get_property_address(Property::DeviceSampleRate, Scope::Output);
+    let mut fixture = Fixture::with_devices(&[70, 71]);
+    fixture.insert_string(
+        72,
+        &get_property_address(Property::DeviceUID, Scope::Global),
+        "uid-72",
+    );
+    for device in [70_u32, 71, 72].iter() {
+        fixture.insert_string(
+            *device,
+            &get_property_address(Property::DeviceName, Scope::Global),
+            &format!("Device {}", device),
+        );
+    }
+    fixture.insert_string(
+        70,
+        &get_property_address(Property::DeviceManufacturer, Scope::Output),
+        "Maker",
+    );
+    fixture.insert_buffer_list(
+        70,
+        &get_property_address(Property::DeviceStreamConfiguration, Scope::Input),
+        &[],
+    );
+    fixture.insert_buffer_list(
+        70,
+        &get_property_address(Property::DeviceStreamConfiguration, Scope::Output),
+        &[2],
+    );
+
+    fixture.replay(|| {
+        let system = SystemDevice::default();
+        let misses = || system.registry().stats().misses;
+        let handles = system.device_handles().unwrap();
+        assert_eq!(handles.len(), 2);
+        let handle = handles[0].clone();
+        let info = handle.info().unwrap();
+        assert_eq!(info.uid, "uid-70");
+        assert_eq!(info.name, "Device 70");
+        assert_eq!(info.manufacturer, "Maker");
+        assert_eq!(info.input_channels, 0);
+        assert_eq!(info.output_channels, 2);
+        assert_eq!(info.sample_rate, 44100.0);
+
+        // The info is cached in the registry of the `SystemDevice`.
+        let read = misses();
+        assert_eq!(handle.info().unwrap(), info);
+        assert_eq!(misses(), read);
+
+        // The info is read again once the HAL reports a change.
+        let device = handle.device();
+        device.set_sample_rate(&Side::Output, 48000.0).unwrap();
+        assert_eq!(handle.info().unwrap().sample_rate, 44100.0);
+        fixture::notify(70, &rate);
+        assert_eq!(handle.info().unwrap().sample_rate, 48000.0);
+        assert_eq!(misses(), read + 1);
+
+        // Clones share the list and the info of the remaining devices.
+        let shared = system.clone();
+        system
+            .object()
+            .set_property_data_common(&devices, &[70_u32, 72])
+            .unwrap();
+        assert_eq!(shared.device_handles().unwrap().len(), 2);
+        assert_eq!(
+            shared.device_handles().unwrap()[1].device(),
+            Device::new(71)
+        );
+        fixture::notify(kAudioObjectSystemObject, &devices);
+        let handles = shared.device_handles().unwrap();
+        assert_eq!(handles[0].device(), device);
+        let read = misses();
+        assert_eq!(handles[0].info().unwrap().sample_rate, 48000.0);
+        assert_eq!(misses(), read);
+        assert_eq!(handles[1].info().unwrap().uid, "uid-72");
+
+        // Invalidating a handle drops its device's info.
+        handles[0].invalidate();
+        let read = misses();
+        assert_eq!(handles[0].info().unwrap(), handle.info().unwrap());
+        assert!(misses() > read);
+    });
+}
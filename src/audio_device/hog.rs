@@ -0,0 +1,142 @@
+use super::super::sys::{kAudioDevicePermissionsError, pid_t, OSStatus};
+use super::property_address::{get_property_address, Property, Scope};
+use super::Device;
+use std::fmt;
+
+// The value of `kAudioDevicePropertyHogMode` when no process owns the device.
+const NO_OWNER: pid_t = -1;
+
+#[derive(Debug, PartialEq)]
+pub enum HogError {
+    HeldByOtherProcess(pid_t),
+    HeldByThisProcess,
+    Status(OSStatus),
+}
+
+impl fmt::Display for HogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HogError::HeldByOtherProcess(pid) => {
+                write!(f, "the device is hogged by process {}", pid)
+            }
+            HogError::HeldByThisProcess => {
+                write!(f, "the device is already hogged by this process")
+            }
+            HogError::Status(status) => write!(f, "failed to hog the device. Error {}", status),
+        }
+    }
+}
+
+impl std::error::Error for HogError {}
+
+impl From<OSStatus> for HogError {
+    fn from(status: OSStatus) -> Self {
+        HogError::Status(status)
+    }
+}
+
+// Exclusive access to a device for the current process. Hog mode is released when the guard is
+// dropped, including while unwinding from a panic.
+pub struct HogGuard {
+    device: Device,
+}
+
+impl HogGuard {
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl Drop for HogGuard {
+    fn drop(&mut self) {
+        // Setting the property toggles hog mode, so only do so if it's still ours to release.
+        if self.device.hog_mode_owner() == Ok(Some(current_pid())) {
+            let _ = self.device.set_hog_mode(NO_OWNER);
+        }
+    }
+}
+
+fn current_pid() -> pid_t {
+    std::process::id() as pid_t
+}
+
+impl Device {
+    // The process that has exclusive access to the device, if any.
+    pub fn hog_mode_owner(&self) -> Result<Option<pid_t>, OSStatus> {
+        let address = get_property_address(Property::DeviceHogMode, Scope::Global);
+        self.0
+            .get_property_data_common::<pid_t>(&address)
+            .map(|pid| if pid == NO_OWNER { None } else { Some(pid) })
+    }
+
+    // Take exclusive access to the device for the current process.
+    pub fn hog(&self) -> Result<HogGuard, HogError> {
+        let pid = current_pid();
+        match self.hog_mode_owner()? {
+            Some(owner) if owner == pid => return Err(HogError::HeldByThisProcess),
+            Some(owner) => return Err(HogError::HeldByOtherProcess(owner)),
+            None => {}
+        }
+        self.set_hog_mode(pid)?;
+        // Another process may have taken the device in the meantime.
+        match self.hog_mode_owner()? {
+            Some(owner) if owner == pid => Ok(HogGuard {
+                device: Device::new(self.0.id()),
+            }),
+            Some(owner) => Err(HogError::HeldByOtherProcess(owner)),
+            None => Err(HogError::Status(kAudioDevicePermissionsError as OSStatus)),
+        }
+    }
+
+    fn set_hog_mode(&self, pid: pid_t) -> Result<(), OSStatus> {
+        let address = get_property_address(Property::DeviceHogMode, Scope::Global);
+        self.0.set_property_data_common(&address, &pid)
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_hog_mode() {
+    use super::fixture::Fixture;
+    use super::{Side, SystemDevice};
+
+    const DEVICE: u32 = 60;
+    let mut fixture = Fixture::default();
+    fixture.insert_data(
+        1,
+        &get_property_address(Property::DefaultOutputDevice, Scope::Global),
+        &[DEVICE],
+    );
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::DeviceHogMode, Scope::Global),
+        &[NO_OWNER],
+    );
+
+    fixture.replay(|| {
+        let device = SystemDevice::default()
+            .get_default_device(&Side::Output)
+            .unwrap();
+        assert_eq!(device.hog_mode_owner().unwrap(), None);
+        {
+            let guard = device.hog().unwrap();
+            assert_eq!(
+                guard.device().hog_mode_owner().unwrap(),
+                Some(current_pid())
+            );
+            assert_eq!(device.hog().err(), Some(HogError::HeldByThisProcess));
+        }
+        assert_eq!(device.hog_mode_owner().unwrap(), None);
+
+        // The guard is released while unwinding.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = device.hog().unwrap();
+            panic!("dropped during unwinding");
+        }));
+        assert!(result.is_err());
+        assert_eq!(device.hog_mode_owner().unwrap(), None);
+
+        device.set_hog_mode(4242).unwrap();
+        assert_eq!(device.hog().err(), Some(HogError::HeldByOtherProcess(4242)));
+    });
+}
@@ -0,0 +1,210 @@
+use super::super::buffer_list::{AudioBuffer, BufferListMut, BufferListRef};
+use super::super::sys::{
+    noErr, AudioBufferList, AudioDeviceCreateIOProcID, AudioDeviceDestroyIOProcID,
+    AudioDeviceIOProcID, AudioDeviceStart, AudioDeviceStop, AudioObjectID, AudioTimeStamp,
+    OSStatus,
+};
+use super::super::time_stamp::TimeStamp;
+use super::sync::call_guarded;
+use super::Device;
+use std::mem::ManuallyDrop;
+use std::os::raw::c_void;
+use std::sync::Mutex;
+
+// The data of one IO cycle. `input` is `None` for devices without input streams, and `output`
+// for devices without output streams. The HAL zeroes `output` before the IO proc is called.
+pub struct IoCycle<'a> {
+    pub now: TimeStamp,
+    pub input: Option<BufferListRef<'a>>,
+    pub input_time: TimeStamp,
+    pub output: Option<BufferListMut<'a>>,
+    pub output_time: TimeStamp,
+}
+
+type Callback = Mutex<Box<dyn FnMut(&mut IoCycle) + Send>>;
+
+// A callback registered as an IO proc of a device. It's called on the device's IO thread once
+// per IO cycle while it's started, and is stopped and unregistered when dropped.
+pub struct IoProc {
+    device: AudioObjectID,
+    id: AudioDeviceIOProcID,
+    // Freed in `drop` only once the HAL can no longer call it.
+    callback: ManuallyDrop<Box<Callback>>,
+    running: bool,
+}
+
+impl IoProc {
+    fn new<F>(device: AudioObjectID, callback: F) -> Result<Self, OSStatus>
+    where
+        F: FnMut(&mut IoCycle) + Send + 'static,
+    {
+        let mut io_proc = Self {
+            device,
+            id: None,
+            callback: ManuallyDrop::new(Box::new(Mutex::new(Box::new(callback)))),
+            running: false,
+        };
+        let client_data = io_proc.client_data();
+        let status = unsafe {
+            AudioDeviceCreateIOProcID(device, Some(device_io_proc), client_data, &mut io_proc.id)
+        };
+        if status != noErr as OSStatus {
+            return Err(status);
+        }
+        Ok(io_proc)
+    }
+
+    fn client_data(&self) -> *mut c_void {
+        &**self.callback as *const Callback as *mut c_void
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn start(&mut self) -> Result<(), OSStatus> {
+        if self.running {
+            return Ok(());
+        }
+        let status = unsafe { AudioDeviceStart(self.device, self.id) };
+        if status != noErr as OSStatus {
+            return Err(status);
+        }
+        self.running = true;
+        Ok(())
+    }
+
+    // Returns once the callback is no longer being called.
+    pub fn stop(&mut self) -> Result<(), OSStatus> {
+        if !self.running {
+            return Ok(());
+        }
+        let status = unsafe { AudioDeviceStop(self.device, self.id) };
+        if status != noErr as OSStatus {
+            return Err(status);
+        }
+        self.running = false;
+        Ok(())
+    }
+}
+
+impl Drop for IoProc {
+    fn drop(&mut self) {
+        // Stopping waits for the running cycle, so `callback` outlives its last call. If the
+        // device can't be stopped, the HAL may still call the IO proc, so it stays registered and
+        // the callback is leaked.
+        if self.stop().is_err() {
+            return;
+        }
+        if self.id.is_some() {
+            let _ = unsafe { AudioDeviceDestroyIOProcID(self.device, self.id) };
+        }
+        unsafe { ManuallyDrop::drop(&mut self.callback) };
+    }
+}
+
+impl Device {
+    // Register `callback` as an IO proc of the device. Call `start` on the returned `IoProc` to
+    // start the device's IO.
+    pub fn create_io_proc<F>(&self, callback: F) -> Result<IoProc, OSStatus>
+    where
+        F: FnMut(&mut IoCycle) + Send + 'static,
+    {
+        IoProc::new(self.0.id(), callback)
+    }
+}
+
+// The number of frames in every buffer of 32-bit float IO data.
+pub(crate) fn frame_count(buffers: &[AudioBuffer]) -> usize {
+    buffers
+        .iter()
+        .filter(|buffer| buffer.mNumberChannels > 0)
+        .map(|buffer| buffer.mDataByteSize as usize / (4 * buffer.mNumberChannels as usize))
+        .min()
+        .unwrap_or(0)
+}
+
+// Copy the frames from `start` of the first `channels` channels of `data`, across its buffers,
+// to the interleaved `samples`. Channels that `data` doesn't have are silent.
+pub(crate) fn read_interleaved(
+    data: &BufferListRef,
+    start: usize,
+    samples: &mut [f32],
+    channels: usize,
+) {
+    for sample in samples.iter_mut() {
+        *sample = 0.0;
+    }
+    let frames = samples.len() / channels;
+    let mut first = 0;
+    for (index, buffer) in data.buffers().iter().enumerate() {
+        let stride = buffer.mNumberChannels as usize;
+        if let Some(data) = data.data::<f32>(index) {
+            for channel in 0..stride.min(channels.saturating_sub(first)) {
+                for frame in 0..frames {
+                    samples[frame * channels + first + channel] =
+                        data[(start + frame) * stride + channel];
+                }
+            }
+        }
+        first += stride;
+    }
+}
+
+// Copy the interleaved `samples` to the frames from `start` of `data`'s first `channels`
+// channels, across its buffers. Its other channels are silent.
+pub(crate) fn write_interleaved(
+    data: &mut BufferListMut,
+    start: usize,
+    samples: &[f32],
+    channels: usize,
+) {
+    let frames = samples.len() / channels;
+    let mut first = 0;
+    for index in 0..data.len() {
+        let stride = data.buffers()[index].mNumberChannels as usize;
+        if let Some(data) = data.data_mut::<f32>(index) {
+            for channel in 0..stride {
+                let source = first + channel;
+                for frame in 0..frames {
+                    data[(start + frame) * stride + channel] = if source < channels {
+                        samples[frame * channels + source]
+                    } else {
+                        0.0
+                    };
+                }
+            }
+        }
+        first += stride;
+    }
+}
+
+unsafe fn time_stamp(time: *const AudioTimeStamp) -> TimeStamp {
+    time.as_ref()
+        .map(|time| TimeStamp::from(*time))
+        .unwrap_or_default()
+}
+
+extern "C" fn device_io_proc(
+    _device: AudioObjectID,
+    now: *const AudioTimeStamp,
+    input_data: *const AudioBufferList,
+    input_time: *const AudioTimeStamp,
+    output_data: *mut AudioBufferList,
+    output_time: *const AudioTimeStamp,
+    client_data: *mut c_void,
+) -> OSStatus {
+    let callback = unsafe { &*(client_data as *const Callback) };
+    let mut cycle = unsafe {
+        IoCycle {
+            now: time_stamp(now),
+            input: BufferListRef::from_raw(input_data.cast()).filter(|list| !list.is_empty()),
+            input_time: time_stamp(input_time),
+            output: BufferListMut::from_raw(output_data.cast()).filter(|list| !list.is_empty()),
+            output_time: time_stamp(output_time),
+        }
+    };
+    // After a panic, the device outputs silence.
+    call_guarded(callback, |callback| callback(&mut cycle));
+    noErr as OSStatus
+}
@@ -0,0 +1,209 @@
+use super::super::sys::{kAudioHardwareUnknownPropertyError, OSStatus};
+use super::audio_object::AudioObject;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, Side};
+
+// The latency of one side of a device, in frames at `sample_rate`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatencyReport {
+    pub sample_rate: f64,
+    // The latency of the device's hardware, e.g., its converters.
+    pub device_latency: u32,
+    // How close to the hardware the HAL reads or writes the device's ring buffer.
+    pub safety_offset: u32,
+    pub buffer_frame_size: u32,
+    // The largest latency of the device's streams on this side.
+    pub stream_latency: u32,
+    // The fraction of the IO cycle the IO procs may use, in (0, 1].
+    pub io_cycle_usage: f32,
+    // The part of the buffer that adds to the latency.
+    pub buffer_latency: u32,
+}
+
+impl LatencyReport {
+    pub fn new(
+        s: &Side,
+        sample_rate: f64,
+        device_latency: u32,
+        safety_offset: u32,
+        buffer_frame_size: u32,
+        stream_latency: u32,
+        io_cycle_usage: f32,
+    ) -> Self {
+        let io_cycle_usage = if io_cycle_usage > 0.0 && io_cycle_usage < 1.0 {
+            io_cycle_usage
+        } else {
+            1.0
+        };
+        // Input data is complete at the start of the IO cycle, so a whole buffer is always
+        // waited for. Output only has to be ready the IO cycle usage into the cycle, so the HAL
+        // wakes the IO procs later when less of the cycle is used.
+        let buffer_latency = match s {
+            Side::Input => buffer_frame_size,
+            Side::Output => {
+                (f64::from(buffer_frame_size) * f64::from(io_cycle_usage)).ceil() as u32
+            }
+        };
+        Self {
+            sample_rate,
+            device_latency,
+            safety_offset,
+            buffer_frame_size,
+            stream_latency,
+            io_cycle_usage,
+            buffer_latency,
+        }
+    }
+
+    // Saturates, since the latencies are whatever the driver reports.
+    pub fn total_frames(&self) -> u32 {
+        self.device_latency
+            .saturating_add(self.safety_offset)
+            .saturating_add(self.buffer_latency)
+            .saturating_add(self.stream_latency)
+    }
+
+    pub fn total_ms(&self) -> f64 {
+        self.frames_to_ms(self.total_frames())
+    }
+
+    pub fn frames_to_ms(&self, frames: u32) -> f64 {
+        if self.sample_rate > 0.0 {
+            f64::from(frames) * 1000.0 / self.sample_rate
+        } else {
+            0.0
+        }
+    }
+}
+
+// The smallest buffer size in `range` that holds `target_ms` of audio at `sample_rate`, or the
+// largest one if none does.
+pub fn buffer_frame_size_for_latency(target_ms: f64, sample_rate: f64, range: (f64, f64)) -> u32 {
+    let frames = (target_ms * sample_rate / 1000.0).ceil();
+    let (min, max) = (range.0.ceil(), range.1.floor());
+    frames.max(min).min(max).max(0.0) as u32
+}
+
+// The time from a sample entering `input` to it leaving `output`, which may run at different
+// sample rates.
+pub fn round_trip_ms(input: &LatencyReport, output: &LatencyReport) -> f64 {
+    input.total_ms() + output.total_ms()
+}
+
+impl Device {
+    pub fn buffer_frame_size(&self, s: &Side) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::DeviceBufferFrameSize, Scope::from(s));
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    pub fn set_buffer_frame_size(&self, s: &Side, frames: u32) -> Result<(), OSStatus> {
+        let address = get_property_address(Property::DeviceBufferFrameSize, Scope::from(s));
+        self.0.set_property_data_common(&address, &frames)
+    }
+
+    // The buffer size for the device's sample rate that's closest to holding `target_ms` of
+    // audio. Pass it to `set_buffer_frame_size`.
+    pub fn buffer_frame_size_for_latency(&self, s: &Side, target_ms: f64) -> Result<u32, OSStatus> {
+        Ok(buffer_frame_size_for_latency(
+            target_ms,
+            self.sample_rate(s)?,
+            self.buffer_frame_size_range(s)?,
+        ))
+    }
+
+    pub fn io_cycle_usage(&self) -> Result<f32, OSStatus> {
+        let address = get_property_address(Property::DeviceIOCycleUsage, Scope::Global);
+        self.0.get_property_data_common::<f32>(&address)
+    }
+
+    // Lower values give the IO procs less of the IO cycle in exchange for lower output latency.
+    pub fn set_io_cycle_usage(&self, usage: f32) -> Result<(), OSStatus> {
+        let address = get_property_address(Property::DeviceIOCycleUsage, Scope::Global);
+        self.0.set_property_data_common(&address, &usage)
+    }
+
+    pub fn safety_offset(&self, s: &Side) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::DeviceSafetyOffset, Scope::from(s));
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    // The latency of each of the device's streams on this side, in frames.
+    pub fn stream_latencies(&self, s: &Side) -> Result<Vec<u32>, OSStatus> {
+        let address = get_property_address(Property::StreamLatency, Scope::Global);
+        self.streams(s)?
+            .into_iter()
+            .map(|stream| AudioObject::new(stream).get_property_data_common::<u32>(&address))
+            .collect()
+    }
+
+    // The largest buffer size the IO procs may be called with if the device's buffer size
+    // varies, or `None` if it's always `buffer_frame_size`.
+    pub fn uses_variable_buffer_frame_sizes(&self, s: &Side) -> Result<Option<u32>, OSStatus> {
+        let address =
+            get_property_address(Property::DeviceUsesVariableBufferFrameSizes, Scope::from(s));
+        match self.0.get_property_data_common::<u32>(&address) {
+            Ok(0) => Ok(None),
+            Ok(frames) => Ok(Some(frames)),
+            // Devices with a fixed buffer size don't have the property.
+            Err(status) if status == kAudioHardwareUnknownPropertyError as OSStatus => Ok(None),
+            Err(status) => Err(status),
+        }
+    }
+
+    pub fn latency_report(&self, s: &Side) -> Result<LatencyReport, OSStatus> {
+        // Devices without an IO cycle usage property use the whole cycle.
+        let io_cycle_usage = self.io_cycle_usage().unwrap_or(1.0);
+        Ok(LatencyReport::new(
+            s,
+            self.sample_rate(s)?,
+            self.latency(s)?,
+            self.safety_offset(s)?,
+            self.buffer_frame_size(s)?,
+            self.stream_latencies(s)?.into_iter().max().unwrap_or(0),
+            io_cycle_usage,
+        ))
+    }
+}
+
+#[test]
+fn test_latency_report() {
+    let input = LatencyReport::new(&Side::Input, 48000.0, 24, 16, 512, 8, 0.5);
+    assert_eq!(input.buffer_latency, 512);
+    assert_eq!(input.total_frames(), 560);
+    assert!((input.total_ms() - 560.0 / 48.0).abs() < 1e-9);
+
+    let output = LatencyReport::new(&Side::Output, 44100.0, 10, 20, 441, 0, 0.5);
+    assert_eq!(output.buffer_latency, 221);
+    assert_eq!(output.total_frames(), 251);
+    assert!((round_trip_ms(&input, &output) - (560.0 / 48.0 + 251.0 / 44.1)).abs() < 1e-9);
+
+    // Out of range IO cycle usages mean the whole cycle.
+    let full = LatencyReport::new(&Side::Output, 48000.0, 0, 0, 256, 0, 0.0);
+    assert_eq!(full.io_cycle_usage, 1.0);
+    assert_eq!(full.buffer_latency, 256);
+    assert_eq!(full.frames_to_ms(48), 1.0);
+
+    let bogus = LatencyReport::new(&Side::Input, 48000.0, u32::MAX, 16, 512, 8, 1.0);
+    assert_eq!(bogus.total_frames(), u32::MAX);
+
+    // 5 ms at 48 kHz is 240 frames.
+    assert_eq!(
+        buffer_frame_size_for_latency(5.0, 48000.0, (15.0, 4096.0)),
+        240
+    );
+    assert_eq!(
+        buffer_frame_size_for_latency(5.0, 44100.0, (15.0, 4096.0)),
+        221
+    );
+    assert_eq!(
+        buffer_frame_size_for_latency(0.1, 48000.0, (15.0, 4096.0)),
+        15
+    );
+    assert_eq!(
+        buffer_frame_size_for_latency(1000.0, 48000.0, (15.0, 4096.0)),
+        4096
+    );
+
+    let unknown_rate = LatencyReport::new(&Side::Input, 0.0, 0, 0, 256, 0, 1.0);
+    assert_eq!(unknown_rate.total_ms(), 0.0);
+}
@@ -0,0 +1,126 @@
+use super::super::sys::{noErr, AudioObjectID, AudioObjectPropertyAddress, OSStatus, UInt32};
+use super::audio_object::AudioObject;
+use super::sync::{call_guarded, lock};
+use std::collections::BTreeMap;
+use std::os::raw::c_void;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Callback = Mutex<Box<dyn FnMut(&[AudioObjectPropertyAddress]) + Send>>;
+
+// The callbacks of the live listeners, by the key registered as their client data. The HAL can
+// still call a listener while or after it's removed, so `listener_proc` looks its callback up
+// instead of dereferencing the client data, and holds it until the call returns.
+static CALLBACKS: Mutex<BTreeMap<usize, Arc<Callback>>> = Mutex::new(BTreeMap::new());
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(1);
+
+fn callbacks() -> MutexGuard<'static, BTreeMap<usize, Arc<Callback>>> {
+    lock(&CALLBACKS)
+}
+
+// A callback registered on some properties of an audio object. The callback is called on a HAL
+// thread with the addresses that changed, and is unregistered when the listener is dropped.
+pub struct PropertyListener {
+    object: AudioObject,
+    addresses: Vec<AudioObjectPropertyAddress>,
+    key: usize,
+}
+
+impl PropertyListener {
+    pub(crate) fn new<F>(
+        object: AudioObjectID,
+        addresses: Vec<AudioObjectPropertyAddress>,
+        callback: F,
+    ) -> Result<Self, OSStatus>
+    where
+        F: FnMut(&[AudioObjectPropertyAddress]) + Send + 'static,
+    {
+        let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+        let callback: Callback = Mutex::new(Box::new(callback));
+        callbacks().insert(key, Arc::new(callback));
+        let mut listener = Self {
+            object: AudioObject::new(object),
+            addresses: Vec::with_capacity(addresses.len()),
+            key,
+        };
+        for address in addresses {
+            let status = listener.object.add_property_listener(
+                &address,
+                Some(listener_proc),
+                listener.client_data(),
+            );
+            if status != noErr as OSStatus {
+                // Dropping the listener removes the ones added so far.
+                return Err(status);
+            }
+            listener.addresses.push(address);
+        }
+        Ok(listener)
+    }
+
+    fn client_data(&self) -> *mut c_void {
+        self.key as *mut c_void
+    }
+}
+
+impl Drop for PropertyListener {
+    fn drop(&mut self) {
+        for address in &self.addresses {
+            let _ = self.object.remove_property_listener(
+                address,
+                Some(listener_proc),
+                self.client_data(),
+            );
+        }
+        // A call already in progress keeps the callback until it returns.
+        callbacks().remove(&self.key);
+    }
+}
+
+extern "C" fn listener_proc(
+    _object: AudioObjectID,
+    number_addresses: UInt32,
+    addresses: *const AudioObjectPropertyAddress,
+    client_data: *mut c_void,
+) -> OSStatus {
+    let callback = match callbacks().get(&(client_data as usize)) {
+        Some(callback) => callback.clone(),
+        // The listener is gone.
+        None => return noErr as OSStatus,
+    };
+    let addresses = if addresses.is_null() {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(addresses, number_addresses as usize) }
+    };
+    call_guarded(&callback, |callback| callback(addresses));
+    noErr as OSStatus
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_listener_called_after_drop() {
+    use super::fixture::Fixture;
+    use super::property_address::{get_property_address, Property, Scope};
+    use std::sync::atomic::AtomicU32;
+
+    let address = get_property_address(Property::DeviceSampleRate, Scope::Global);
+    Fixture::default().replay(|| {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let listener = PropertyListener::new(70, vec![address], move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        let client_data = listener.client_data();
+        listener_proc(70, 1, &address, client_data);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // The HAL may still be calling the listener when it's dropped.
+        drop(listener);
+        listener_proc(70, 1, &address, client_data);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(Arc::strong_count(&calls), 1);
+    });
+}
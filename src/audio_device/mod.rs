@@ -1,22 +1,71 @@
+mod audio_box;
 mod audio_object;
+mod channel;
+mod clock;
+mod control;
+mod duplex;
+#[cfg(feature = "async")]
+mod events;
+#[cfg(feature = "fixture")]
+pub mod fixture;
+mod handle;
+mod hog;
+mod io_proc;
+mod latency;
+mod listener;
+mod object;
+mod plugin;
+mod process;
 mod property_address;
-
-use super::string::StringRef;
-use audio_object::AudioObject;
-use core_foundation_sys::string::CFStringRef;
-use coreaudio_sys::{
-    kAudioObjectSystemObject, kAudioObjectUnknown, noErr, AudioBuffer, AudioBufferList,
-    AudioObjectID, AudioStreamID, AudioValueRange, AudioValueTranslation, OSStatus,
+mod registry;
+mod state;
+mod stream;
+pub(crate) mod sync;
+mod tap;
+mod time;
+mod workgroup;
+
+use super::buffer_list::BufferList;
+use super::channel_layout::ChannelLayout;
+use super::sys::{
+    kAudioHardwareBadPropertySizeError, kAudioObjectSystemObject, kAudioObjectUnknown,
+    AudioObjectID, AudioStreamID, AudioValueRange, OSStatus,
+};
+pub use audio_box::AudioBox;
+pub use audio_object::AudioObject;
+pub use channel::Channel;
+pub use clock::{ClockDevice, ClockDomainGroup, ClockDomains, ClockRelation};
+pub use control::{
+    BooleanControl, Control, ControlKind, LevelControl, SelectorControl, SelectorItem,
+    SliderControl, StereoPanControl,
 };
+pub use duplex::{DuplexOptions, DuplexStats, DuplexStream};
+#[cfg(feature = "async")]
+pub use events::{DeviceEvent, PropertyStream, WaitForSampleRate};
+pub use handle::{DeviceHandle, DeviceInfo};
+pub use hog::{HogError, HogGuard};
+#[cfg(all(target_os = "macos", feature = "host"))]
+pub(crate) use io_proc::{frame_count, read_interleaved, write_interleaved};
+pub use io_proc::{IoCycle, IoProc};
+pub use latency::{buffer_frame_size_for_latency, round_trip_ms, LatencyReport};
+pub use listener::PropertyListener;
+pub use object::{Class, Object};
+pub use plugin::PlugIn;
+pub use process::Process;
 use property_address::{get_property_address, Property, Scope};
+use registry::RegistryCache;
+pub use registry::{DeviceRegistry, RegistryStats};
+pub use state::DeviceState;
 use std::fmt;
-use std::mem;
-use std::os::raw::c_void;
-use std::ptr;
-use std::slice;
-
-const NO_ERR: OSStatus = noErr as OSStatus;
+use std::sync::Arc;
+pub use stream::Stream;
+pub use tap::{
+    ProcessTap, TapDescription, TapMuteBehavior, AGGREGATE_TAP_LIST_KEY,
+    SUB_TAP_DRIFT_COMPENSATION_KEY, SUB_TAP_UID_KEY,
+};
+pub use workgroup::{OsWorkgroup, WorkgroupMembership};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Side {
     Input,
     Output,
@@ -44,9 +93,20 @@ impl From<&Side> for Scope {
     }
 }
 
-pub struct SystemDevice(AudioObject);
+// The system object. Clones share the cache of its `DeviceRegistry`, so a `SystemDevice` can be
+// put in an `Arc` or cloned into each thread that needs the devices.
+#[derive(Clone)]
+pub struct SystemDevice(AudioObject, Arc<RegistryCache>);
 
 impl SystemDevice {
+    pub(crate) fn new(object: AudioObject) -> Self {
+        Self(object, Arc::default())
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
     pub fn get_default_device(&self, s: &Side) -> Result<Device, OSStatus> {
         let address = get_property_address(
             match s {
@@ -70,11 +130,11 @@ impl SystemDevice {
 
 impl Default for SystemDevice {
     fn default() -> Self {
-        Self(AudioObject::new(kAudioObjectSystemObject))
+        Self::new(AudioObject::new(kAudioObjectSystemObject))
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct DeviceId(AudioObjectID);
 
 impl fmt::Display for DeviceId {
@@ -83,6 +143,17 @@ impl fmt::Display for DeviceId {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamId(AudioStreamID);
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Like `AudioObject`, a device is only an ID, so it can be sent to and shared with any thread.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Device(AudioObject);
 
 impl Device {
@@ -94,6 +165,10 @@ impl Device {
         DeviceId(self.0.id())
     }
 
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
     pub fn is_valid(&self) -> bool {
         self.id() != DeviceId(kAudioObjectUnknown)
     }
@@ -112,11 +187,7 @@ impl Device {
 
     pub fn channel_count(&self, s: &Side) -> Result<u32, OSStatus> {
         let buffers = self.stream_configuration(s)?;
-        let mut count = 0;
-        for buffer in buffers {
-            count += buffer.mNumberChannels;
-        }
-        Ok(count)
+        Ok(buffers.channel_count())
     }
 
     pub fn clock_domain(&self, s: &Side) -> Result<u32, OSStatus> {
@@ -131,9 +202,7 @@ impl Device {
 
     pub fn manufacturer(&self, s: &Side) -> Result<String, OSStatus> {
         let address = get_property_address(Property::DeviceManufacturer, Scope::from(s));
-        self.0
-            .get_property_data_common::<StringRef>(&address)
-            .map(|s| String::from_utf8_lossy(&s.to_utf8()).to_string())
+        self.0.get_property_string(&address)
     }
 
     pub fn model_uid(&self, s: Option<&Side>) -> Result<String, OSStatus> {
@@ -145,9 +214,7 @@ impl Device {
                 Scope::Global
             },
         );
-        self.0
-            .get_property_data_common::<StringRef>(&address)
-            .map(|s| String::from_utf8_lossy(&s.to_utf8()).to_string())
+        self.0.get_property_string(&address)
     }
 
     pub fn name(&self, s: Option<&Side>) -> Result<String, OSStatus> {
@@ -159,9 +226,43 @@ impl Device {
                 Scope::Global
             },
         );
+        self.0.get_property_string(&address)
+    }
+
+    pub fn preferred_channel_layout(&self, s: &Side) -> Result<ChannelLayout, OSStatus> {
+        let address = get_property_address(Property::DevicePreferredChannelLayout, Scope::from(s));
+        let bytes = self.0.get_property_array_common::<u8>(&address)?;
+        ChannelLayout::from_bytes(&bytes).ok_or(kAudioHardwareBadPropertySizeError as OSStatus)
+    }
+
+    pub fn set_preferred_channel_layout(
+        &self,
+        s: &Side,
+        layout: &ChannelLayout,
+    ) -> Result<(), OSStatus> {
+        let address = get_property_address(Property::DevicePreferredChannelLayout, Scope::from(s));
         self.0
-            .get_property_data_common::<StringRef>(&address)
-            .map(|s| String::from_utf8_lossy(&s.to_utf8()).to_string())
+            .set_property_array_common(&address, &layout.to_words())
+    }
+
+    // The 1-based channel numbers of the left and right channels of the stereo pair.
+    pub fn preferred_channels_for_stereo(&self, s: &Side) -> Result<(u32, u32), OSStatus> {
+        let address =
+            get_property_address(Property::DevicePreferredChannelsForStereo, Scope::from(s));
+        self.0
+            .get_property_data_common::<[u32; 2]>(&address)
+            .map(|[left, right]| (left, right))
+    }
+
+    pub fn set_preferred_channels_for_stereo(
+        &self,
+        s: &Side,
+        channels: (u32, u32),
+    ) -> Result<(), OSStatus> {
+        let address =
+            get_property_address(Property::DevicePreferredChannelsForStereo, Scope::from(s));
+        self.0
+            .set_property_data_common(&address, &[channels.0, channels.1])
     }
 
     pub fn sample_rate(&self, s: &Side) -> Result<f64, OSStatus> {
@@ -169,6 +270,18 @@ impl Device {
         self.0.get_property_data_common::<f64>(&address)
     }
 
+    // The rate should be within one of `sample_rate_ranges`. The change is asynchronous, so the
+    // new rate may not be reported right away.
+    pub fn set_sample_rate(&self, s: &Side, rate: f64) -> Result<(), OSStatus> {
+        let address = get_property_address(Property::DeviceSampleRate, Scope::from(s));
+        self.0.set_property_data_common(&address, &rate)
+    }
+
+    // The device's streams on this side, in the order of their buffers in the IO data.
+    pub fn stream_list(&self, s: &Side) -> Result<Vec<Stream>, OSStatus> {
+        Ok(self.streams(s)?.into_iter().map(Stream::new).collect())
+    }
+
     pub fn sample_rate_ranges(&self, s: &Side) -> Result<Vec<(f64, f64)>, OSStatus> {
         let address = get_property_address(Property::DeviceSampleRates, Scope::from(s));
         self.0
@@ -187,26 +300,9 @@ impl Device {
     }
 
     pub fn source_name(&self, s: &Side) -> Result<String, OSStatus> {
-        let mut source = self.source(s)?;
+        let source = self.source(s)?;
         let address = get_property_address(Property::DeviceSourceName, Scope::from(s));
-        let mut size = mem::size_of::<AudioValueTranslation>();
-        let mut name: CFStringRef = ptr::null();
-        let mut trl = AudioValueTranslation {
-            mInputData: &mut source as *mut u32 as *mut c_void,
-            mInputDataSize: mem::size_of::<u32>() as u32,
-            mOutputData: &mut name as *mut CFStringRef as *mut c_void,
-            mOutputDataSize: mem::size_of::<CFStringRef>() as u32,
-        };
-        let status = self
-            .0
-            .get_property_data_without_qualifier(&address, &mut size, &mut trl);
-        if status == NO_ERR {
-            let s = StringRef::new(name);
-            let utf8 = s.to_utf8();
-            Ok(String::from_utf8_lossy(&utf8).to_string())
-        } else {
-            Err(status)
-        }
+        self.0.get_property_translated_string(&address, source)
     }
 
     pub fn transport_type(&self, s: &Side) -> Result<u32, OSStatus> {
@@ -223,22 +319,13 @@ impl Device {
                 Scope::Global
             },
         );
-        self.0
-            .get_property_data_common::<StringRef>(&address)
-            .map(|s| String::from_utf8_lossy(&s.to_utf8()).to_string())
+        self.0.get_property_string(&address)
     }
 
-    fn stream_configuration(&self, s: &Side) -> Result<Vec<AudioBuffer>, OSStatus> {
+    fn stream_configuration(&self, s: &Side) -> Result<BufferList, OSStatus> {
         let address = get_property_address(Property::DeviceStreamConfiguration, Scope::from(s));
         let buffer = self.0.get_property_array_common::<u8>(&address)?;
-        let list = unsafe { &*(buffer.as_ptr() as *mut AudioBufferList) };
-        let s = unsafe {
-            slice::from_raw_parts(
-                list.mBuffers.as_ptr() as *const AudioBuffer,
-                list.mNumberBuffers as usize,
-            )
-        };
-        Ok(s.to_vec())
+        BufferList::from_bytes(&buffer).ok_or(kAudioHardwareBadPropertySizeError as OSStatus)
     }
 
     fn streams(&self, s: &Side) -> Result<Vec<AudioStreamID>, OSStatus> {
@@ -247,13 +334,15 @@ impl Device {
     }
 }
 
+// Needs the HAL.
+#[cfg(target_os = "macos")]
 #[test]
 fn test_default_devices() {
     check_device_is_in_scope(Side::Input);
     check_device_is_in_scope(Side::Output);
 
     fn check_device_is_in_scope(s: Side) {
-        use coreaudio_sys::kAudioHardwareBadObjectError;
+        use super::sys::kAudioHardwareBadObjectError;
         let system_device = SystemDevice::default();
         match system_device.get_default_device(&s) {
             Ok(device) => {
@@ -273,6 +362,8 @@ fn test_default_devices() {
     }
 }
 
+// Needs the HAL.
+#[cfg(target_os = "macos")]
 #[test]
 fn test_device_list() {
     let system_device = SystemDevice::default();
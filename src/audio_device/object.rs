@@ -0,0 +1,287 @@
+use super::super::sys::{
+    kAudioAggregateDeviceClassID, kAudioBooleanControlClassID, kAudioBoxClassID,
+    kAudioClockDeviceClassID, kAudioControlClassID, kAudioDeviceClassID,
+    kAudioEndPointDeviceClassID, kAudioLevelControlClassID, kAudioObjectClassID,
+    kAudioObjectUnknown, kAudioPlugInClassID, kAudioSelectorControlClassID,
+    kAudioSliderControlClassID, kAudioStereoPanControlClassID, kAudioStreamClassID,
+    kAudioSubDeviceClassID, kAudioSystemObjectClassID, kAudioTransportManagerClassID, AudioClassID,
+    AudioObjectID, OSStatus,
+};
+use super::audio_box::AudioBox;
+use super::audio_object::AudioObject;
+use super::clock::ClockDevice;
+use super::control::Control;
+use super::plugin::PlugIn;
+use super::process::Process;
+use super::property_address::{get_property_address, Property, Scope};
+use super::stream::Stream;
+use super::{Device, SystemDevice};
+
+// The class of an audio object, i.e., a `kAudio*ClassID`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    Object,
+    System,
+    PlugIn,
+    TransportManager,
+    Box,
+    Device,
+    ClockDevice,
+    EndPointDevice,
+    AggregateDevice,
+    SubDevice,
+    Stream,
+    Control,
+    LevelControl,
+    BooleanControl,
+    SelectorControl,
+    SliderControl,
+    StereoPanControl,
+    Process,
+    Other(AudioClassID),
+}
+
+// `kAudioProcessClassID` ('clnt') is new in the macOS 14 SDK.
+const PROCESS_CLASS_ID: AudioClassID = 0x636c_6e74;
+
+const CLASSES: &[(Class, AudioClassID)] = {
+    use Class::*;
+    &[
+        (Object, kAudioObjectClassID),
+        (System, kAudioSystemObjectClassID),
+        (PlugIn, kAudioPlugInClassID),
+        (TransportManager, kAudioTransportManagerClassID),
+        (Box, kAudioBoxClassID),
+        (Device, kAudioDeviceClassID),
+        (ClockDevice, kAudioClockDeviceClassID),
+        (EndPointDevice, kAudioEndPointDeviceClassID),
+        (AggregateDevice, kAudioAggregateDeviceClassID),
+        (SubDevice, kAudioSubDeviceClassID),
+        (Stream, kAudioStreamClassID),
+        (Control, kAudioControlClassID),
+        (LevelControl, kAudioLevelControlClassID),
+        (BooleanControl, kAudioBooleanControlClassID),
+        (SelectorControl, kAudioSelectorControlClassID),
+        (SliderControl, kAudioSliderControlClassID),
+        (StereoPanControl, kAudioStereoPanControlClassID),
+        (Process, PROCESS_CLASS_ID),
+    ]
+};
+
+impl Class {
+    // The class of the wrapper that objects of this class are downcast into, or `None` if the
+    // class isn't known to calx.
+    fn kind(self) -> Option<Class> {
+        match self {
+            Class::Object
+            | Class::System
+            | Class::PlugIn
+            | Class::Box
+            | Class::Device
+            | Class::ClockDevice
+            | Class::Stream
+            | Class::Control
+            | Class::Process => Some(self),
+            Class::TransportManager => Some(Class::PlugIn),
+            Class::EndPointDevice | Class::AggregateDevice | Class::SubDevice => {
+                Some(Class::Device)
+            }
+            Class::LevelControl
+            | Class::BooleanControl
+            | Class::SelectorControl
+            | Class::SliderControl
+            | Class::StereoPanControl => Some(Class::Control),
+            Class::Other(_) => None,
+        }
+    }
+}
+
+impl From<AudioClassID> for Class {
+    fn from(class: AudioClassID) -> Self {
+        CLASSES
+            .iter()
+            .find(|(_, c)| *c == class)
+            .map_or(Class::Other(class), |(c, _)| *c)
+    }
+}
+
+impl From<Class> for AudioClassID {
+    fn from(class: Class) -> Self {
+        match class {
+            Class::Other(c) => c,
+            _ => CLASSES
+                .iter()
+                .find(|(c, _)| *c == class)
+                .map(|(_, c)| *c)
+                .expect("every named class is listed in CLASSES"),
+        }
+    }
+}
+
+// An audio object downcast into the wrapper for its class.
+pub enum Object {
+    System(SystemDevice),
+    PlugIn(PlugIn),
+    Box(AudioBox),
+    Device(Device),
+    Clock(ClockDevice),
+    Stream(Stream),
+    Control(Control),
+    Process(Process),
+    Other(AudioObject),
+}
+
+impl AudioObject {
+    pub fn class(&self) -> Result<Class, OSStatus> {
+        let address = get_property_address(Property::ObjectClass, Scope::Global);
+        self.get_property_data_common::<AudioClassID>(&address)
+            .map(Class::from)
+    }
+
+    // The class that the object's class is derived from, e.g., `Class::Device` for an
+    // aggregate device.
+    pub fn base_class(&self) -> Result<Class, OSStatus> {
+        let address = get_property_address(Property::ObjectBaseClass, Scope::Global);
+        self.get_property_data_common::<AudioClassID>(&address)
+            .map(Class::from)
+    }
+
+    // The object that owns this one, or `None` for the system object.
+    pub fn owner(&self) -> Result<Option<AudioObject>, OSStatus> {
+        let address = get_property_address(Property::ObjectOwner, Scope::Global);
+        self.get_property_data_common::<AudioObjectID>(&address)
+            .map(|id| {
+                if id == kAudioObjectUnknown {
+                    None
+                } else {
+                    Some(AudioObject::new(id))
+                }
+            })
+    }
+
+    // The objects owned by this one whose class is one of `classes`, or all of them if
+    // `classes` is empty.
+    pub fn owned_objects(&self, classes: &[Class]) -> Result<Vec<AudioObject>, OSStatus> {
+        let address = get_property_address(Property::ObjectOwnedObjects, Scope::Global);
+        let classes: Vec<AudioClassID> = classes.iter().map(|c| AudioClassID::from(*c)).collect();
+        self.get_property_array_with_qualifier::<AudioClassID, AudioObjectID>(&address, &classes)
+            .map(|ids| ids.into_iter().map(AudioObject::new).collect())
+    }
+
+    pub fn downcast(self) -> Result<Object, OSStatus> {
+        // Objects of classes calx doesn't know, e.g., a concrete control class, are downcast by
+        // their base class.
+        let kind = match self.class()?.kind() {
+            Some(kind) => Some(kind),
+            None => self.base_class()?.kind(),
+        };
+        let id = self.id();
+        Ok(match kind {
+            Some(Class::System) => Object::System(SystemDevice::new(self)),
+            Some(Class::PlugIn) => Object::PlugIn(PlugIn::new(id)),
+            Some(Class::Box) => Object::Box(AudioBox::new(id)),
+            Some(Class::Device) => Object::Device(Device::new(id)),
+            Some(Class::ClockDevice) => Object::Clock(ClockDevice::new(id)),
+            Some(Class::Stream) => Object::Stream(Stream::new(id)),
+            Some(Class::Control) => Object::Control(Control::new(id)),
+            Some(Class::Process) => Object::Process(Process::new(id)),
+            _ => Object::Other(self),
+        })
+    }
+}
+
+#[test]
+fn test_class_conversion() {
+    for (class, value) in CLASSES.iter() {
+        assert_eq!(Class::from(*value), *class);
+        assert_eq!(AudioClassID::from(*class), *value);
+    }
+    assert_eq!(Class::from(0x766c_6d65), Class::Other(0x766c_6d65));
+    assert_eq!(Class::AggregateDevice.kind(), Some(Class::Device));
+    assert_eq!(Class::SliderControl.kind(), Some(Class::Control));
+    assert_eq!(Class::Other(0x766c_6d65).kind(), None);
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_object_tree() {
+    use super::super::sys::kAudioObjectSystemObject;
+    use super::fixture::Fixture;
+
+    const PLUG_IN: u32 = 80;
+    const DEVICE: u32 = 81;
+    const STREAM: u32 = 82;
+    const VOLUME: u32 = 83;
+    // 'vlme', a concrete level control class.
+    const VOLUME_CLASS: u32 = 0x766c_6d65;
+
+    let mut fixture = Fixture::default();
+    let class = get_property_address(Property::ObjectClass, Scope::Global);
+    let base_class = get_property_address(Property::ObjectBaseClass, Scope::Global);
+    let owner = get_property_address(Property::ObjectOwner, Scope::Global);
+    for (object, c, o) in [
+        (kAudioObjectSystemObject, kAudioSystemObjectClassID, 0),
+        (PLUG_IN, kAudioPlugInClassID, kAudioObjectSystemObject),
+        (DEVICE, kAudioAggregateDeviceClassID, PLUG_IN),
+        (STREAM, kAudioStreamClassID, DEVICE),
+        (VOLUME, VOLUME_CLASS, DEVICE),
+    ]
+    .iter()
+    {
+        fixture.insert_data(*object, &class, &[*c]);
+        fixture.insert_data(*object, &owner, &[*o]);
+    }
+    fixture.insert_data(VOLUME, &base_class, &[kAudioLevelControlClassID]);
+    let owned = get_property_address(Property::ObjectOwnedObjects, Scope::Global);
+    fixture.insert_qualified_data::<u32, u32>(DEVICE, &owned, &[], &[STREAM, VOLUME]);
+    fixture.insert_qualified_data(DEVICE, &owned, &[kAudioStreamClassID], &[STREAM]);
+
+    fixture.replay(|| {
+        let device = AudioObject::new(DEVICE);
+        assert_eq!(device.class().unwrap(), Class::AggregateDevice);
+        assert_eq!(device.owner().unwrap(), Some(AudioObject::new(PLUG_IN)));
+        assert_eq!(
+            device.owned_objects(&[]).unwrap(),
+            vec![AudioObject::new(STREAM), AudioObject::new(VOLUME)]
+        );
+        assert_eq!(
+            device.owned_objects(&[Class::Stream]).unwrap(),
+            vec![AudioObject::new(STREAM)]
+        );
+
+        // Walk up to the system object.
+        let mut path = vec![];
+        let mut object = Some(AudioObject::new(STREAM));
+        while let Some(o) = object {
+            path.push(o.id());
+            object = o.owner().unwrap();
+        }
+        assert_eq!(
+            path,
+            vec![STREAM, DEVICE, PLUG_IN, kAudioObjectSystemObject]
+        );
+
+        match device.downcast().unwrap() {
+            Object::Device(d) => assert_eq!(d.id(), super::DeviceId(DEVICE)),
+            _ => panic!("not downcast into a device"),
+        }
+        assert!(matches!(
+            AudioObject::new(VOLUME).downcast().unwrap(),
+            Object::Control(_)
+        ));
+        assert!(matches!(
+            AudioObject::new(STREAM).downcast().unwrap(),
+            Object::Stream(_)
+        ));
+        assert!(matches!(
+            AudioObject::new(PLUG_IN).downcast().unwrap(),
+            Object::PlugIn(_)
+        ));
+        assert!(matches!(
+            AudioObject::new(kAudioObjectSystemObject)
+                .downcast()
+                .unwrap(),
+            Object::System(_)
+        ));
+    });
+}
@@ -0,0 +1,93 @@
+use super::super::sys::{kAudioObjectUnknown, AudioObjectID, OSStatus};
+use super::audio_box::AudioBox;
+use super::audio_object::AudioObject;
+use super::clock::ClockDevice;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, SystemDevice};
+
+// A HAL plug-in, which publishes boxes, devices and clocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlugIn(AudioObject);
+
+impl PlugIn {
+    pub(crate) fn new(id: AudioObjectID) -> Self {
+        Self(AudioObject::new(id))
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
+    // The bundle ID of the plug-in, e.g., "com.apple.audio.CoreAudio".
+    pub fn bundle_id(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::PlugInBundleID, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn name(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::DeviceName, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn manufacturer(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::DeviceManufacturer, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn devices(&self) -> Result<Vec<Device>, OSStatus> {
+        let address = get_property_address(Property::PlugInDeviceList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(Device::new).collect())
+    }
+
+    pub fn boxes(&self) -> Result<Vec<AudioBox>, OSStatus> {
+        let address = get_property_address(Property::PlugInBoxList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(AudioBox::new).collect())
+    }
+
+    pub fn clock_devices(&self) -> Result<Vec<ClockDevice>, OSStatus> {
+        let address = get_property_address(Property::PlugInClockDeviceList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(ClockDevice::new).collect())
+    }
+}
+
+impl SystemDevice {
+    pub fn plug_ins(&self) -> Result<Vec<PlugIn>, OSStatus> {
+        let address = get_property_address(Property::PlugInList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(PlugIn::new).collect())
+    }
+
+    // The loaded plug-in with this bundle ID, if any.
+    pub fn plug_in_for_bundle_id(&self, bundle_id: &str) -> Result<Option<PlugIn>, OSStatus> {
+        let address = get_property_address(Property::TranslateBundleIDToPlugIn, Scope::Global);
+        self.0
+            .get_property_data_with_string_qualifier::<AudioObjectID>(&address, bundle_id)
+            .map(|id| {
+                if id == kAudioObjectUnknown {
+                    None
+                } else {
+                    Some(PlugIn::new(id))
+                }
+            })
+    }
+
+    pub fn device_for_uid(&self, uid: &str) -> Result<Option<Device>, OSStatus> {
+        let address = get_property_address(Property::TranslateUIDToDevice, Scope::Global);
+        self.0
+            .get_property_data_with_string_qualifier::<AudioObjectID>(&address, uid)
+            .map(|id| {
+                if id == kAudioObjectUnknown {
+                    None
+                } else {
+                    Some(Device::new(id))
+                }
+            })
+    }
+}
@@ -0,0 +1,167 @@
+use super::super::sys::{kAudioObjectUnknown, pid_t, AudioObjectID, OSStatus};
+use super::audio_object::AudioObject;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, Side, SystemDevice};
+
+// A process that is a client of the HAL, available from macOS 14.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Process(AudioObject);
+
+impl Process {
+    pub(crate) fn new(id: AudioObjectID) -> Self {
+        Self(AudioObject::new(id))
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
+    pub fn pid(&self) -> Result<pid_t, OSStatus> {
+        let address = get_property_address(Property::ProcessPID, Scope::Global);
+        self.0.get_property_data_common::<pid_t>(&address)
+    }
+
+    // Processes without a bundle, e.g., command line tools, have an empty bundle ID.
+    pub fn bundle_id(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::ProcessBundleID, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    // Whether IO is running in the process on any device.
+    pub fn is_running(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::ProcessIsRunning)
+    }
+
+    pub fn is_running_input(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::ProcessIsRunningInput)
+    }
+
+    pub fn is_running_output(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::ProcessIsRunningOutput)
+    }
+
+    // The devices the process is running IO on for this side.
+    pub fn devices(&self, s: &Side) -> Result<Vec<Device>, OSStatus> {
+        let address = get_property_address(Property::ProcessDevices, Scope::from(s));
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(Device::new).collect())
+    }
+
+    fn get_bool(&self, property: Property) -> Result<bool, OSStatus> {
+        let address = get_property_address(property, Scope::Global);
+        self.0
+            .get_property_data_common::<u32>(&address)
+            .map(|value| value != 0)
+    }
+}
+
+impl SystemDevice {
+    pub fn processes(&self) -> Result<Vec<Process>, OSStatus> {
+        let address = get_property_address(Property::ProcessObjectList, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(Process::new).collect())
+    }
+
+    // The process object of `pid`, if it's a client of the HAL.
+    pub fn process_for_pid(&self, pid: pid_t) -> Result<Option<Process>, OSStatus> {
+        let address = get_property_address(Property::TranslatePIDToProcessObject, Scope::Global);
+        let ids = self
+            .0
+            .get_property_array_with_qualifier::<pid_t, AudioObjectID>(&address, &[pid])?;
+        Ok(ids
+            .first()
+            .filter(|id| **id != kAudioObjectUnknown)
+            .map(|id| Process::new(*id)))
+    }
+
+    // The processes running IO on this side of `device`, e.g., the apps recording from a
+    // microphone. A process whose devices can't be read, e.g., one that just exited, is skipped.
+    pub fn processes_using(&self, device: &Device, s: &Side) -> Result<Vec<Process>, OSStatus> {
+        Ok(self
+            .processes()?
+            .into_iter()
+            .filter(|process| {
+                process
+                    .devices(s)
+                    .is_ok_and(|devices| devices.contains(device))
+            })
+            .collect())
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_processes() {
+    use super::super::sys::kAudioObjectSystemObject;
+    use super::fixture::Fixture;
+
+    const MIC: u32 = 130;
+    const SPEAKER: u32 = 131;
+    // The devices of the last process can't be read.
+    const PROCESSES: [u32; 3] = [132, 133, 134];
+    let mut fixture = Fixture::default();
+    let global = |property| get_property_address(property, Scope::Global);
+    fixture.insert_data(
+        kAudioObjectSystemObject,
+        &global(Property::ProcessObjectList),
+        &PROCESSES,
+    );
+    fixture.insert_qualified_data(
+        kAudioObjectSystemObject,
+        &global(Property::TranslatePIDToProcessObject),
+        &[4242 as pid_t],
+        &[PROCESSES[0]],
+    );
+    fixture.insert_qualified_data(
+        kAudioObjectSystemObject,
+        &global(Property::TranslatePIDToProcessObject),
+        &[1 as pid_t],
+        &[kAudioObjectUnknown],
+    );
+    fixture.insert_data(
+        PROCESSES[0],
+        &global(Property::ProcessPID),
+        &[4242 as pid_t],
+    );
+    fixture.insert_string(
+        PROCESSES[0],
+        &global(Property::ProcessBundleID),
+        "com.example.Recorder",
+    );
+    fixture.insert_data(
+        PROCESSES[0],
+        &global(Property::ProcessIsRunningInput),
+        &[1_u32],
+    );
+    for (process, input, output) in [
+        (PROCESSES[0], vec![MIC], vec![]),
+        (PROCESSES[1], vec![], vec![SPEAKER]),
+    ]
+    .iter()
+    {
+        let devices = |s| get_property_address(Property::ProcessDevices, s);
+        fixture.insert_data(*process, &devices(Scope::Input), input);
+        fixture.insert_data(*process, &devices(Scope::Output), output);
+    }
+
+    fixture.replay(|| {
+        let system = SystemDevice::default();
+        let recorder = system.process_for_pid(4242).unwrap().unwrap();
+        assert_eq!(recorder.pid().unwrap(), 4242);
+        assert_eq!(recorder.bundle_id().unwrap(), "com.example.Recorder");
+        assert!(recorder.is_running_input().unwrap());
+        assert_eq!(system.process_for_pid(1).unwrap(), None);
+
+        let mic = Device::new(MIC);
+        assert_eq!(
+            system.processes_using(&mic, &Side::Input).unwrap(),
+            vec![recorder]
+        );
+        assert!(system
+            .processes_using(&mic, &Side::Output)
+            .unwrap()
+            .is_empty());
+    });
+}
@@ -1,17 +1,70 @@
-use coreaudio_sys::{
-    kAudioDevicePropertyAvailableNominalSampleRates, kAudioDevicePropertyBufferFrameSizeRange,
-    kAudioDevicePropertyClockDomain, kAudioDevicePropertyDataSource,
-    kAudioDevicePropertyDataSourceNameForIDCFString, kAudioDevicePropertyDeviceUID,
-    kAudioDevicePropertyLatency, kAudioDevicePropertyModelUID,
-    kAudioDevicePropertyNominalSampleRate, kAudioDevicePropertyStreamConfiguration,
-    kAudioDevicePropertyStreams, kAudioDevicePropertyTransportType,
-    kAudioHardwarePropertyDefaultInputDevice, kAudioHardwarePropertyDefaultOutputDevice,
-    kAudioHardwarePropertyDevices, kAudioObjectPropertyElementMaster,
-    kAudioObjectPropertyManufacturer, kAudioObjectPropertyName, kAudioObjectPropertyScopeGlobal,
-    kAudioObjectPropertyScopeInput, kAudioObjectPropertyScopeOutput, AudioObjectPropertyAddress,
+use super::super::sys::{
+    kAudioBooleanControlPropertyValue, kAudioBoxPropertyAcquired,
+    kAudioBoxPropertyAcquisitionFailed, kAudioBoxPropertyBoxUID, kAudioBoxPropertyClockDeviceList,
+    kAudioBoxPropertyDeviceList, kAudioBoxPropertyHasAudio, kAudioBoxPropertyHasMIDI,
+    kAudioBoxPropertyHasVideo, kAudioBoxPropertyIsProtected, kAudioBoxPropertyTransportType,
+    kAudioClockDevicePropertyAvailableNominalSampleRates, kAudioClockDevicePropertyClockDomain,
+    kAudioClockDevicePropertyDeviceIsAlive, kAudioClockDevicePropertyDeviceIsRunning,
+    kAudioClockDevicePropertyDeviceUID, kAudioClockDevicePropertyLatency,
+    kAudioClockDevicePropertyNominalSampleRate, kAudioClockDevicePropertyTransportType,
+    kAudioControlPropertyElement, kAudioControlPropertyScope, kAudioDeviceProcessorOverload,
+    kAudioDevicePropertyAvailableNominalSampleRates, kAudioDevicePropertyBufferFrameSize,
+    kAudioDevicePropertyBufferFrameSizeRange, kAudioDevicePropertyClockDomain,
+    kAudioDevicePropertyDataSource, kAudioDevicePropertyDataSourceNameForIDCFString,
+    kAudioDevicePropertyDeviceCanBeDefaultDevice,
+    kAudioDevicePropertyDeviceCanBeDefaultSystemDevice, kAudioDevicePropertyDeviceIsAlive,
+    kAudioDevicePropertyDeviceIsRunning, kAudioDevicePropertyDeviceIsRunningSomewhere,
+    kAudioDevicePropertyDeviceUID, kAudioDevicePropertyHogMode, kAudioDevicePropertyIOCycleUsage,
+    kAudioDevicePropertyIsHidden, kAudioDevicePropertyLatency, kAudioDevicePropertyModelUID,
+    kAudioDevicePropertyNominalSampleRate, kAudioDevicePropertyPreferredChannelLayout,
+    kAudioDevicePropertyPreferredChannelsForStereo, kAudioDevicePropertySafetyOffset,
+    kAudioDevicePropertyStreamConfiguration, kAudioDevicePropertyStreams,
+    kAudioDevicePropertyTransportType, kAudioDevicePropertyUsesVariableBufferFrameSizes,
+    kAudioDevicePropertyZeroTimeStampPeriod, kAudioHardwarePropertyBoxList,
+    kAudioHardwarePropertyClockDeviceList, kAudioHardwarePropertyDefaultInputDevice,
+    kAudioHardwarePropertyDefaultOutputDevice, kAudioHardwarePropertyDevices,
+    kAudioHardwarePropertyPlugInList, kAudioHardwarePropertyTranslateBundleIDToPlugIn,
+    kAudioHardwarePropertyTranslateUIDToBox, kAudioHardwarePropertyTranslateUIDToClockDevice,
+    kAudioHardwarePropertyTranslateUIDToDevice, kAudioLevelControlPropertyConvertDecibelsToScalar,
+    kAudioLevelControlPropertyConvertScalarToDecibels, kAudioLevelControlPropertyDecibelRange,
+    kAudioLevelControlPropertyDecibelValue, kAudioLevelControlPropertyScalarValue,
+    kAudioObjectPropertyBaseClass, kAudioObjectPropertyClass, kAudioObjectPropertyControlList,
+    kAudioObjectPropertyElementCategoryName, kAudioObjectPropertyElementMaster,
+    kAudioObjectPropertyElementName, kAudioObjectPropertyElementNumberName,
+    kAudioObjectPropertyManufacturer, kAudioObjectPropertyName, kAudioObjectPropertyOwnedObjects,
+    kAudioObjectPropertyOwner, kAudioObjectPropertyScopeGlobal, kAudioObjectPropertyScopeInput,
+    kAudioObjectPropertyScopeOutput, kAudioPlugInPropertyBoxList, kAudioPlugInPropertyBundleID,
+    kAudioPlugInPropertyClockDeviceList, kAudioPlugInPropertyDeviceList,
+    kAudioSelectorControlPropertyAvailableItems, kAudioSelectorControlPropertyCurrentItem,
+    kAudioSelectorControlPropertyItemName, kAudioSliderControlPropertyRange,
+    kAudioSliderControlPropertyValue, kAudioStereoPanControlPropertyPanningChannels,
+    kAudioStereoPanControlPropertyValue, kAudioStreamPropertyDirection,
+    kAudioStreamPropertyLatency, kAudioStreamPropertyStartingChannel,
+    kAudioStreamPropertyVirtualFormat, AudioObjectPropertyAddress, AudioObjectPropertyElement,
     AudioObjectPropertyScope, AudioObjectPropertySelector,
 };
 
+// `kAudioDevicePropertyIOThreadOSWorkgroup` ('oswg') is missing from the SDKs older than
+// macOS 11 that coreaudio-sys may be generated from.
+const IO_THREAD_OS_WORKGROUP: AudioObjectPropertySelector = 0x6f73_7767;
+
+// The tap selectors are new in the macOS 14.2 SDK.
+const TAP_UID: AudioObjectPropertySelector = 0x7475_6964; // 'tuid'
+const TAP_FORMAT: AudioObjectPropertySelector = 0x7466_6d74; // 'tfmt'
+const AGGREGATE_TAP_LIST: AudioObjectPropertySelector = 0x7461_7023; // 'tap#'
+const AGGREGATE_ACTIVE_SUB_TAPS: AudioObjectPropertySelector = 0x6174_6170; // 'atap'
+
+// The process object selectors are new in the macOS 14 SDK.
+const PROCESS_OBJECT_LIST: AudioObjectPropertySelector = 0x7072_7323; // 'prs#'
+const TRANSLATE_PID_TO_PROCESS_OBJECT: AudioObjectPropertySelector = 0x6964_3270; // 'id2p'
+const PROCESS_PID: AudioObjectPropertySelector = 0x7070_6964; // 'ppid'
+const PROCESS_BUNDLE_ID: AudioObjectPropertySelector = 0x7062_6964; // 'pbid'
+const PROCESS_DEVICES: AudioObjectPropertySelector = 0x7064_7623; // 'pdv#'
+const PROCESS_IS_RUNNING: AudioObjectPropertySelector = 0x7069_723f; // 'pir?'
+const PROCESS_IS_RUNNING_INPUT: AudioObjectPropertySelector = 0x7069_7269; // 'piri'
+const PROCESS_IS_RUNNING_OUTPUT: AudioObjectPropertySelector = 0x7069_726f; // 'piro'
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Property {
     DefaultInputDevice,
     DefaultOutputDevice,
@@ -30,6 +83,90 @@ pub enum Property {
     DeviceStreams,
     DeviceUID,
     TransportType,
+    DevicePreferredChannelLayout,
+    DevicePreferredChannelsForStereo,
+    ElementName,
+    ElementCategoryName,
+    ElementNumberName,
+    StreamStartingChannel,
+    DeviceHogMode,
+    DeviceIsAlive,
+    DeviceIsRunning,
+    DeviceIsRunningSomewhere,
+    DeviceIsHidden,
+    DeviceCanBeDefault,
+    DeviceCanBeDefaultSystem,
+    DeviceSafetyOffset,
+    DeviceBufferFrameSize,
+    DeviceIOCycleUsage,
+    StreamLatency,
+    DeviceUsesVariableBufferFrameSizes,
+    DeviceIOThreadOSWorkgroup,
+    ObjectClass,
+    ObjectBaseClass,
+    ObjectOwner,
+    ObjectOwnedObjects,
+    StreamDirection,
+    ObjectControlList,
+    ControlScope,
+    ControlElement,
+    LevelControlScalarValue,
+    LevelControlDecibelValue,
+    LevelControlDecibelRange,
+    LevelControlScalarToDecibels,
+    LevelControlDecibelsToScalar,
+    BooleanControlValue,
+    SelectorControlCurrentItem,
+    SelectorControlAvailableItems,
+    SelectorControlItemName,
+    SliderControlValue,
+    SliderControlRange,
+    StereoPanControlValue,
+    StereoPanControlPanningChannels,
+    PlugInList,
+    TranslateBundleIDToPlugIn,
+    BoxList,
+    TranslateUIDToBox,
+    TranslateUIDToDevice,
+    PlugInBundleID,
+    PlugInDeviceList,
+    PlugInBoxList,
+    PlugInClockDeviceList,
+    BoxUID,
+    BoxTransportType,
+    BoxHasAudio,
+    BoxHasVideo,
+    BoxHasMIDI,
+    BoxIsProtected,
+    BoxAcquired,
+    BoxAcquisitionFailed,
+    BoxDeviceList,
+    BoxClockDeviceList,
+    ClockDeviceList,
+    TranslateUIDToClockDevice,
+    ClockDeviceUID,
+    ClockDeviceTransportType,
+    ClockDeviceClockDomain,
+    ClockDeviceIsAlive,
+    ClockDeviceIsRunning,
+    ClockDeviceLatency,
+    ClockDeviceSampleRate,
+    ClockDeviceSampleRates,
+    TapUID,
+    TapFormat,
+    AggregateTapList,
+    AggregateActiveSubTaps,
+    ProcessObjectList,
+    TranslatePIDToProcessObject,
+    ProcessPID,
+    ProcessBundleID,
+    ProcessDevices,
+    ProcessIsRunning,
+    ProcessIsRunningInput,
+    ProcessIsRunningOutput,
+    DeviceZeroTimeStampPeriod,
+    DeviceProcessorOverload,
+    StreamVirtualFormat,
 }
 
 impl From<Property> for AudioObjectPropertySelector {
@@ -55,6 +192,104 @@ impl From<Property> for AudioObjectPropertySelector {
             Property::DeviceStreams => kAudioDevicePropertyStreams,
             Property::DeviceUID => kAudioDevicePropertyDeviceUID,
             Property::TransportType => kAudioDevicePropertyTransportType,
+            Property::DevicePreferredChannelLayout => kAudioDevicePropertyPreferredChannelLayout,
+            Property::DevicePreferredChannelsForStereo => {
+                kAudioDevicePropertyPreferredChannelsForStereo
+            }
+            Property::ElementName => kAudioObjectPropertyElementName,
+            Property::ElementCategoryName => kAudioObjectPropertyElementCategoryName,
+            Property::ElementNumberName => kAudioObjectPropertyElementNumberName,
+            Property::StreamStartingChannel => kAudioStreamPropertyStartingChannel,
+            Property::DeviceHogMode => kAudioDevicePropertyHogMode,
+            Property::DeviceIsAlive => kAudioDevicePropertyDeviceIsAlive,
+            Property::DeviceIsRunning => kAudioDevicePropertyDeviceIsRunning,
+            Property::DeviceIsRunningSomewhere => kAudioDevicePropertyDeviceIsRunningSomewhere,
+            Property::DeviceIsHidden => kAudioDevicePropertyIsHidden,
+            Property::DeviceCanBeDefault => kAudioDevicePropertyDeviceCanBeDefaultDevice,
+            Property::DeviceCanBeDefaultSystem => {
+                kAudioDevicePropertyDeviceCanBeDefaultSystemDevice
+            }
+            Property::DeviceSafetyOffset => kAudioDevicePropertySafetyOffset,
+            Property::DeviceBufferFrameSize => kAudioDevicePropertyBufferFrameSize,
+            Property::DeviceIOCycleUsage => kAudioDevicePropertyIOCycleUsage,
+            Property::StreamLatency => kAudioStreamPropertyLatency,
+            Property::DeviceUsesVariableBufferFrameSizes => {
+                kAudioDevicePropertyUsesVariableBufferFrameSizes
+            }
+            Property::DeviceIOThreadOSWorkgroup => IO_THREAD_OS_WORKGROUP,
+            Property::ObjectClass => kAudioObjectPropertyClass,
+            Property::ObjectBaseClass => kAudioObjectPropertyBaseClass,
+            Property::ObjectOwner => kAudioObjectPropertyOwner,
+            Property::ObjectOwnedObjects => kAudioObjectPropertyOwnedObjects,
+            Property::StreamDirection => kAudioStreamPropertyDirection,
+            Property::ObjectControlList => kAudioObjectPropertyControlList,
+            Property::ControlScope => kAudioControlPropertyScope,
+            Property::ControlElement => kAudioControlPropertyElement,
+            Property::LevelControlScalarValue => kAudioLevelControlPropertyScalarValue,
+            Property::LevelControlDecibelValue => kAudioLevelControlPropertyDecibelValue,
+            Property::LevelControlDecibelRange => kAudioLevelControlPropertyDecibelRange,
+            Property::LevelControlScalarToDecibels => {
+                kAudioLevelControlPropertyConvertScalarToDecibels
+            }
+            Property::LevelControlDecibelsToScalar => {
+                kAudioLevelControlPropertyConvertDecibelsToScalar
+            }
+            Property::BooleanControlValue => kAudioBooleanControlPropertyValue,
+            Property::SelectorControlCurrentItem => kAudioSelectorControlPropertyCurrentItem,
+            Property::SelectorControlAvailableItems => kAudioSelectorControlPropertyAvailableItems,
+            Property::SelectorControlItemName => kAudioSelectorControlPropertyItemName,
+            Property::SliderControlValue => kAudioSliderControlPropertyValue,
+            Property::SliderControlRange => kAudioSliderControlPropertyRange,
+            Property::StereoPanControlValue => kAudioStereoPanControlPropertyValue,
+            Property::StereoPanControlPanningChannels => {
+                kAudioStereoPanControlPropertyPanningChannels
+            }
+            Property::PlugInList => kAudioHardwarePropertyPlugInList,
+            Property::TranslateBundleIDToPlugIn => kAudioHardwarePropertyTranslateBundleIDToPlugIn,
+            Property::BoxList => kAudioHardwarePropertyBoxList,
+            Property::TranslateUIDToBox => kAudioHardwarePropertyTranslateUIDToBox,
+            Property::TranslateUIDToDevice => kAudioHardwarePropertyTranslateUIDToDevice,
+            Property::PlugInBundleID => kAudioPlugInPropertyBundleID,
+            Property::PlugInDeviceList => kAudioPlugInPropertyDeviceList,
+            Property::PlugInBoxList => kAudioPlugInPropertyBoxList,
+            Property::PlugInClockDeviceList => kAudioPlugInPropertyClockDeviceList,
+            Property::BoxUID => kAudioBoxPropertyBoxUID,
+            Property::BoxTransportType => kAudioBoxPropertyTransportType,
+            Property::BoxHasAudio => kAudioBoxPropertyHasAudio,
+            Property::BoxHasVideo => kAudioBoxPropertyHasVideo,
+            Property::BoxHasMIDI => kAudioBoxPropertyHasMIDI,
+            Property::BoxIsProtected => kAudioBoxPropertyIsProtected,
+            Property::BoxAcquired => kAudioBoxPropertyAcquired,
+            Property::BoxAcquisitionFailed => kAudioBoxPropertyAcquisitionFailed,
+            Property::BoxDeviceList => kAudioBoxPropertyDeviceList,
+            Property::BoxClockDeviceList => kAudioBoxPropertyClockDeviceList,
+            Property::ClockDeviceList => kAudioHardwarePropertyClockDeviceList,
+            Property::TranslateUIDToClockDevice => kAudioHardwarePropertyTranslateUIDToClockDevice,
+            Property::ClockDeviceUID => kAudioClockDevicePropertyDeviceUID,
+            Property::ClockDeviceTransportType => kAudioClockDevicePropertyTransportType,
+            Property::ClockDeviceClockDomain => kAudioClockDevicePropertyClockDomain,
+            Property::ClockDeviceIsAlive => kAudioClockDevicePropertyDeviceIsAlive,
+            Property::ClockDeviceIsRunning => kAudioClockDevicePropertyDeviceIsRunning,
+            Property::ClockDeviceLatency => kAudioClockDevicePropertyLatency,
+            Property::ClockDeviceSampleRate => kAudioClockDevicePropertyNominalSampleRate,
+            Property::ClockDeviceSampleRates => {
+                kAudioClockDevicePropertyAvailableNominalSampleRates
+            }
+            Property::TapUID => TAP_UID,
+            Property::TapFormat => TAP_FORMAT,
+            Property::AggregateTapList => AGGREGATE_TAP_LIST,
+            Property::AggregateActiveSubTaps => AGGREGATE_ACTIVE_SUB_TAPS,
+            Property::ProcessObjectList => PROCESS_OBJECT_LIST,
+            Property::TranslatePIDToProcessObject => TRANSLATE_PID_TO_PROCESS_OBJECT,
+            Property::ProcessPID => PROCESS_PID,
+            Property::ProcessBundleID => PROCESS_BUNDLE_ID,
+            Property::ProcessDevices => PROCESS_DEVICES,
+            Property::ProcessIsRunning => PROCESS_IS_RUNNING,
+            Property::ProcessIsRunningInput => PROCESS_IS_RUNNING_INPUT,
+            Property::ProcessIsRunningOutput => PROCESS_IS_RUNNING_OUTPUT,
+            Property::DeviceZeroTimeStampPeriod => kAudioDevicePropertyZeroTimeStampPeriod,
+            Property::DeviceProcessorOverload => kAudioDeviceProcessorOverload,
+            Property::StreamVirtualFormat => kAudioStreamPropertyVirtualFormat,
         }
     }
 }
@@ -76,9 +311,18 @@ impl From<Scope> for AudioObjectPropertyScope {
 }
 
 pub fn get_property_address(property: Property, scope: Scope) -> AudioObjectPropertyAddress {
+    get_element_property_address(property, scope, kAudioObjectPropertyElementMaster)
+}
+
+// The address of a property of a single element, e.g., a channel of a device.
+pub fn get_element_property_address(
+    property: Property,
+    scope: Scope,
+    element: AudioObjectPropertyElement,
+) -> AudioObjectPropertyAddress {
     AudioObjectPropertyAddress {
         mSelector: AudioObjectPropertySelector::from(property),
         mScope: AudioObjectPropertyScope::from(scope),
-        mElement: kAudioObjectPropertyElementMaster,
+        mElement: element,
     }
 }
@@ -0,0 +1,403 @@
+use super::super::sys::{AudioObjectID, AudioObjectPropertyAddress, OSStatus};
+use super::handle::DeviceHandle;
+use super::listener::PropertyListener;
+use super::property_address::{get_property_address, Property, Scope};
+use super::sync::lock;
+use super::{AudioObject, Device, Side, SystemDevice};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+// The device properties that can change while the device is alive. Other properties, e.g., the
+// UID, are kept until the device is removed.
+const MUTABLE_PROPERTIES: [Property; 5] = [
+    Property::DeviceName,
+    Property::DeviceSampleRate,
+    Property::DeviceSource,
+    Property::DeviceStreamConfiguration,
+    Property::DeviceStreams,
+];
+
+const SYSTEM_PROPERTIES: [Property; 3] = [
+    Property::Devices,
+    Property::DefaultInputDevice,
+    Property::DefaultOutputDevice,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RegistryStats {
+    // Queries answered from the cache.
+    pub hits: u64,
+    // Queries that went to the HAL.
+    pub misses: u64,
+    // Cached values dropped because the HAL reported a change.
+    pub invalidations: u64,
+    pub entries: usize,
+}
+
+impl RegistryStats {
+    pub fn hit_rate(&self) -> f64 {
+        let queries = self.hits + self.misses;
+        if queries == 0 {
+            0.0
+        } else {
+            self.hits as f64 / queries as f64
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Key {
+    object: AudioObjectID,
+    selector: u32,
+    scope: u32,
+}
+
+impl Key {
+    fn new(object: AudioObjectID, address: &AudioObjectPropertyAddress) -> Self {
+        Self {
+            object,
+            selector: address.mSelector,
+            scope: address.mScope,
+        }
+    }
+}
+
+// What the listeners share with the registry. The listeners are owned by the registry, so they
+// don't keep it alive.
+#[derive(Default)]
+struct Shared {
+    values: Mutex<HashMap<Key, Box<dyn Any + Send>>>,
+    // Bumped on every change, so a value read while a change is reported isn't cached.
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl Shared {
+    // Drop the values `remove` picks, and return how many there were.
+    fn remove<F: Fn(&Key) -> bool>(&self, remove: F) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let mut values = lock(&self.values);
+        let count = values.len();
+        values.retain(|key, _| !remove(key));
+        (count - values.len()) as u64
+    }
+
+    // The HAL may report a change in one scope for a value read from another, e.g., a sample
+    // rate set through the global scope, so every scope of a changed property is dropped.
+    fn invalidate(&self, object: AudioObjectID, addresses: &[AudioObjectPropertyAddress]) {
+        let removed = self.remove(|key| {
+            key.object == object
+                && addresses
+                    .iter()
+                    .any(|address| address.mSelector == key.selector)
+        });
+        self.invalidations.fetch_add(removed, Ordering::Relaxed);
+    }
+}
+
+// The cache of a `SystemDevice`, shared by its clones and the registries built on them.
+#[derive(Default)]
+pub(crate) struct RegistryCache {
+    shared: Arc<Shared>,
+    listeners: Mutex<HashMap<AudioObjectID, PropertyListener>>,
+}
+
+impl SystemDevice {
+    pub fn registry(&self) -> DeviceRegistry {
+        DeviceRegistry(self.clone())
+    }
+}
+
+// Caches device properties for UIs and tools that query them repeatedly. Properties that can
+// change are dropped from the cache when the HAL reports a change, through listeners that are
+// registered the first time an object is queried. The cache belongs to the `SystemDevice` the
+// registry is built on, so clones of either, and `DeviceHandle`s, share it.
+#[derive(Clone, Default)]
+pub struct DeviceRegistry(SystemDevice);
+
+impl DeviceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn system(&self) -> &SystemDevice {
+        &self.0
+    }
+
+    fn cache(&self) -> &RegistryCache {
+        &(self.0).1
+    }
+
+    pub fn stats(&self) -> RegistryStats {
+        let shared = &self.cache().shared;
+        RegistryStats {
+            hits: shared.hits.load(Ordering::Relaxed),
+            misses: shared.misses.load(Ordering::Relaxed),
+            invalidations: shared.invalidations.load(Ordering::Relaxed),
+            entries: lock(&shared.values).len(),
+        }
+    }
+
+    // Drop every cached value, e.g., after the system wakes from sleep.
+    pub fn clear(&self) {
+        self.cache().shared.remove(|_| true);
+    }
+
+    // Drop the cached values of `object`, a device or the system, e.g., when a UI refreshes it.
+    pub fn invalidate(&self, object: &AudioObject) {
+        let id = object.id();
+        self.cache().shared.remove(|key| key.object == id);
+    }
+
+    pub fn handle(&self, device: Device) -> DeviceHandle {
+        DeviceHandle::new(self.clone(), device)
+    }
+
+    pub fn devices(&self) -> Result<Vec<Device>, OSStatus> {
+        let id = self.0.object().id();
+        let (devices, fresh) = self.cached(id, Property::Devices, Scope::Global, || {
+            self.0.get_all_devices()
+        })?;
+        if fresh {
+            self.prune(&devices);
+        }
+        Ok(devices)
+    }
+
+    pub fn default_device(&self, s: &Side) -> Result<Device, OSStatus> {
+        let property = match s {
+            Side::Input => Property::DefaultInputDevice,
+            Side::Output => Property::DefaultOutputDevice,
+        };
+        let id = self.0.object().id();
+        self.cached(id, property, Scope::Global, || self.0.get_default_device(s))
+            .map(|(device, _)| device)
+    }
+
+    pub fn uid(&self, device: &Device, s: Option<&Side>) -> Result<String, OSStatus> {
+        self.query(device, Property::DeviceUID, s, || device.uid(s))
+    }
+
+    pub fn model_uid(&self, device: &Device, s: Option<&Side>) -> Result<String, OSStatus> {
+        self.query(device, Property::DeviceModelUID, s, || device.model_uid(s))
+    }
+
+    pub fn name(&self, device: &Device, s: Option<&Side>) -> Result<String, OSStatus> {
+        self.query(device, Property::DeviceName, s, || device.name(s))
+    }
+
+    pub fn manufacturer(&self, device: &Device, s: &Side) -> Result<String, OSStatus> {
+        self.query(device, Property::DeviceManufacturer, Some(s), || {
+            device.manufacturer(s)
+        })
+    }
+
+    pub fn transport_type(&self, device: &Device, s: &Side) -> Result<u32, OSStatus> {
+        self.query(device, Property::TransportType, Some(s), || {
+            device.transport_type(s)
+        })
+    }
+
+    pub fn sample_rate(&self, device: &Device, s: &Side) -> Result<f64, OSStatus> {
+        self.query(device, Property::DeviceSampleRate, Some(s), || {
+            device.sample_rate(s)
+        })
+    }
+
+    pub fn source(&self, device: &Device, s: &Side) -> Result<u32, OSStatus> {
+        self.query(device, Property::DeviceSource, Some(s), || device.source(s))
+    }
+
+    // Counted from the stream configuration, so it changes with it.
+    pub fn channel_count(&self, device: &Device, s: &Side) -> Result<u32, OSStatus> {
+        self.query(device, Property::DeviceStreamConfiguration, Some(s), || {
+            device.channel_count(s)
+        })
+    }
+
+    pub fn in_scope(&self, device: &Device, s: &Side) -> Result<bool, OSStatus> {
+        self.query(device, Property::DeviceStreams, Some(s), || {
+            device.in_scope(s)
+        })
+    }
+
+    fn query<T, F>(
+        &self,
+        device: &Device,
+        property: Property,
+        s: Option<&Side>,
+        read: F,
+    ) -> Result<T, OSStatus>
+    where
+        T: Clone + Send + 'static,
+        F: FnOnce() -> Result<T, OSStatus>,
+    {
+        let scope = s.map_or(Scope::Global, Scope::from);
+        self.cached(device.0.id(), property, scope, read)
+            .map(|(value, _)| value)
+    }
+
+    // The cached value of the property, or the one `read` returns, and whether it was read.
+    fn cached<T, F>(
+        &self,
+        object: AudioObjectID,
+        property: Property,
+        scope: Scope,
+        read: F,
+    ) -> Result<(T, bool), OSStatus>
+    where
+        T: Clone + Send + 'static,
+        F: FnOnce() -> Result<T, OSStatus>,
+    {
+        let shared = &self.cache().shared;
+        let key = Key::new(object, &get_property_address(property, scope));
+        let cached = lock(&shared.values)
+            .get(&key)
+            .and_then(|value| value.downcast_ref::<T>().cloned());
+        if let Some(value) = cached {
+            shared.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok((value, false));
+        }
+
+        shared.misses.fetch_add(1, Ordering::Relaxed);
+        // Listen before reading, so a change during the read is reported.
+        self.watch(object)?;
+        let generation = shared.generation.load(Ordering::SeqCst);
+        let value = read()?;
+        let mut values = lock(&shared.values);
+        if shared.generation.load(Ordering::SeqCst) == generation {
+            values.insert(key, Box::new(value.clone()));
+        }
+        Ok((value, true))
+    }
+
+    fn watch(&self, object: AudioObjectID) -> Result<(), OSStatus> {
+        let mut listeners = lock(&self.cache().listeners);
+        if listeners.contains_key(&object) {
+            return Ok(());
+        }
+        let addresses = if object == self.0.object().id() {
+            SYSTEM_PROPERTIES
+                .iter()
+                .map(|property| get_property_address(*property, Scope::Global))
+                .collect()
+        } else {
+            MUTABLE_PROPERTIES
+                .iter()
+                .flat_map(|property| {
+                    vec![Scope::Global, Scope::Input, Scope::Output]
+                        .into_iter()
+                        .map(move |scope| get_property_address(*property, scope))
+                })
+                .collect()
+        };
+        let shared = self.cache().shared.clone();
+        let listener = PropertyListener::new(object, addresses, move |changed| {
+            shared.invalidate(object, changed)
+        })?;
+        listeners.insert(object, listener);
+        Ok(())
+    }
+
+    // Forget the devices that are gone, along with their listeners.
+    fn prune(&self, devices: &[Device]) {
+        let system = self.0.object().id();
+        let alive = |object: &AudioObjectID| {
+            *object == system || devices.iter().any(|d| d.0.id() == *object)
+        };
+        lock(&self.cache().listeners).retain(|object, _| alive(object));
+        self.cache().shared.remove(|key| !alive(&key.object));
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_device_registry() {
+    use super::super::sys::kAudioObjectSystemObject;
+    use super::fixture::{self, Fixture};
+
+    let devices = get_property_address(Property::Devices, Scope::Global);
+    let default_output = get_property_address(Property::DefaultOutputDevice, Scope::Global);
+    let rate = get_property_address(Property::DeviceSampleRate, Scope::Output);
+    let mut fixture = Fixture::with_devices(&[70, 71]);
+    let input_rate = get_property_address(Property::DeviceSampleRate, Scope::Input);
+    fixture.insert_data(70, &input_rate, &[44100.0_f64]);
+
+    fixture.replay(|| {
+        let registry = DeviceRegistry::new();
+        let device = registry.default_device(&Side::Output).unwrap();
+        assert_eq!(registry.devices().unwrap().len(), 2);
+        for _ in 0..3 {
+            assert_eq!(registry.uid(&device, None).unwrap(), "uid-70");
+            assert_eq!(
+                registry.sample_rate(&device, &Side::Output).unwrap(),
+                44100.0
+            );
+        }
+        assert_eq!(
+            registry.stats(),
+            RegistryStats {
+                hits: 4,
+                misses: 4,
+                invalidations: 0,
+                entries: 4,
+            }
+        );
+        assert_eq!(registry.stats().hit_rate(), 0.5);
+        // Failed queries aren't cached.
+        assert!(registry.name(&device, None).is_err());
+        assert_eq!(registry.stats().entries, 4);
+
+        // A change reported by the HAL drops the value, in every scope.
+        assert_eq!(
+            registry.sample_rate(&device, &Side::Input).unwrap(),
+            44100.0
+        );
+        device.set_sample_rate(&Side::Output, 48000.0).unwrap();
+        assert_eq!(
+            registry.sample_rate(&device, &Side::Output).unwrap(),
+            44100.0
+        );
+        fixture::notify(70, &rate);
+        assert_eq!(registry.stats().invalidations, 2);
+        assert_eq!(registry.stats().entries, 3);
+        assert_eq!(
+            registry.sample_rate(&device, &Side::Output).unwrap(),
+            48000.0
+        );
+
+        // Clones and the `SystemDevice` share the cache.
+        let clone = SystemDevice::default().registry();
+        assert_eq!(clone.stats(), RegistryStats::default());
+        let clone = registry.system().registry();
+        assert_eq!(clone.stats(), registry.stats());
+        registry.invalidate(&device.object());
+        assert_eq!(clone.stats().entries, 2);
+
+        // A removed device is forgotten once the device list is read again.
+        let system = SystemDevice::default().object();
+        system
+            .set_property_data_common(&devices, &[71_u32])
+            .unwrap();
+        system
+            .set_property_data_common(&default_output, &71_u32)
+            .unwrap();
+        fixture::notify(kAudioObjectSystemObject, &devices);
+        fixture::notify(kAudioObjectSystemObject, &default_output);
+        assert_eq!(registry.devices().unwrap(), vec![Device::new(71)]);
+        assert_eq!(
+            registry.default_device(&Side::Output).unwrap(),
+            Device::new(71)
+        );
+        assert_eq!(registry.stats().entries, 2);
+        assert_eq!(lock(&registry.cache().listeners).len(), 1);
+
+        registry.clear();
+        assert_eq!(registry.stats().entries, 0);
+        assert_eq!(registry.uid(&device, None).unwrap(), "uid-70");
+    });
+}
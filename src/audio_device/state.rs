@@ -0,0 +1,147 @@
+use super::super::sys::OSStatus;
+use super::listener::PropertyListener;
+use super::property_address::{get_property_address, Property, Scope};
+use super::{Device, Side};
+
+// The properties that make up a `DeviceState`.
+const STATE_PROPERTIES: [Property; 4] = [
+    Property::DeviceIsAlive,
+    Property::DeviceIsRunning,
+    Property::DeviceIsRunningSomewhere,
+    Property::DeviceIsHidden,
+];
+
+// A snapshot of whether a device is usable and in use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeviceState {
+    // False once the device is unplugged or otherwise gone.
+    pub alive: bool,
+    // Whether IO is running on the device in this process.
+    pub running: bool,
+    // Whether IO is running on the device in any process.
+    pub running_somewhere: bool,
+    pub hidden: bool,
+}
+
+impl Device {
+    pub fn is_alive(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::DeviceIsAlive, Scope::Global)
+    }
+
+    pub fn is_running(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::DeviceIsRunning, Scope::Global)
+    }
+
+    pub fn is_running_somewhere(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::DeviceIsRunningSomewhere, Scope::Global)
+    }
+
+    // Hidden devices are not listed to users but can still be opened by their UID.
+    pub fn is_hidden(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::DeviceIsHidden, Scope::Global)
+    }
+
+    // Whether the device can be the default input or output device.
+    pub fn can_be_default(&self, s: &Side) -> Result<bool, OSStatus> {
+        self.get_bool(Property::DeviceCanBeDefault, Scope::from(s))
+    }
+
+    // Whether the device can be the output device for alerts and sound effects.
+    pub fn can_be_default_system(&self) -> Result<bool, OSStatus> {
+        self.get_bool(Property::DeviceCanBeDefaultSystem, Scope::Output)
+    }
+
+    pub fn state(&self) -> Result<DeviceState, OSStatus> {
+        Ok(DeviceState {
+            alive: self.is_alive()?,
+            running: self.is_running()?,
+            running_somewhere: self.is_running_somewhere()?,
+            hidden: self.is_hidden()?,
+        })
+    }
+
+    // Call `callback` with the new state whenever any part of the device's state changes. The
+    // callback runs on a HAL thread until the returned listener is dropped. A device that can
+    // no longer be queried, e.g., after it's unplugged, is reported as not alive.
+    pub fn on_state_change<F>(&self, mut callback: F) -> Result<PropertyListener, OSStatus>
+    where
+        F: FnMut(DeviceState) + Send + 'static,
+    {
+        let id = self.0.id();
+        let addresses = STATE_PROPERTIES
+            .iter()
+            .map(|property| get_property_address(*property, Scope::Global))
+            .collect();
+        PropertyListener::new(id, addresses, move |_| {
+            callback(Device::new(id).state().unwrap_or_default());
+        })
+    }
+
+    fn get_bool(&self, property: Property, scope: Scope) -> Result<bool, OSStatus> {
+        let address = get_property_address(property, scope);
+        self.0
+            .get_property_data_common::<u32>(&address)
+            .map(|value| value != 0)
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_state_change() {
+    use super::fixture::{self, Fixture};
+    use super::SystemDevice;
+    use std::sync::{Arc, Mutex};
+
+    const DEVICE: u32 = 70;
+    let mut fixture = Fixture::default();
+    fixture.insert_data(
+        1,
+        &get_property_address(Property::DefaultOutputDevice, Scope::Global),
+        &[DEVICE],
+    );
+    for (property, value) in STATE_PROPERTIES.iter().zip(&[1_u32, 0, 1, 0]) {
+        fixture.insert_data(
+            DEVICE,
+            &get_property_address(*property, Scope::Global),
+            &[*value],
+        );
+    }
+    fixture.insert_data(
+        DEVICE,
+        &get_property_address(Property::DeviceCanBeDefault, Scope::Output),
+        &[1_u32],
+    );
+
+    fixture.replay(|| {
+        let device = SystemDevice::default()
+            .get_default_device(&Side::Output)
+            .unwrap();
+        assert!(device.can_be_default(&Side::Output).unwrap());
+        assert!(device.can_be_default_system().is_err());
+        assert_eq!(
+            device.state().unwrap(),
+            DeviceState {
+                alive: true,
+                running: false,
+                running_somewhere: true,
+                hidden: false,
+            }
+        );
+
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let listener = {
+            let states = states.clone();
+            device
+                .on_state_change(move |state| states.lock().unwrap().push(state))
+                .unwrap()
+        };
+        let address = get_property_address(Property::DeviceIsRunning, Scope::Global);
+        device.0.set_property_data_common(&address, &1_u32).unwrap();
+        fixture::notify(DEVICE, &address);
+        assert!(states.lock().unwrap()[0].running);
+
+        drop(listener);
+        fixture::notify(DEVICE, &address);
+        assert_eq!(states.lock().unwrap().len(), 1);
+    });
+}
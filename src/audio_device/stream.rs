@@ -0,0 +1,49 @@
+use super::super::stream_format::StreamFormat;
+use super::super::sys::{AudioObjectID, AudioStreamBasicDescription, OSStatus};
+use super::audio_object::AudioObject;
+use super::property_address::{get_property_address, Property, Scope};
+use super::StreamId;
+
+// A stream of a device, carrying one buffer of the device's IO.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stream(AudioObject);
+
+impl Stream {
+    pub(crate) fn new(id: AudioObjectID) -> Self {
+        Self(AudioObject::new(id))
+    }
+
+    pub fn id(&self) -> StreamId {
+        StreamId(self.0.id())
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
+    pub fn is_input(&self) -> Result<bool, OSStatus> {
+        let address = get_property_address(Property::StreamDirection, Scope::Global);
+        self.0
+            .get_property_data_common::<u32>(&address)
+            .map(|direction| direction == 1)
+    }
+
+    // The 1-based channel number in the device of the stream's first channel.
+    pub fn starting_channel(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::StreamStartingChannel, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    pub fn latency(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::StreamLatency, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    // The format of the stream's buffer in the IO data, usually 32-bit float.
+    pub fn virtual_format(&self) -> Result<StreamFormat, OSStatus> {
+        let address = get_property_address(Property::StreamVirtualFormat, Scope::Global);
+        self.0
+            .get_property_data_common::<AudioStreamBasicDescription>(&address)
+            .map(StreamFormat::from)
+    }
+}
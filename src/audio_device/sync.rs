@@ -0,0 +1,29 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, MutexGuard};
+
+// Locks a mutex whose data is only changed in steps a panic can't interrupt halfway, e.g.,
+// single inserts and removals, so it's still consistent after a panic poisons it.
+pub(crate) fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Calls a callback on a HAL thread. Unwinding into the HAL is undefined behavior, so a panic is
+// caught here. It poisons the mutex, so the callback won't be called again.
+pub(crate) fn call_guarded<T: ?Sized>(callback: &Mutex<T>, call: impl FnOnce(&mut T)) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        if let Ok(mut callback) = callback.lock() {
+            call(&mut callback);
+        }
+    }));
+}
+
+#[test]
+fn test_call_guarded_after_panic() {
+    let callback = Mutex::new(0);
+    call_guarded(&callback, |calls| {
+        *calls += 1;
+        panic!("callback");
+    });
+    call_guarded(&callback, |calls| *calls += 1);
+    assert_eq!(*lock(&callback), 1);
+}
@@ -0,0 +1,313 @@
+use super::super::stream_format::StreamFormat;
+use super::super::string::StringRef;
+use super::super::sys::{
+    kAudioHardwareUnspecifiedError, kAudioHardwareUnsupportedOperationError, kAudioObjectUnknown,
+    kCFTypeArrayCallBacks, noErr, symbol, AudioObjectID, AudioStreamBasicDescription,
+    CFArrayCreate, CFArrayRef, CFIndex, CFRelease, OSStatus,
+};
+use super::audio_object::AudioObject;
+use super::property_address::{get_property_address, Property, Scope};
+use super::Device;
+use std::os::raw::c_void;
+use std::ptr;
+
+// The keys of a tap in the composition dictionary of an aggregate device, which can include
+// taps as sub-devices: `kAudioAggregateDeviceTapListKey`, `kAudioSubTapUIDKey` and
+// `kAudioSubTapDriftCompensationKey`.
+pub const AGGREGATE_TAP_LIST_KEY: &str = "taps";
+pub const SUB_TAP_UID_KEY: &str = "uid";
+pub const SUB_TAP_DRIFT_COMPENSATION_KEY: &str = "drift";
+
+// What happens to the tapped processes' audio on its way to the hardware.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TapMuteBehavior {
+    Unmuted,
+    Muted,
+    // Muted only while the tap is read by an IO proc.
+    MutedWhenTapped,
+}
+
+// A safe mirror of `CATapDescription`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TapDescription {
+    // The process objects to tap or, for a global tap, to exclude.
+    pub processes: Vec<AudioObject>,
+    // Tap every process except `processes`.
+    pub global: bool,
+    // Mix down to one channel instead of two.
+    pub mono: bool,
+    pub mute: TapMuteBehavior,
+    // Private taps are only visible to the process that created them.
+    pub private: bool,
+    pub name: Option<String>,
+}
+
+impl TapDescription {
+    // A stereo mixdown of `processes`.
+    pub fn processes(processes: &[AudioObject]) -> Self {
+        Self {
+            processes: processes.to_vec(),
+            global: false,
+            mono: false,
+            mute: TapMuteBehavior::Unmuted,
+            private: false,
+            name: None,
+        }
+    }
+
+    // A stereo mixdown of every process except `excluded`, e.g., the current one.
+    pub fn global_excluding(excluded: &[AudioObject]) -> Self {
+        Self {
+            global: true,
+            ..Self::processes(excluded)
+        }
+    }
+
+    // The nul-terminated `CATapDescription` initializer to call.
+    fn initializer(&self) -> &'static [u8] {
+        match (self.global, self.mono) {
+            (false, false) => b"initStereoMixdownOfProcesses:\0",
+            (false, true) => b"initMonoMixdownOfProcesses:\0",
+            (true, false) => b"initStereoGlobalTapButExcludeProcesses:\0",
+            (true, true) => b"initMonoGlobalTapButExcludeProcesses:\0",
+        }
+    }
+}
+
+// A tap on the audio output of some processes, available from macOS 14.2. Its audio is read
+// by adding it to an aggregate device. The tap is destroyed when dropped.
+pub struct ProcessTap(AudioObject);
+
+impl ProcessTap {
+    pub fn new(description: &TapDescription) -> Result<Self, OSStatus> {
+        let unsupported = kAudioHardwareUnsupportedOperationError as OSStatus;
+        let create: CreateProcessTap =
+            unsafe { symbol(b"AudioHardwareCreateProcessTap\0") }.ok_or(unsupported)?;
+        let description = unsafe { objc::tap_description(description) }.ok_or(unsupported)?;
+        let mut id: AudioObjectID = kAudioObjectUnknown;
+        let status = unsafe { create(description, &mut id) };
+        unsafe { objc::release(description) };
+        if status == noErr as OSStatus {
+            Ok(Self(AudioObject::new(id)))
+        } else {
+            Err(status)
+        }
+    }
+
+    pub fn object(&self) -> AudioObject {
+        self.0
+    }
+
+    // The UID to list the tap by in an aggregate device.
+    pub fn uid(&self) -> Result<String, OSStatus> {
+        let address = get_property_address(Property::TapUID, Scope::Global);
+        self.0.get_property_string(&address)
+    }
+
+    pub fn format(&self) -> Result<StreamFormat, OSStatus> {
+        let address = get_property_address(Property::TapFormat, Scope::Global);
+        self.0
+            .get_property_data_common::<AudioStreamBasicDescription>(&address)
+            .map(StreamFormat::from)
+    }
+}
+
+impl Drop for ProcessTap {
+    fn drop(&mut self) {
+        let destroy: Option<DestroyProcessTap> =
+            unsafe { symbol(b"AudioHardwareDestroyProcessTap\0") };
+        if let Some(destroy) = destroy {
+            unsafe { destroy(self.0.id()) };
+        }
+    }
+}
+
+impl Device {
+    // Replace the taps of an aggregate device with the taps with these UIDs.
+    pub fn set_aggregate_taps(&self, uids: &[&str]) -> Result<(), OSStatus> {
+        let strings: Vec<StringRef> = uids.iter().map(|uid| StringRef::from(*uid)).collect();
+        let values: Vec<*const c_void> = strings
+            .iter()
+            .map(|s| s.as_concrete_type_ref() as *const c_void)
+            .collect();
+        let array: CFArrayRef = unsafe {
+            CFArrayCreate(
+                ptr::null(),
+                values.as_ptr(),
+                values.len() as CFIndex,
+                &kCFTypeArrayCallBacks,
+            )
+        };
+        if array.is_null() {
+            return Err(kAudioHardwareUnspecifiedError as OSStatus);
+        }
+        let address = get_property_address(Property::AggregateTapList, Scope::Global);
+        let result = self.0.set_property_data_common(&address, &array);
+        unsafe { CFRelease(array as *const c_void) };
+        result
+    }
+
+    // The taps of an aggregate device that are currently delivering audio.
+    pub fn active_sub_taps(&self) -> Result<Vec<AudioObject>, OSStatus> {
+        let address = get_property_address(Property::AggregateActiveSubTaps, Scope::Global);
+        self.0
+            .get_property_array_common::<AudioObjectID>(&address)
+            .map(|ids| ids.into_iter().map(AudioObject::new).collect())
+    }
+}
+
+// The tap API and the Objective-C runtime are looked up at runtime, so calx still loads on
+// systems and SDKs without them.
+type CreateProcessTap = unsafe extern "C" fn(*mut c_void, *mut AudioObjectID) -> OSStatus;
+type DestroyProcessTap = unsafe extern "C" fn(AudioObjectID) -> OSStatus;
+
+mod objc {
+    use super::{symbol, StringRef, TapDescription, TapMuteBehavior};
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_void};
+
+    type Id = *mut c_void;
+    type Sel = *mut c_void;
+
+    struct Runtime {
+        get_class: unsafe extern "C" fn(*const c_char) -> Id,
+        register_name: unsafe extern "C" fn(*const c_char) -> Sel,
+        msg_send: *mut c_void,
+    }
+
+    impl Runtime {
+        unsafe fn get() -> Option<Self> {
+            Some(Self {
+                get_class: symbol(b"objc_getClass\0")?,
+                register_name: symbol(b"sel_registerName\0")?,
+                msg_send: symbol(b"objc_msgSend\0")?,
+            })
+        }
+
+        unsafe fn class(&self, name: &[u8]) -> Option<Id> {
+            let name = CStr::from_bytes_with_nul(name).ok()?;
+            let class = (self.get_class)(name.as_ptr());
+            if class.is_null() {
+                None
+            } else {
+                Some(class)
+            }
+        }
+
+        unsafe fn sel(&self, name: &[u8]) -> Sel {
+            let name = CStr::from_bytes_with_nul(name).expect("selectors are nul-terminated");
+            (self.register_name)(name.as_ptr())
+        }
+
+        // `objc_msgSend` must be called through a pointer of the method's exact type.
+        unsafe fn send0(&self, receiver: Id, selector: &[u8]) -> Id {
+            let send: unsafe extern "C" fn(Id, Sel) -> Id = std::mem::transmute(self.msg_send);
+            send(receiver, self.sel(selector))
+        }
+
+        unsafe fn send1<A: Copy>(&self, receiver: Id, selector: &[u8], argument: A) -> Id {
+            let send: unsafe extern "C" fn(Id, Sel, A) -> Id = std::mem::transmute(self.msg_send);
+            send(receiver, self.sel(selector), argument)
+        }
+
+        unsafe fn new_array(&self, objects: &[Owned]) -> Option<Owned> {
+            let array = self.alloc(b"NSArray\0")?;
+            let send: unsafe extern "C" fn(Id, Sel, *const Id, usize) -> Id =
+                std::mem::transmute(self.msg_send);
+            let selector = self.sel(b"initWithObjects:count:\0");
+            let objects: Vec<Id> = objects.iter().map(|object| object.0).collect();
+            Some(Owned(send(
+                array,
+                selector,
+                objects.as_ptr(),
+                objects.len(),
+            )))
+        }
+
+        unsafe fn alloc(&self, class: &[u8]) -> Option<Id> {
+            let class = self.class(class)?;
+            Some(self.send0(class, b"alloc\0"))
+        }
+    }
+
+    // An object that's released when dropped, so returning early doesn't leak it.
+    struct Owned(Id);
+
+    impl Owned {
+        fn into_raw(self) -> Id {
+            let object = self.0;
+            std::mem::forget(self);
+            object
+        }
+    }
+
+    impl Drop for Owned {
+        fn drop(&mut self) {
+            if !self.0.is_null() {
+                unsafe { release(self.0) };
+            }
+        }
+    }
+
+    // A retained `CATapDescription`, or `None` if the class isn't available.
+    pub(super) unsafe fn tap_description(description: &TapDescription) -> Option<Id> {
+        let runtime = Runtime::get()?;
+        // `init` methods take over the object `alloc` returned, so it's only owned until then.
+        let tap = Owned(runtime.alloc(b"CATapDescription\0")?);
+
+        let mut numbers = Vec::with_capacity(description.processes.len());
+        for process in &description.processes {
+            let number = runtime.alloc(b"NSNumber\0")?;
+            numbers.push(Owned(runtime.send1(
+                number,
+                b"initWithUnsignedInt:\0",
+                process.id(),
+            )));
+        }
+        let processes = runtime.new_array(&numbers)?;
+        let tap = Owned(runtime.send1(tap.into_raw(), description.initializer(), processes.0));
+        if tap.0.is_null() {
+            return None;
+        }
+
+        let mute: isize = match description.mute {
+            TapMuteBehavior::Unmuted => 0,
+            TapMuteBehavior::Muted => 1,
+            TapMuteBehavior::MutedWhenTapped => 2,
+        };
+        runtime.send1(tap.0, b"setMuteBehavior:\0", mute);
+        runtime.send1(tap.0, b"setPrivate:\0", description.private);
+        if let Some(name) = &description.name {
+            // `CFString` is toll-free bridged to `NSString`.
+            let name = StringRef::from(name.as_str());
+            runtime.send1(tap.0, b"setName:\0", name.as_concrete_type_ref());
+        }
+        Some(tap.into_raw())
+    }
+
+    pub(super) unsafe fn release(object: Id) {
+        if let Some(runtime) = Runtime::get() {
+            runtime.send0(object, b"release\0");
+        }
+    }
+}
+
+#[test]
+fn test_tap_description() {
+    let process = AudioObject::new(120);
+    let tap = TapDescription::processes(&[process]);
+    assert!(!tap.global && !tap.mono);
+    assert_eq!(tap.mute, TapMuteBehavior::Unmuted);
+    assert_eq!(tap.initializer(), b"initStereoMixdownOfProcesses:\0");
+
+    let global = TapDescription {
+        mono: true,
+        mute: TapMuteBehavior::MutedWhenTapped,
+        ..TapDescription::global_excluding(&[process])
+    };
+    assert_eq!(global.processes, vec![process]);
+    assert_eq!(
+        global.initializer(),
+        b"initMonoGlobalTapButExcludeProcesses:\0"
+    );
+}
@@ -0,0 +1,44 @@
+use super::super::sys::{
+    kAudioTimeStampHostTimeValid, kAudioTimeStampRateScalarValid, kAudioTimeStampSampleTimeValid,
+    noErr, AudioDeviceGetCurrentTime, AudioDeviceTranslateTime, AudioTimeStamp, OSStatus,
+};
+use super::super::time_stamp::TimeStamp;
+use super::property_address::{get_property_address, Property, Scope};
+use super::Device;
+
+impl Device {
+    // The number of frames between the time stamps the device's clock reports, which is often
+    // the size of its ring buffer.
+    pub fn zero_time_stamp_period(&self) -> Result<u32, OSStatus> {
+        let address = get_property_address(Property::DeviceZeroTimeStampPeriod, Scope::Global);
+        self.0.get_property_data_common::<u32>(&address)
+    }
+
+    // The device's current time, which is only known while it's running.
+    pub fn current_time(&self) -> Result<TimeStamp, OSStatus> {
+        let mut time = AudioTimeStamp::from(TimeStamp::default());
+        let status = unsafe { AudioDeviceGetCurrentTime(self.0.id(), &mut time) };
+        if status == noErr as OSStatus {
+            Ok(TimeStamp::from(time))
+        } else {
+            Err(status)
+        }
+    }
+
+    // The sample time, host time and rate scalar of `time`, which needs a sample time or a host
+    // time, on the device's clock. The device must be running.
+    pub fn translate_time(&self, time: &TimeStamp) -> Result<TimeStamp, OSStatus> {
+        let input = AudioTimeStamp::from(*time);
+        let mut output = AudioTimeStamp::from(TimeStamp::default());
+        // The flags of the output ask for the fields to translate to.
+        output.mFlags = kAudioTimeStampSampleTimeValid
+            | kAudioTimeStampHostTimeValid
+            | kAudioTimeStampRateScalarValid;
+        let status = unsafe { AudioDeviceTranslateTime(self.0.id(), &input, &mut output) };
+        if status == noErr as OSStatus {
+            Ok(TimeStamp::from(output))
+        } else {
+            Err(status)
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use super::super::sys::{
+    kAudioHardwareUnspecifiedError, kAudioHardwareUnsupportedOperationError, noErr, symbol,
+    OSStatus,
+};
+#[cfg(feature = "fixture")]
+use super::fixture;
+use super::property_address::{get_property_address, Property, Scope};
+use super::Device;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr::{self, NonNull};
+
+// `OS_WORKGROUP_JOIN_TOKEN_SIZE` in <os/workgroup_object.h>.
+const JOIN_TOKEN_SIZE: usize = 36;
+
+#[repr(C)]
+struct JoinToken {
+    sig: u32,
+    opaque: [c_char; JOIN_TOKEN_SIZE],
+}
+
+type OsRelease = unsafe extern "C" fn(*mut c_void);
+type OsWorkgroupJoin = unsafe extern "C" fn(*mut c_void, *mut JoinToken) -> c_int;
+type OsWorkgroupLeave = unsafe extern "C" fn(*mut c_void, *mut JoinToken);
+
+// The workgroup API is looked up at runtime, since it's only there from macOS 11.
+struct Api {
+    release: OsRelease,
+    join: OsWorkgroupJoin,
+    leave: OsWorkgroupLeave,
+}
+
+impl Api {
+    fn get() -> Option<Self> {
+        unsafe {
+            Some(Self {
+                release: symbol(b"os_release\0")?,
+                join: symbol(b"os_workgroup_join\0")?,
+                leave: symbol(b"os_workgroup_leave\0")?,
+            })
+        }
+    }
+}
+
+// The OS workgroup of a device's IO thread. Threads doing real-time work for the device, e.g.,
+// rendering on other cores, should join it so the scheduler accounts for them.
+pub struct OsWorkgroup {
+    workgroup: NonNull<c_void>,
+    api: Api,
+}
+
+impl OsWorkgroup {
+    // Join the workgroup from the current thread until the returned guard is dropped.
+    pub fn join(&self) -> Result<WorkgroupMembership<'_>, c_int> {
+        let mut token = Box::new(JoinToken {
+            sig: 0,
+            opaque: [0; JOIN_TOKEN_SIZE],
+        });
+        let result = unsafe { (self.api.join)(self.workgroup.as_ptr(), &mut *token) };
+        if result != 0 {
+            return Err(result);
+        }
+        Ok(WorkgroupMembership {
+            workgroup: self,
+            token,
+            _not_send: PhantomData,
+        })
+    }
+}
+
+impl Drop for OsWorkgroup {
+    fn drop(&mut self) {
+        // The HAL retains the workgroup it returns for the caller.
+        unsafe { (self.api.release)(self.workgroup.as_ptr()) };
+    }
+}
+
+// The current thread's membership of a workgroup. It must be left on the joining thread.
+pub struct WorkgroupMembership<'a> {
+    workgroup: &'a OsWorkgroup,
+    token: Box<JoinToken>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for WorkgroupMembership<'_> {
+    fn drop(&mut self) {
+        let workgroup = self.workgroup;
+        unsafe { (workgroup.api.leave)(workgroup.workgroup.as_ptr(), &mut *self.token) };
+    }
+}
+
+impl Device {
+    // Only available from macOS 11.
+    pub fn io_thread_os_workgroup(&self) -> Result<OsWorkgroup, OSStatus> {
+        let unsupported = kAudioHardwareUnsupportedOperationError as OSStatus;
+        // A pointer can't be recorded, so the property is read from the HAL, bypassing the
+        // fixture, and there's no workgroup while replaying.
+        #[cfg(feature = "fixture")]
+        if fixture::is_replaying() {
+            return Err(unsupported);
+        }
+        let api = Api::get().ok_or(unsupported)?;
+        let address = get_property_address(Property::DeviceIOThreadOSWorkgroup, Scope::Global);
+        // The property is an `os_workgroup_t`, which is a pointer.
+        let mut workgroup: *mut c_void = ptr::null_mut();
+        let mut size = mem::size_of_val(&workgroup);
+        let status =
+            self.0
+                .get_property_data_without_qualifier(&address, &mut size, &mut workgroup);
+        if status != noErr as OSStatus {
+            return Err(status);
+        }
+        NonNull::new(workgroup)
+            .map(|workgroup| OsWorkgroup { workgroup, api })
+            .ok_or(kAudioHardwareUnspecifiedError as OSStatus)
+    }
+}
+
+#[cfg(feature = "fixture")]
+#[test]
+fn test_replay_no_workgroup() {
+    use super::fixture::Fixture;
+
+    let address = get_property_address(Property::DeviceIOThreadOSWorkgroup, Scope::Global);
+    let mut fixture = Fixture::default();
+    fixture.insert_data(70, &address, &[0x1234_usize]);
+    fixture.replay(|| {
+        assert_eq!(
+            Device::new(70).io_thread_os_workgroup().err(),
+            Some(kAudioHardwareUnsupportedOperationError as OSStatus)
+        );
+    });
+}
@@ -0,0 +1,492 @@
+use super::stream_format::StreamFormat;
+use std::alloc::{self, Layout};
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+use std::slice;
+
+/// A sample type that can be read from and written to raw audio data.
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value of the type.
+pub unsafe trait Sample: Copy + Default + 'static {}
+
+unsafe impl Sample for u8 {}
+unsafe impl Sample for i8 {}
+unsafe impl Sample for u16 {}
+unsafe impl Sample for i16 {}
+unsafe impl Sample for u32 {}
+unsafe impl Sample for i32 {}
+unsafe impl Sample for f32 {}
+unsafe impl Sample for f64 {}
+
+// `AudioBuffer` and `AudioBufferList` as declared in <CoreAudioTypes.h>, so buffer lists can be
+// built and tested without Core Audio, e.g., under Miri. Lists are passed to Core Audio by
+// pointer, with `cast`.
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct AudioBuffer {
+    pub mNumberChannels: u32,
+    pub mDataByteSize: u32,
+    pub mData: *mut c_void,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct AudioBufferList {
+    pub mNumberBuffers: u32,
+    pub mBuffers: [AudioBuffer; 1],
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use super::{AudioBuffer, AudioBufferList};
+    use std::mem;
+
+    const _: () = assert!(
+        mem::size_of::<AudioBufferList>() == mem::size_of::<coreaudio_sys::AudioBufferList>()
+            && mem::align_of::<AudioBufferList>()
+                == mem::align_of::<coreaudio_sys::AudioBufferList>()
+            && mem::size_of::<AudioBuffer>() == mem::size_of::<coreaudio_sys::AudioBuffer>()
+    );
+
+    impl From<coreaudio_sys::AudioBuffer> for AudioBuffer {
+        fn from(buffer: coreaudio_sys::AudioBuffer) -> Self {
+            Self {
+                mNumberChannels: buffer.mNumberChannels,
+                mDataByteSize: buffer.mDataByteSize,
+                mData: buffer.mData,
+            }
+        }
+    }
+
+    impl From<AudioBuffer> for coreaudio_sys::AudioBuffer {
+        fn from(buffer: AudioBuffer) -> Self {
+            Self {
+                mNumberChannels: buffer.mNumberChannels,
+                mDataByteSize: buffer.mDataByteSize,
+                mData: buffer.mData,
+            }
+        }
+    }
+}
+
+// `AudioBufferList` declares `mBuffers` as a one-element array, but the list actually holds
+// `mNumberBuffers` buffers. The memory needed for `buffers` buffers is the size of the header
+// plus the buffers, and never less than the size of the declared struct.
+fn list_layout(buffers: usize) -> Layout {
+    let header = mem::size_of::<AudioBufferList>() - mem::size_of::<AudioBuffer>();
+    let size = header + buffers.max(1) * mem::size_of::<AudioBuffer>();
+    Layout::from_size_align(size, mem::align_of::<AudioBufferList>())
+        .expect("the AudioBufferList is too large")
+}
+
+// The alignment of the data buffers allocated by `BufferList`, enough for any `Sample`.
+type DataUnit = u64;
+
+// An owned, correctly aligned `AudioBufferList` with any number of buffers.
+pub struct BufferList {
+    list: NonNull<AudioBufferList>,
+    layout: Layout,
+    // The data buffers allocated by `allocate`. `mData` of each `AudioBuffer` points into these.
+    data: Vec<Vec<DataUnit>>,
+    // The size of each data buffer, in bytes.
+    data_size: usize,
+}
+
+impl BufferList {
+    // Create a list of `buffers` empty buffers, with no channels and no data.
+    pub fn new(buffers: usize) -> Self {
+        let layout = list_layout(buffers);
+        let list = unsafe { alloc::alloc_zeroed(layout) } as *mut AudioBufferList;
+        let list = NonNull::new(list).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        unsafe {
+            (*list.as_ptr()).mNumberBuffers = buffers as u32;
+        }
+        Self {
+            list,
+            layout,
+            data: Vec::new(),
+            data_size: 0,
+        }
+    }
+
+    // Copy an `AudioBufferList` serialized by the HAL, e.g., the stream configuration of a device,
+    // out of a byte buffer that has no alignment guarantee. Returns `None` if `bytes` is too short
+    // for the number of buffers it declares. The data pointers are cleared since the list doesn't
+    // own the memory they point to.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < mem::size_of::<u32>() {
+            return None;
+        }
+        let buffers = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        let header = mem::size_of::<AudioBufferList>() - mem::size_of::<AudioBuffer>();
+        // An empty list may be serialized without the padding after `mNumberBuffers`.
+        let size = if buffers == 0 {
+            mem::size_of::<u32>()
+        } else {
+            buffers
+                .checked_mul(mem::size_of::<AudioBuffer>())?
+                .checked_add(header)?
+        };
+        if bytes.len() < size {
+            return None;
+        }
+
+        let mut list = Self::new(buffers);
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), list.list.as_ptr() as *mut u8, size);
+        }
+        for buffer in list.buffers_mut() {
+            buffer.mData = ptr::null_mut();
+        }
+        Some(list)
+    }
+
+    // Create a list with zeroed data buffers holding `frames` frames of `format`.
+    pub fn allocate(format: &StreamFormat, frames: usize) -> Self {
+        Self::allocate_bytes(
+            format.buffer_count() as usize,
+            format.channels_per_buffer(),
+            format.bytes_per_buffer(frames),
+        )
+    }
+
+    // Allocate `buffers` zeroed buffers of `bytes` bytes, e.g., for packets of a compressed
+    // format, whose frames have no fixed size. Panics if `bytes` doesn't fit `mDataByteSize`.
+    pub fn allocate_bytes(buffers: usize, channels: u32, bytes: usize) -> Self {
+        let byte_size = u32::try_from(bytes).expect("the buffer is too large for an AudioBuffer");
+        let units = bytes.div_ceil(mem::size_of::<DataUnit>());
+
+        let mut list = Self::new(buffers);
+        list.data = (0..buffers).map(|_| vec![0; units]).collect();
+        list.data_size = bytes;
+        let list_buffers =
+            unsafe { slice::from_raw_parts_mut(buffers_ptr(list.list.as_ptr()), buffers) };
+        for (buffer, data) in list_buffers.iter_mut().zip(list.data.iter_mut()) {
+            buffer.mNumberChannels = channels;
+            buffer.mDataByteSize = byte_size;
+            buffer.mData = data.as_mut_ptr() as *mut c_void;
+        }
+        list
+    }
+
+    // Give every allocated buffer its whole size again, after an API that fills the list, e.g.,
+    // `AudioConverterFillComplexBuffer`, shrank `mDataByteSize` to the data it wrote.
+    pub fn reset_data_sizes(&mut self) {
+        let size = self.data_size as u32;
+        let allocated = self.data.len();
+        for buffer in self.buffers_mut().iter_mut().take(allocated) {
+            buffer.mDataByteSize = size;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.view().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.view().is_empty()
+    }
+
+    pub fn buffers(&self) -> &[AudioBuffer] {
+        unsafe { slice::from_raw_parts(buffers_ptr(self.list.as_ptr()), self.len()) }
+    }
+
+    // The data pointers are not exposed mutably, so they can't be pointed at unowned memory.
+    fn buffers_mut(&mut self) -> &mut [AudioBuffer] {
+        let len = self.len();
+        unsafe { slice::from_raw_parts_mut(buffers_ptr(self.list.as_ptr()), len) }
+    }
+
+    pub fn channel_count(&self) -> u32 {
+        self.view().channel_count()
+    }
+
+    // The samples of the buffer at `index`, or `None` if there is no such buffer, it has no data,
+    // or its data is not aligned for `T`.
+    pub fn data<T: Sample>(&self, index: usize) -> Option<&[T]> {
+        unsafe { buffer_data(self.buffers().get(index)?) }
+    }
+
+    pub fn data_mut<T: Sample>(&mut self, index: usize) -> Option<&mut [T]> {
+        unsafe { buffer_data_mut(self.buffers_mut().get_mut(index)?) }
+    }
+
+    pub fn view(&self) -> BufferListRef<'_> {
+        BufferListRef {
+            list: self.list,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn view_mut(&mut self) -> BufferListMut<'_> {
+        BufferListMut {
+            list: self.list,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *const AudioBufferList {
+        self.list.as_ptr()
+    }
+
+    // Callers passing the list to APIs that replace `mData` must not access the data through this
+    // list afterwards, unless the new data outlives it.
+    pub fn as_mut_ptr(&mut self) -> *mut AudioBufferList {
+        self.list.as_ptr()
+    }
+}
+
+impl Drop for BufferList {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.list.as_ptr() as *mut u8, self.layout) };
+    }
+}
+
+// A borrowed `AudioBufferList`, e.g., the input data handed to an IOProc.
+#[derive(Clone, Copy)]
+pub struct BufferListRef<'a> {
+    list: NonNull<AudioBufferList>,
+    _marker: PhantomData<&'a AudioBufferList>,
+}
+
+impl<'a> BufferListRef<'a> {
+    /// # Safety
+    ///
+    /// `list` must point to a valid `AudioBufferList`, with valid data for all its buffers, that
+    /// outlives `'a` and isn't modified during `'a`.
+    pub unsafe fn from_raw(list: *const AudioBufferList) -> Option<Self> {
+        NonNull::new(list as *mut AudioBufferList).map(|list| Self {
+            list,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { (*self.list.as_ptr()).mNumberBuffers as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn buffers(&self) -> &'a [AudioBuffer] {
+        unsafe { slice::from_raw_parts(buffers_ptr(self.list.as_ptr()), self.len()) }
+    }
+
+    pub fn channel_count(&self) -> u32 {
+        self.buffers().iter().map(|b| b.mNumberChannels).sum()
+    }
+
+    pub fn data<T: Sample>(&self, index: usize) -> Option<&'a [T]> {
+        unsafe { buffer_data(self.buffers().get(index)?) }
+    }
+}
+
+// A mutably borrowed `AudioBufferList`, e.g., the output data handed to an IOProc.
+pub struct BufferListMut<'a> {
+    list: NonNull<AudioBufferList>,
+    _marker: PhantomData<&'a mut AudioBufferList>,
+}
+
+impl<'a> BufferListMut<'a> {
+    /// # Safety
+    ///
+    /// `list` must point to a valid `AudioBufferList`, with valid data for all its buffers, that
+    /// outlives `'a` and isn't accessed by anything else during `'a`.
+    pub unsafe fn from_raw(list: *mut AudioBufferList) -> Option<Self> {
+        NonNull::new(list).map(|list| Self {
+            list,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { (*self.list.as_ptr()).mNumberBuffers as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn buffers(&self) -> &[AudioBuffer] {
+        unsafe { slice::from_raw_parts(buffers_ptr(self.list.as_ptr()), self.len()) }
+    }
+
+    pub fn channel_count(&self) -> u32 {
+        self.buffers().iter().map(|b| b.mNumberChannels).sum()
+    }
+
+    pub fn data<T: Sample>(&self, index: usize) -> Option<&[T]> {
+        unsafe { buffer_data(self.buffers().get(index)?) }
+    }
+
+    pub fn data_mut<T: Sample>(&mut self, index: usize) -> Option<&mut [T]> {
+        let len = self.len();
+        let buffers = unsafe { slice::from_raw_parts_mut(buffers_ptr(self.list.as_ptr()), len) };
+        unsafe { buffer_data_mut(buffers.get_mut(index)?) }
+    }
+}
+
+// Derive the pointer from the whole allocation rather than from a reference to `mBuffers`, whose
+// declared length is one.
+unsafe fn buffers_ptr(list: *mut AudioBufferList) -> *mut AudioBuffer {
+    ptr::addr_of_mut!((*list).mBuffers) as *mut AudioBuffer
+}
+
+unsafe fn buffer_data<T: Sample>(buffer: &AudioBuffer) -> Option<&[T]> {
+    let (data, len) = checked_data::<T>(buffer)?;
+    Some(slice::from_raw_parts(data, len))
+}
+
+unsafe fn buffer_data_mut<T: Sample>(buffer: &mut AudioBuffer) -> Option<&mut [T]> {
+    let (data, len) = checked_data::<T>(buffer)?;
+    Some(slice::from_raw_parts_mut(data, len))
+}
+
+fn checked_data<T: Sample>(buffer: &AudioBuffer) -> Option<(*mut T, usize)> {
+    let data = buffer.mData as *mut T;
+    if data.is_null() || data as usize & (mem::align_of::<T>() - 1) != 0 {
+        return None;
+    }
+    Some((data, buffer.mDataByteSize as usize / mem::size_of::<T>()))
+}
+
+#[test]
+fn test_new_buffer_list() {
+    for buffers in 0..4 {
+        let list = BufferList::new(buffers);
+        assert_eq!(list.len(), buffers);
+        assert_eq!(list.is_empty(), buffers == 0);
+        assert_eq!(
+            list.as_ptr() as usize % mem::align_of::<AudioBufferList>(),
+            0
+        );
+        assert_eq!(list.channel_count(), 0);
+        assert!(list.buffers().iter().all(|b| b.mData.is_null()));
+        assert!(list.data::<f32>(0).is_none());
+    }
+}
+
+#[test]
+fn test_buffer_list_from_bytes() {
+    let header = mem::size_of::<AudioBufferList>() - mem::size_of::<AudioBuffer>();
+    let buffers = [(2_u32, 4096_u32), (6, 12288), (1, 2048)];
+    // Start at an odd offset so the source is misaligned.
+    let mut bytes = vec![0_u8; 1];
+    bytes.extend_from_slice(&(buffers.len() as u32).to_ne_bytes());
+    bytes.resize(1 + header, 0);
+    for (channels, size) in buffers.iter() {
+        let buffer = AudioBuffer {
+            mNumberChannels: *channels,
+            mDataByteSize: *size,
+            mData: NonNull::<u32>::dangling().as_ptr() as *mut c_void,
+        };
+        let buffer = unsafe {
+            slice::from_raw_parts(
+                &buffer as *const AudioBuffer as *const u8,
+                mem::size_of::<AudioBuffer>(),
+            )
+        };
+        bytes.extend_from_slice(buffer);
+    }
+
+    let list = BufferList::from_bytes(&bytes[1..]).unwrap();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list.channel_count(), 9);
+    for (buffer, (channels, size)) in list.buffers().iter().zip(buffers.iter()) {
+        assert_eq!(buffer.mNumberChannels, *channels);
+        assert_eq!(buffer.mDataByteSize, *size);
+        assert!(buffer.mData.is_null());
+    }
+    assert!(list.data::<u8>(0).is_none());
+
+    assert!(BufferList::from_bytes(&bytes[1..bytes.len() - 1]).is_none());
+    assert!(BufferList::from_bytes(&[0, 0]).is_none());
+    assert!(BufferList::from_bytes(&bytes[1..5]).is_none());
+    assert!(BufferList::from_bytes(&0_u32.to_ne_bytes())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_allocate_buffer_list() {
+    let format = StreamFormat::float32(48000.0, 2, true);
+    let mut interleaved = BufferList::allocate(&format, 5);
+    assert_eq!(interleaved.len(), 1);
+    assert_eq!(interleaved.channel_count(), 2);
+    assert_eq!(interleaved.buffers()[0].mDataByteSize, 40);
+    {
+        let samples = interleaved.data_mut::<f32>(0).unwrap();
+        assert_eq!(samples.len(), 10);
+        assert!(samples.iter().all(|s| *s == 0.0));
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = i as f32;
+        }
+    }
+    assert_eq!(interleaved.data::<f32>(0).unwrap()[9], 9.0);
+    assert_eq!(interleaved.data::<f64>(0).unwrap().len(), 5);
+    assert_eq!(interleaved.data::<u8>(0).unwrap().len(), 40);
+    assert!(interleaved.data::<f32>(1).is_none());
+
+    let format = StreamFormat::int16(44100.0, 3, false);
+    let mut planar = BufferList::allocate(&format, 7);
+    assert_eq!(planar.len(), 3);
+    assert_eq!(planar.channel_count(), 3);
+    for channel in 0..3 {
+        let samples = planar.data_mut::<i16>(channel).unwrap();
+        assert_eq!(samples.len(), 7);
+        samples.iter_mut().for_each(|s| *s = channel as i16);
+    }
+    {
+        let mut view = planar.view_mut();
+        assert_eq!(view.len(), 3);
+        view.data_mut::<i16>(2).unwrap()[6] = -1;
+    }
+    let view = planar.view();
+    assert_eq!(view.data::<i16>(0).unwrap(), &[0; 7]);
+    assert_eq!(view.data::<i16>(1).unwrap(), &[1; 7]);
+    assert_eq!(view.data::<i16>(2).unwrap()[6], -1);
+    // The second byte of an i16 buffer is never aligned for an i16.
+    let misaligned = AudioBuffer {
+        mNumberChannels: 1,
+        mDataByteSize: 2,
+        mData: unsafe { (view.buffers()[0].mData as *mut u8).add(1) } as *mut c_void,
+    };
+    assert!(checked_data::<i16>(&misaligned).is_none());
+
+    let mut packets = BufferList::allocate_bytes(1, 2, 1500);
+    assert_eq!(packets.channel_count(), 2);
+    unsafe { (*packets.as_mut_ptr()).mBuffers[0].mDataByteSize = 371 };
+    assert_eq!(packets.data::<u8>(0).unwrap().len(), 371);
+    packets.reset_data_sizes();
+    assert_eq!(packets.data::<u8>(0).unwrap().len(), 1500);
+}
+
+// `mDataByteSize` can't describe buffers of 4 GiB or more, so they're refused before allocating.
+#[cfg(target_pointer_width = "64")]
+#[test]
+#[should_panic(expected = "too large")]
+fn test_allocate_too_many_bytes() {
+    BufferList::allocate_bytes(1, 1, u32::MAX as usize + 1);
+}
+
+#[test]
+fn test_buffer_list_ref_from_raw() {
+    assert!(unsafe { BufferListRef::from_raw(ptr::null()) }.is_none());
+    assert!(unsafe { BufferListMut::from_raw(ptr::null_mut()) }.is_none());
+
+    let format = StreamFormat::float32(48000.0, 2, false);
+    let mut list = BufferList::allocate(&format, 4);
+    list.data_mut::<f32>(1).unwrap()[3] = 0.5;
+    let view = unsafe { BufferListRef::from_raw(list.as_ptr()) }.unwrap();
+    assert_eq!(view.len(), 2);
+    assert_eq!(view.channel_count(), 2);
+    assert_eq!(view.data::<f32>(1).unwrap(), &[0.0, 0.0, 0.0, 0.5]);
+}
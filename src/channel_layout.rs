@@ -0,0 +1,423 @@
+use super::sys::{
+    kAudioChannelLabel_Center, kAudioChannelLabel_CenterSurround, kAudioChannelLabel_Discrete_0,
+    kAudioChannelLabel_Discrete_65535, kAudioChannelLabel_LFE2, kAudioChannelLabel_LFEScreen,
+    kAudioChannelLabel_Left, kAudioChannelLabel_LeftCenter, kAudioChannelLabel_LeftSurround,
+    kAudioChannelLabel_LeftSurroundDirect, kAudioChannelLabel_LeftTotal,
+    kAudioChannelLabel_LeftWide, kAudioChannelLabel_Mono, kAudioChannelLabel_RearSurroundLeft,
+    kAudioChannelLabel_RearSurroundRight, kAudioChannelLabel_Right, kAudioChannelLabel_RightCenter,
+    kAudioChannelLabel_RightSurround, kAudioChannelLabel_RightSurroundDirect,
+    kAudioChannelLabel_RightTotal, kAudioChannelLabel_RightWide, kAudioChannelLabel_TopBackCenter,
+    kAudioChannelLabel_TopBackLeft, kAudioChannelLabel_TopBackRight,
+    kAudioChannelLabel_TopCenterSurround, kAudioChannelLabel_Unknown, kAudioChannelLabel_Unused,
+    kAudioChannelLabel_UseCoordinates, kAudioChannelLabel_VerticalHeightCenter,
+    kAudioChannelLabel_VerticalHeightLeft, kAudioChannelLabel_VerticalHeightRight,
+    kAudioChannelLayoutTag_DiscreteInOrder, kAudioChannelLayoutTag_MPEG_3_0_A,
+    kAudioChannelLayoutTag_MPEG_3_0_B, kAudioChannelLayoutTag_MPEG_4_0_A,
+    kAudioChannelLayoutTag_MPEG_4_0_B, kAudioChannelLayoutTag_MPEG_5_0_A,
+    kAudioChannelLayoutTag_MPEG_5_0_B, kAudioChannelLayoutTag_MPEG_5_0_C,
+    kAudioChannelLayoutTag_MPEG_5_0_D, kAudioChannelLayoutTag_MPEG_5_1_A,
+    kAudioChannelLayoutTag_MPEG_5_1_B, kAudioChannelLayoutTag_MPEG_5_1_C,
+    kAudioChannelLayoutTag_MPEG_5_1_D, kAudioChannelLayoutTag_MPEG_6_1_A,
+    kAudioChannelLayoutTag_MPEG_7_1_A, kAudioChannelLayoutTag_MPEG_7_1_B,
+    kAudioChannelLayoutTag_MPEG_7_1_C, kAudioChannelLayoutTag_Mono,
+    kAudioChannelLayoutTag_Quadraphonic, kAudioChannelLayoutTag_Stereo,
+    kAudioChannelLayoutTag_UseChannelBitmap, kAudioChannelLayoutTag_UseChannelDescriptions,
+};
+use std::mem;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelLabel {
+    Unknown,
+    Unused,
+    UseCoordinates,
+    Left,
+    Right,
+    Center,
+    LFEScreen,
+    LeftSurround,
+    RightSurround,
+    LeftCenter,
+    RightCenter,
+    CenterSurround,
+    LeftSurroundDirect,
+    RightSurroundDirect,
+    TopCenterSurround,
+    VerticalHeightLeft,
+    VerticalHeightCenter,
+    VerticalHeightRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+    RearSurroundLeft,
+    RearSurroundRight,
+    LeftWide,
+    RightWide,
+    LFE2,
+    LeftTotal,
+    RightTotal,
+    Mono,
+    // A channel with no speaker position, numbered from 0.
+    Discrete(u16),
+    // Any label not listed above.
+    Other(u32),
+}
+
+// The named labels and their `kAudioChannelLabel_*` values.
+const LABELS: &[(ChannelLabel, u32)] = {
+    use ChannelLabel::*;
+    &[
+        (Unknown, kAudioChannelLabel_Unknown),
+        (Unused, kAudioChannelLabel_Unused),
+        (UseCoordinates, kAudioChannelLabel_UseCoordinates),
+        (Left, kAudioChannelLabel_Left),
+        (Right, kAudioChannelLabel_Right),
+        (Center, kAudioChannelLabel_Center),
+        (LFEScreen, kAudioChannelLabel_LFEScreen),
+        (LeftSurround, kAudioChannelLabel_LeftSurround),
+        (RightSurround, kAudioChannelLabel_RightSurround),
+        (LeftCenter, kAudioChannelLabel_LeftCenter),
+        (RightCenter, kAudioChannelLabel_RightCenter),
+        (CenterSurround, kAudioChannelLabel_CenterSurround),
+        (LeftSurroundDirect, kAudioChannelLabel_LeftSurroundDirect),
+        (RightSurroundDirect, kAudioChannelLabel_RightSurroundDirect),
+        (TopCenterSurround, kAudioChannelLabel_TopCenterSurround),
+        (VerticalHeightLeft, kAudioChannelLabel_VerticalHeightLeft),
+        (
+            VerticalHeightCenter,
+            kAudioChannelLabel_VerticalHeightCenter,
+        ),
+        (VerticalHeightRight, kAudioChannelLabel_VerticalHeightRight),
+        (TopBackLeft, kAudioChannelLabel_TopBackLeft),
+        (TopBackCenter, kAudioChannelLabel_TopBackCenter),
+        (TopBackRight, kAudioChannelLabel_TopBackRight),
+        (RearSurroundLeft, kAudioChannelLabel_RearSurroundLeft),
+        (RearSurroundRight, kAudioChannelLabel_RearSurroundRight),
+        (LeftWide, kAudioChannelLabel_LeftWide),
+        (RightWide, kAudioChannelLabel_RightWide),
+        (LFE2, kAudioChannelLabel_LFE2),
+        (LeftTotal, kAudioChannelLabel_LeftTotal),
+        (RightTotal, kAudioChannelLabel_RightTotal),
+        (Mono, kAudioChannelLabel_Mono),
+    ]
+};
+
+impl From<u32> for ChannelLabel {
+    fn from(label: u32) -> Self {
+        if (kAudioChannelLabel_Discrete_0..=kAudioChannelLabel_Discrete_65535).contains(&label) {
+            return ChannelLabel::Discrete((label - kAudioChannelLabel_Discrete_0) as u16);
+        }
+        LABELS
+            .iter()
+            .find(|(_, l)| *l == label)
+            .map_or(ChannelLabel::Other(label), |(c, _)| *c)
+    }
+}
+
+impl From<ChannelLabel> for u32 {
+    fn from(label: ChannelLabel) -> Self {
+        match label {
+            ChannelLabel::Discrete(n) => kAudioChannelLabel_Discrete_0 + u32::from(n),
+            ChannelLabel::Other(l) => l,
+            _ => LABELS
+                .iter()
+                .find(|(c, _)| *c == label)
+                .map(|(_, l)| *l)
+                .expect("every named label is listed in LABELS"),
+        }
+    }
+}
+
+// A safe mirror of `AudioChannelDescription`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelDescription {
+    pub label: ChannelLabel,
+    pub flags: u32,
+    pub coordinates: [f32; 3],
+}
+
+impl From<ChannelLabel> for ChannelDescription {
+    fn from(label: ChannelLabel) -> Self {
+        Self {
+            label,
+            flags: 0,
+            coordinates: [0.0; 3],
+        }
+    }
+}
+
+// A safe mirror of the variable-length `AudioChannelLayout`. The layout is described by either
+// a layout tag, a channel bitmap, or a list of channel descriptions, depending on `tag`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelLayout {
+    pub tag: u32,
+    pub bitmap: u32,
+    pub descriptions: Vec<ChannelDescription>,
+}
+
+// The size of `mChannelLayoutTag`, `mChannelBitmap` and `mNumberChannelDescriptions`.
+const HEADER_WORDS: usize = 3;
+// The size of `mChannelLabel`, `mChannelFlags` and `mCoordinates`.
+const DESCRIPTION_WORDS: usize = 5;
+
+impl ChannelLayout {
+    pub fn from_labels(labels: &[ChannelLabel]) -> Self {
+        Self {
+            tag: kAudioChannelLayoutTag_UseChannelDescriptions,
+            bitmap: 0,
+            descriptions: labels
+                .iter()
+                .map(|l| ChannelDescription::from(*l))
+                .collect(),
+        }
+    }
+
+    // Parse an `AudioChannelLayout` from the bytes returned by the HAL. Returns `None` if `bytes`
+    // is too short for the number of channel descriptions it declares.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let word = |i: usize| -> Option<u32> {
+            let start = i * mem::size_of::<u32>();
+            let bytes = bytes.get(start..start + mem::size_of::<u32>())?;
+            Some(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        let tag = word(0)?;
+        let bitmap = word(1)?;
+        let count = word(2)? as usize;
+        let descriptions = (0..count)
+            .map(|i| {
+                let base = HEADER_WORDS + i * DESCRIPTION_WORDS;
+                Some(ChannelDescription {
+                    label: ChannelLabel::from(word(base)?),
+                    flags: word(base + 1)?,
+                    coordinates: [
+                        f32::from_bits(word(base + 2)?),
+                        f32::from_bits(word(base + 3)?),
+                        f32::from_bits(word(base + 4)?),
+                    ],
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            tag,
+            bitmap,
+            descriptions,
+        })
+    }
+
+    // Serialize into an `AudioChannelLayout`. All its fields are 32 bits wide, so a `u32` buffer
+    // is correctly aligned for it.
+    pub fn to_words(&self) -> Vec<u32> {
+        let mut words =
+            Vec::with_capacity(HEADER_WORDS + self.descriptions.len() * DESCRIPTION_WORDS);
+        words.push(self.tag);
+        words.push(self.bitmap);
+        words.push(self.descriptions.len() as u32);
+        for d in self.descriptions.iter() {
+            words.push(u32::from(d.label));
+            words.push(d.flags);
+            words.extend(d.coordinates.iter().map(|c| c.to_bits()));
+        }
+        words
+    }
+
+    // The label of each channel, in channel order, or `None` if the layout tag is not one that
+    // calx knows how to expand.
+    pub fn labels(&self) -> Option<Vec<ChannelLabel>> {
+        use ChannelLabel::*;
+        let labels = match self.tag {
+            t if t == kAudioChannelLayoutTag_UseChannelDescriptions => {
+                return Some(self.descriptions.iter().map(|d| d.label).collect())
+            }
+            t if t == kAudioChannelLayoutTag_UseChannelBitmap => {
+                // Bit N of the bitmap stands for the label N + 1, from `Left` to `TopBackRight`.
+                return Some(
+                    (0..18)
+                        .filter(|bit| self.bitmap & (1 << bit) != 0)
+                        .map(|bit| ChannelLabel::from(bit + 1))
+                        .collect(),
+                );
+            }
+            t if t & 0xFFFF_0000 == kAudioChannelLayoutTag_DiscreteInOrder => {
+                return Some((0..(t & 0xFFFF) as u16).map(Discrete).collect());
+            }
+            t if t == kAudioChannelLayoutTag_Mono => vec![Mono],
+            t if t == kAudioChannelLayoutTag_Stereo => vec![Left, Right],
+            t if t == kAudioChannelLayoutTag_Quadraphonic => {
+                vec![Left, Right, LeftSurround, RightSurround]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_3_0_A => vec![Left, Right, Center],
+            t if t == kAudioChannelLayoutTag_MPEG_3_0_B => vec![Center, Left, Right],
+            t if t == kAudioChannelLayoutTag_MPEG_4_0_A => {
+                vec![Left, Right, Center, CenterSurround]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_4_0_B => {
+                vec![Center, Left, Right, CenterSurround]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_0_A => {
+                vec![Left, Right, Center, LeftSurround, RightSurround]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_0_B => {
+                vec![Left, Right, LeftSurround, RightSurround, Center]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_0_C => {
+                vec![Left, Center, Right, LeftSurround, RightSurround]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_0_D => {
+                vec![Center, Left, Right, LeftSurround, RightSurround]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_1_A => {
+                vec![Left, Right, Center, LFEScreen, LeftSurround, RightSurround]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_1_B => {
+                vec![Left, Right, LeftSurround, RightSurround, Center, LFEScreen]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_1_C => {
+                vec![Left, Center, Right, LeftSurround, RightSurround, LFEScreen]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_5_1_D => {
+                vec![Center, Left, Right, LeftSurround, RightSurround, LFEScreen]
+            }
+            t if t == kAudioChannelLayoutTag_MPEG_6_1_A => vec![
+                Left,
+                Right,
+                Center,
+                LFEScreen,
+                LeftSurround,
+                RightSurround,
+                CenterSurround,
+            ],
+            t if t == kAudioChannelLayoutTag_MPEG_7_1_A => vec![
+                Left,
+                Right,
+                Center,
+                LFEScreen,
+                LeftSurround,
+                RightSurround,
+                LeftCenter,
+                RightCenter,
+            ],
+            t if t == kAudioChannelLayoutTag_MPEG_7_1_B => vec![
+                Center,
+                LeftCenter,
+                RightCenter,
+                Left,
+                Right,
+                LeftSurround,
+                RightSurround,
+                LFEScreen,
+            ],
+            t if t == kAudioChannelLayoutTag_MPEG_7_1_C => vec![
+                Left,
+                Right,
+                Center,
+                LFEScreen,
+                LeftSurround,
+                RightSurround,
+                RearSurroundLeft,
+                RearSurroundRight,
+            ],
+            _ => return None,
+        };
+        Some(labels)
+    }
+
+    // The index of the first channel carrying `label`.
+    pub fn channel_of(&self, label: ChannelLabel) -> Option<usize> {
+        self.labels()?.iter().position(|l| *l == label)
+    }
+
+    // The number of channels, or `None` if it can't be told from the layout.
+    pub fn channel_count(&self) -> Option<usize> {
+        if let Some(labels) = self.labels() {
+            return Some(labels.len());
+        }
+        // Other layout tags carry their channel count in the low 16 bits.
+        let count = (self.tag & 0xFFFF) as usize;
+        if count > 0 {
+            Some(count)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_channel_label_conversion() {
+    for (label, value) in LABELS.iter() {
+        assert_eq!(ChannelLabel::from(*value), *label);
+        assert_eq!(u32::from(*label), *value);
+    }
+    assert_eq!(ChannelLabel::from((1 << 16) | 3), ChannelLabel::Discrete(3));
+    assert_eq!(u32::from(ChannelLabel::Discrete(3)), (1 << 16) | 3);
+    assert_eq!(ChannelLabel::from(301), ChannelLabel::Other(301));
+    assert_eq!(u32::from(ChannelLabel::Other(301)), 301);
+}
+
+#[test]
+fn test_channel_layout_bytes() {
+    let mut layout = ChannelLayout::from_labels(&[
+        ChannelLabel::Left,
+        ChannelLabel::Right,
+        ChannelLabel::Center,
+        ChannelLabel::LFEScreen,
+    ]);
+    layout.descriptions[2].coordinates = [0.0, 1.0, -0.5];
+    let words = layout.to_words();
+    assert_eq!(words.len(), 3 + 4 * 5);
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|w| w.to_ne_bytes().to_vec())
+        .collect();
+    assert_eq!(ChannelLayout::from_bytes(&bytes), Some(layout.clone()));
+    // Truncated descriptions.
+    assert_eq!(ChannelLayout::from_bytes(&bytes[..bytes.len() - 1]), None);
+    assert_eq!(ChannelLayout::from_bytes(&bytes[..8]), None);
+
+    assert_eq!(layout.channel_of(ChannelLabel::LFEScreen), Some(3));
+    assert_eq!(layout.channel_of(ChannelLabel::LeftSurround), None);
+    assert_eq!(layout.channel_count(), Some(4));
+}
+
+#[test]
+fn test_channel_layout_labels() {
+    use ChannelLabel::*;
+
+    let tagged = ChannelLayout {
+        tag: kAudioChannelLayoutTag_MPEG_5_1_D,
+        bitmap: 0,
+        descriptions: Vec::new(),
+    };
+    assert_eq!(
+        tagged.labels().unwrap(),
+        vec![Center, Left, Right, LeftSurround, RightSurround, LFEScreen]
+    );
+    assert_eq!(tagged.channel_of(LFEScreen), Some(5));
+
+    let bitmap = ChannelLayout {
+        tag: kAudioChannelLayoutTag_UseChannelBitmap,
+        bitmap: 0b1011 | (1 << 17),
+        descriptions: Vec::new(),
+    };
+    assert_eq!(
+        bitmap.labels().unwrap(),
+        vec![Left, Right, LFEScreen, TopBackRight]
+    );
+
+    let discrete = ChannelLayout {
+        tag: kAudioChannelLayoutTag_DiscreteInOrder | 3,
+        bitmap: 0,
+        descriptions: Vec::new(),
+    };
+    assert_eq!(
+        discrete.labels().unwrap(),
+        vec![Discrete(0), Discrete(1), Discrete(2)]
+    );
+
+    // An Ambisonic B-format tag isn't expanded, but its channel count is known.
+    let unsupported = ChannelLayout {
+        tag: (107 << 16) | 4,
+        bitmap: 0,
+        descriptions: Vec::new(),
+    };
+    assert_eq!(unsupported.labels(), None);
+    assert_eq!(unsupported.channel_count(), Some(4));
+}
@@ -0,0 +1,685 @@
+use super::buffer_list::{BufferListMut, BufferListRef};
+use super::stream_format::StreamFormat;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConvertError {
+    // The format isn't linear PCM, or its samples aren't 8 to 32 bit integers or 32 or 64 bit
+    // floats.
+    UnsupportedFormat(StreamFormat),
+    // The formats have different numbers of channels. Channels aren't mixed.
+    ChannelCountMismatch,
+    // The number of buffers doesn't match the format.
+    BufferCountMismatch,
+    // The output buffers can't hold the converted input.
+    OutputTooSmall,
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::UnsupportedFormat(format) => {
+                write!(f, "can't convert from or to {:?}", format)
+            }
+            ConvertError::ChannelCountMismatch => {
+                write!(f, "the formats have different channel counts")
+            }
+            ConvertError::BufferCountMismatch => {
+                write!(f, "the number of buffers doesn't match the format")
+            }
+            ConvertError::OutputTooSmall => write!(f, "the output buffers are too small"),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+// The noise added to samples that lose precision, so the error isn't correlated with the signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    None,
+    // Triangular noise of up to one least significant bit.
+    Triangular,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Float,
+    Int {
+        bits: u32,
+        signed: bool,
+        // The sample is in the high bits of its container rather than the low ones.
+        aligned_high: bool,
+    },
+}
+
+// Where the samples of a format are and how they are encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Layout {
+    encoding: Encoding,
+    // The size of a sample's container, in bytes.
+    size: usize,
+    big_endian: bool,
+    interleaved: bool,
+    channels: usize,
+    // The size of a frame in one buffer, in bytes.
+    frame_size: usize,
+}
+
+impl Layout {
+    fn new(format: &StreamFormat) -> Option<Self> {
+        let channels = format.channels_per_frame as usize;
+        let channels_per_buffer = format.channels_per_buffer() as usize;
+        let frame_size = format.bytes_per_frame as usize;
+        if !format.is_linear_pcm()
+            || format.frames_per_packet != 1
+            || channels == 0
+            || frame_size == 0
+            || !frame_size.is_multiple_of(channels_per_buffer)
+        {
+            return None;
+        }
+        let size = frame_size / channels_per_buffer;
+        let bits = format.bits_per_channel;
+        let encoding = if format.is_float() {
+            if (size != 4 && size != 8) || bits as usize != size * 8 {
+                return None;
+            }
+            Encoding::Float
+        } else {
+            if size > 4 || bits < 8 || bits as usize > size * 8 {
+                return None;
+            }
+            Encoding::Int {
+                bits,
+                signed: format.is_signed_integer(),
+                aligned_high: format.is_aligned_high(),
+            }
+        };
+        Some(Self {
+            encoding,
+            size,
+            big_endian: format.is_big_endian(),
+            interleaved: format.is_interleaved(),
+            channels,
+            frame_size,
+        })
+    }
+
+    fn buffers(&self) -> usize {
+        if self.interleaved {
+            1
+        } else {
+            self.channels
+        }
+    }
+
+    // The buffer holding a channel, the offset of its first sample and the distance between its
+    // samples, in bytes.
+    fn channel(&self, channel: usize) -> (usize, usize, usize) {
+        if self.interleaved {
+            (0, channel * self.size, self.frame_size)
+        } else {
+            (channel, 0, self.frame_size)
+        }
+    }
+
+    fn is_native(&self, encoding: Encoding, size: usize) -> bool {
+        self.encoding == encoding
+            && self.size == size
+            && self.big_endian == cfg!(target_endian = "big")
+    }
+
+    // Decode a sample into [-1, 1).
+    fn read(&self, bytes: &[u8]) -> f64 {
+        let mut raw: u64 = 0;
+        if self.big_endian {
+            for byte in bytes {
+                raw = raw << 8 | u64::from(*byte);
+            }
+        } else {
+            for byte in bytes.iter().rev() {
+                raw = raw << 8 | u64::from(*byte);
+            }
+        }
+        match self.encoding {
+            Encoding::Float if self.size == 4 => f64::from(f32::from_bits(raw as u32)),
+            Encoding::Float => f64::from_bits(raw),
+            Encoding::Int {
+                bits,
+                signed,
+                aligned_high,
+            } => {
+                let mut value = raw as u32;
+                if aligned_high {
+                    value >>= self.size as u32 * 8 - bits;
+                }
+                // Move the sample to the top of a 32 bit integer, dropping any padding bits.
+                let shift = 32 - bits;
+                value <<= shift;
+                if !signed {
+                    value ^= 1 << 31;
+                }
+                f64::from(value as i32 >> shift) / full_scale(bits)
+            }
+        }
+    }
+
+    // Encode a sample in [-1, 1), adding `noise` least significant bits before rounding.
+    fn write(&self, sample: f64, noise: f64, bytes: &mut [u8]) {
+        let mut raw: u64 = match self.encoding {
+            Encoding::Float if self.size == 4 => u64::from((sample as f32).to_bits()),
+            Encoding::Float => sample.to_bits(),
+            Encoding::Int {
+                bits,
+                signed,
+                aligned_high,
+            } => {
+                let scale = full_scale(bits);
+                // NaN is converted to silence.
+                let value = (sample * scale + noise).round().clamp(-scale, scale - 1.0) as i32;
+                let shift = 32 - bits;
+                let mut value = (value as u32) << shift;
+                if !signed {
+                    value ^= 1 << 31;
+                }
+                value >>= shift;
+                if aligned_high {
+                    value <<= self.size as u32 * 8 - bits;
+                }
+                u64::from(value)
+            }
+        };
+        if self.big_endian {
+            for byte in bytes.iter_mut().rev() {
+                *byte = raw as u8;
+                raw >>= 8;
+            }
+        } else {
+            for byte in bytes.iter_mut() {
+                *byte = raw as u8;
+                raw >>= 8;
+            }
+        }
+    }
+}
+
+fn full_scale(bits: u32) -> f64 {
+    (1u64 << (bits - 1)) as f64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Path {
+    // The samples are encoded the same way, so only their bytes are moved.
+    Copy,
+    // Native-endian 16 bit integers to native-endian 32 bit floats, and back.
+    Int16ToFloat32,
+    Float32ToInt16,
+    Generic,
+}
+
+// Converts linear PCM audio between sample formats, bit depths, endianness and interleaving.
+// Sample rates and channel counts aren't converted.
+pub struct Converter {
+    source: StreamFormat,
+    destination: StreamFormat,
+    input: Layout,
+    output: Layout,
+    path: Path,
+    dither: Dither,
+    // Whether the destination is less precise than the source, so it's dithered.
+    lossy: bool,
+    random: u32,
+}
+
+impl Converter {
+    pub fn new(source: &StreamFormat, destination: &StreamFormat) -> Result<Self, ConvertError> {
+        let input = Layout::new(source).ok_or(ConvertError::UnsupportedFormat(*source))?;
+        let output =
+            Layout::new(destination).ok_or(ConvertError::UnsupportedFormat(*destination))?;
+        if input.channels != output.channels {
+            return Err(ConvertError::ChannelCountMismatch);
+        }
+
+        let int16 = Encoding::Int {
+            bits: 16,
+            signed: true,
+            aligned_high: false,
+        };
+        let path = if input.encoding == output.encoding
+            && input.size == output.size
+            && input.big_endian == output.big_endian
+        {
+            Path::Copy
+        } else if input.is_native(int16, 2) && output.is_native(Encoding::Float, 4) {
+            Path::Int16ToFloat32
+        } else if input.is_native(Encoding::Float, 4) && output.is_native(int16, 2) {
+            Path::Float32ToInt16
+        } else {
+            Path::Generic
+        };
+        let lossy = match (input.encoding, output.encoding) {
+            (_, Encoding::Float) => false,
+            (Encoding::Float, Encoding::Int { .. }) => true,
+            (Encoding::Int { bits: from, .. }, Encoding::Int { bits: to, .. }) => from > to,
+        };
+
+        Ok(Self {
+            source: *source,
+            destination: *destination,
+            input,
+            output,
+            path,
+            dither: Dither::Triangular,
+            lossy,
+            random: 0x9e37_79b9,
+        })
+    }
+
+    pub fn source(&self) -> &StreamFormat {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &StreamFormat {
+        &self.destination
+    }
+
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.dither = dither;
+    }
+
+    // Convert all the whole frames in `input`, which holds one buffer per buffer of the source
+    // format, into `output`. Returns the number of frames converted.
+    pub fn convert(
+        &mut self,
+        input: &[&[u8]],
+        output: &mut [&mut [u8]],
+    ) -> Result<usize, ConvertError> {
+        self.convert_buffers(input, output)
+    }
+
+    pub fn convert_buffer_list(
+        &mut self,
+        input: &BufferListRef<'_>,
+        output: &mut BufferListMut<'_>,
+    ) -> Result<usize, ConvertError> {
+        self.convert_buffers(input, output)
+    }
+
+    fn convert_buffers<I: Buffers + ?Sized, O: BuffersMut + ?Sized>(
+        &mut self,
+        input: &I,
+        output: &mut O,
+    ) -> Result<usize, ConvertError> {
+        if input.len() != self.input.buffers() || output.len() != self.output.buffers() {
+            return Err(ConvertError::BufferCountMismatch);
+        }
+        let frames = (0..input.len())
+            .map(|i| input.get(i).len() / self.input.frame_size)
+            .min()
+            .unwrap_or(0);
+        let bytes = frames * self.output.frame_size;
+        if (0..output.len()).any(|i| output.get_mut(i).len() < bytes) {
+            return Err(ConvertError::OutputTooSmall);
+        }
+
+        let (input_layout, output_layout) = (self.input, self.output);
+        if self.path == Path::Copy
+            && input_layout.interleaved == output_layout.interleaved
+            && input_layout.frame_size == output_layout.frame_size
+        {
+            for i in 0..input.len() {
+                output.get_mut(i)[..bytes].copy_from_slice(&input.get(i)[..bytes]);
+            }
+            return Ok(frames);
+        }
+
+        for channel in 0..input_layout.channels {
+            let (from, mut i, input_stride) = input_layout.channel(channel);
+            let (to, mut o, output_stride) = output_layout.channel(channel);
+            let source = input.get(from);
+            let destination = output.get_mut(to);
+            for _ in 0..frames {
+                let sample = &source[i..i + input_layout.size];
+                let target = &mut destination[o..o + output_layout.size];
+                match self.path {
+                    Path::Copy => target.copy_from_slice(sample),
+                    Path::Int16ToFloat32 => {
+                        let value = i16::from_ne_bytes([sample[0], sample[1]]);
+                        let value = f32::from(value) / 32768.0;
+                        target.copy_from_slice(&value.to_ne_bytes());
+                    }
+                    Path::Float32ToInt16 => {
+                        let value =
+                            f32::from_ne_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                        let noise = self.noise();
+                        let value = (f64::from(value) * 32768.0 + noise)
+                            .round()
+                            .clamp(-32768.0, 32767.0) as i16;
+                        target.copy_from_slice(&value.to_ne_bytes());
+                    }
+                    Path::Generic => {
+                        let noise = self.noise();
+                        output_layout.write(input_layout.read(sample), noise, target);
+                    }
+                }
+                i += input_stride;
+                o += output_stride;
+            }
+        }
+        Ok(frames)
+    }
+
+    // Triangular noise in (-1, 1), from the sum of two uniform values in [-0.5, 0.5).
+    fn noise(&mut self) -> f64 {
+        if !self.lossy || self.dither == Dither::None {
+            return 0.0;
+        }
+        self.next_uniform() + self.next_uniform()
+    }
+
+    // A xorshift generator, which is cheap and good enough for dither.
+    fn next_uniform(&mut self) -> f64 {
+        let mut x = self.random;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random = x;
+        f64::from(x) / 4_294_967_296.0 - 0.5
+    }
+}
+
+// The buffers of a conversion, one per buffer of the format.
+trait Buffers {
+    fn len(&self) -> usize;
+    fn get(&self, index: usize) -> &[u8];
+}
+
+trait BuffersMut {
+    fn len(&self) -> usize;
+    fn get_mut(&mut self, index: usize) -> &mut [u8];
+}
+
+impl Buffers for [&[u8]] {
+    fn len(&self) -> usize {
+        <[&[u8]]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> &[u8] {
+        self[index]
+    }
+}
+
+impl BuffersMut for [&mut [u8]] {
+    fn len(&self) -> usize {
+        <[&mut [u8]]>::len(self)
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut [u8] {
+        self[index]
+    }
+}
+
+// Buffers without data are treated as empty.
+impl Buffers for BufferListRef<'_> {
+    fn len(&self) -> usize {
+        BufferListRef::len(self)
+    }
+
+    fn get(&self, index: usize) -> &[u8] {
+        self.data::<u8>(index).unwrap_or(&[])
+    }
+}
+
+impl BuffersMut for BufferListMut<'_> {
+    fn len(&self) -> usize {
+        BufferListMut::len(self)
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut [u8] {
+        self.data_mut::<u8>(index).unwrap_or(&mut [])
+    }
+}
+
+// `kAudioFormatFlagIsBigEndian`, `kAudioFormatFlagIsSignedInteger`,
+// `kAudioFormatFlagIsAlignedHigh` and `kAudioFormatFlagIsNonInterleaved`.
+#[cfg(test)]
+const BIG: u32 = 1 << 1;
+#[cfg(test)]
+const SIGNED: u32 = 1 << 2;
+#[cfg(test)]
+const HIGH: u32 = 1 << 4;
+#[cfg(test)]
+const PLANAR: u32 = 1 << 5;
+
+// A little-endian, unsigned integer format unless `flags` say otherwise.
+#[cfg(test)]
+fn test_format(bits: u32, size: u32, float: bool, flags: u32, channels: u32) -> StreamFormat {
+    let mut format = StreamFormat::linear_pcm(48000.0, channels, size * 8, float, true);
+    let float_and_packed = format.flags & ((1 << 0) | (1 << 3));
+    format.flags = flags | float_and_packed;
+    format.bits_per_channel = bits;
+    if bits != size * 8 {
+        // Clear `kAudioFormatFlagIsPacked`.
+        format.flags &= !(1 << 3);
+    }
+    if !format.is_interleaved() {
+        format.bytes_per_frame = size;
+        format.bytes_per_packet = size;
+    }
+    format
+}
+
+// Convert all of `input` with a converter from and to any format.
+#[cfg(test)]
+fn test_convert(converter: &mut Converter, input: &[Vec<u8>], frames: usize) -> Vec<Vec<u8>> {
+    let layout = converter.output;
+    let mut output = vec![vec![0xa5; frames * layout.frame_size]; layout.buffers()];
+    let input: Vec<&[u8]> = input.iter().map(|b| b.as_slice()).collect();
+    let mut slices: Vec<&mut [u8]> = output.iter_mut().map(|b| b.as_mut_slice()).collect();
+    assert_eq!(converter.convert(&input, &mut slices), Ok(frames));
+    output
+}
+
+#[test]
+fn test_convert_known_values() {
+    let float = StreamFormat::float32(48000.0, 1, true);
+    let input = [[0.5f32, -1.0, 0.0]
+        .iter()
+        .flat_map(|s| s.to_ne_bytes().to_vec())
+        .collect::<Vec<u8>>()];
+
+    let cases: [(StreamFormat, &[u8]); 4] = [
+        (
+            test_format(24, 3, false, BIG | SIGNED, 1),
+            &[0x40, 0, 0, 0x80, 0, 0, 0, 0, 0],
+        ),
+        (test_format(8, 1, false, 0, 1), &[0xc0, 0x00, 0x80]),
+        (
+            test_format(24, 4, false, SIGNED | HIGH, 1),
+            &[0, 0, 0, 0x40, 0, 0, 0, 0x80, 0, 0, 0, 0],
+        ),
+        (
+            test_format(24, 4, false, SIGNED, 1),
+            &[0, 0, 0x40, 0, 0, 0, 0x80, 0, 0, 0, 0, 0],
+        ),
+    ];
+    for (format, expected) in cases.iter() {
+        let mut converter = Converter::new(&float, format).unwrap();
+        converter.set_dither(Dither::None);
+        assert_eq!(test_convert(&mut converter, &input, 3)[0], *expected);
+    }
+}
+
+#[test]
+fn test_convert_every_format_pair() {
+    const CHANNELS: usize = 2;
+
+    let mut formats = vec![];
+    for &interleaving in [0, PLANAR].iter() {
+        for &endianness in [0, BIG].iter() {
+            for &(bits, size) in [(8, 1), (16, 2), (20, 3), (24, 3), (24, 4), (32, 4)].iter() {
+                let alignments: &[u32] = if bits == size * 8 { &[0] } else { &[0, HIGH] };
+                for &signedness in [0, SIGNED].iter() {
+                    for &alignment in alignments {
+                        let flags = interleaving | endianness | signedness | alignment;
+                        formats.push(test_format(bits, size, false, flags, CHANNELS as u32));
+                    }
+                }
+            }
+            for &size in [4, 8].iter() {
+                let flags = interleaving | endianness;
+                formats.push(test_format(size * 8, size, true, flags, CHANNELS as u32));
+            }
+        }
+    }
+
+    // Every value is exactly representable in a 64 bit float.
+    let reference = StreamFormat::linear_pcm(48000.0, CHANNELS as u32, 64, true, true);
+    let values: Vec<f64> = [-1.0, -0.5, 0.0, 0.25, 0.999_999, 1.0 - 1.0 / 65536.0]
+        .iter()
+        .copied()
+        .chain((0..32).map(|i| (i as f64 * 0.37).sin() * 0.9))
+        .collect();
+    let input = vec![values
+        .iter()
+        .flat_map(|v| v.to_ne_bytes().to_vec())
+        .collect::<Vec<u8>>()];
+    let frames = values.len() / CHANNELS;
+
+    // The value a format stores for `value`, without dither.
+    let quantize = |format: &StreamFormat, value: f64| -> f64 {
+        if format.is_float() {
+            if format.bits_per_channel == 32 {
+                f64::from(value as f32)
+            } else {
+                value
+            }
+        } else {
+            let scale = full_scale(format.bits_per_channel);
+            (value * scale).round().clamp(-scale, scale - 1.0) / scale
+        }
+    };
+    let read = |bytes: &[u8]| -> Vec<f64> {
+        bytes
+            .chunks(8)
+            .map(|c| f64::from_ne_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+            .collect()
+    };
+
+    for from in formats.iter() {
+        let mut to_from = Converter::new(&reference, from).unwrap();
+        to_from.set_dither(Dither::None);
+        let encoded = test_convert(&mut to_from, &input, frames);
+        for to in formats.iter() {
+            let mut converter = Converter::new(from, to).unwrap();
+            converter.set_dither(Dither::None);
+            let converted = test_convert(&mut converter, &encoded, frames);
+            let mut back = Converter::new(to, &reference).unwrap();
+            let decoded = read(&test_convert(&mut back, &converted, frames)[0]);
+            let expected: Vec<f64> = values
+                .iter()
+                .map(|v| quantize(to, quantize(from, *v)))
+                .collect();
+            assert_eq!(decoded, expected, "{:?} to {:?}", from, to);
+        }
+    }
+}
+
+#[test]
+fn test_convert_fast_paths() {
+    let int16 = StreamFormat::int16(48000.0, 2, true);
+    let float = StreamFormat::float32(48000.0, 2, true);
+    let planar = StreamFormat::float32(48000.0, 2, false);
+    let samples: Vec<f32> = (0..256).map(|i| (i as f32 * 0.1).sin() * 1.2).collect();
+    let floats = vec![samples
+        .iter()
+        .flat_map(|s| s.to_ne_bytes().to_vec())
+        .collect::<Vec<u8>>()];
+    let ints = vec![(0..256)
+        .flat_map(|i| ((i * 257 - 32768) as i16).to_ne_bytes().to_vec())
+        .collect::<Vec<u8>>()];
+
+    for (from, to, input, path) in [
+        (&float, &int16, &floats, Path::Float32ToInt16),
+        (&int16, &float, &ints, Path::Int16ToFloat32),
+        (&float, &planar, &floats, Path::Copy),
+    ]
+    .iter()
+    {
+        let mut fast = Converter::new(from, to).unwrap();
+        assert_eq!(fast.path, *path);
+        let mut generic = Converter::new(from, to).unwrap();
+        generic.path = Path::Generic;
+        assert_eq!(
+            test_convert(&mut fast, input, 128),
+            test_convert(&mut generic, input, 128)
+        );
+    }
+}
+
+#[test]
+fn test_convert_dither() {
+    let float = StreamFormat::float32(48000.0, 1, true);
+    let int16 = StreamFormat::int16(48000.0, 1, true);
+    // A quarter of a 16 bit step, which rounds to silence without dither.
+    let input = vec![(0.25f32 / 32768.0)
+        .to_ne_bytes()
+        .iter()
+        .copied()
+        .cycle()
+        .take(4 * 4096)
+        .collect::<Vec<u8>>()];
+    let decode = |bytes: &[u8]| -> Vec<i16> {
+        bytes
+            .chunks(2)
+            .map(|c| i16::from_ne_bytes([c[0], c[1]]))
+            .collect()
+    };
+
+    let mut converter = Converter::new(&float, &int16).unwrap();
+    converter.set_dither(Dither::None);
+    assert!(decode(&test_convert(&mut converter, &input, 4096)[0])
+        .iter()
+        .all(|s| *s == 0));
+
+    converter.set_dither(Dither::Triangular);
+    let dithered = decode(&test_convert(&mut converter, &input, 4096)[0]);
+    assert!(dithered.iter().all(|s| (-1..=1).contains(s)));
+    // The dithered signal keeps the level that rounding lost.
+    let mean = dithered.iter().map(|s| f64::from(*s)).sum::<f64>() / 4096.0;
+    assert!((mean - 0.25).abs() < 0.05, "mean {}", mean);
+
+    // Increasing the precision isn't dithered.
+    let mut upconverter = Converter::new(&int16, &float).unwrap();
+    assert!(!upconverter.lossy);
+    assert_eq!(upconverter.noise(), 0.0);
+}
+
+#[test]
+fn test_convert_errors() {
+    let stereo = StreamFormat::float32(48000.0, 2, false);
+    let mono = StreamFormat::int16(48000.0, 1, true);
+    assert_eq!(
+        Converter::new(&stereo, &mono).err(),
+        Some(ConvertError::ChannelCountMismatch)
+    );
+    let mut compressed = stereo;
+    compressed.format_id = 0x6161_6320; // 'aac '
+    assert_eq!(
+        Converter::new(&compressed, &stereo).err(),
+        Some(ConvertError::UnsupportedFormat(compressed))
+    );
+
+    let mut converter = Converter::new(&stereo, &stereo).unwrap();
+    let input = [0u8; 16];
+    let mut output = [0u8; 8];
+    assert_eq!(
+        converter.convert(&[&input], &mut [&mut output, &mut [0u8; 8]]),
+        Err(ConvertError::BufferCountMismatch)
+    );
+    assert_eq!(
+        converter.convert(&[&input, &input], &mut [&mut output, &mut [0u8; 8]]),
+        Err(ConvertError::OutputTooSmall)
+    );
+}
@@ -0,0 +1,531 @@
+use super::audio_device::{self, sync, IoProc, PropertyListener, Side, SystemDevice};
+use super::stream_format::StreamFormat;
+use super::time_stamp::TimeStamp;
+use coreaudio_sys::{kAudioHardwareBadDeviceError, OSStatus};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
+    DefaultStreamConfigError, DeviceDescription, DeviceDescriptionBuilder, DeviceId, DeviceIdError,
+    DeviceNameError, DevicesError, FrameCount, HostId, InputCallbackInfo, InputStreamTimestamp,
+    OutputCallbackInfo, OutputStreamTimestamp, PauseStreamError, PlayStreamError, SampleFormat,
+    StreamConfig, StreamError, StreamInstant, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
+};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use std::vec;
+
+// Streams carry interleaved 32-bit float samples, the format of the HAL's IO data, so it's the
+// only sample format supported.
+fn is_f32(format: &StreamFormat) -> bool {
+    format.is_linear_pcm()
+        && format.is_float()
+        && format.bits_per_channel == 32
+        && format.is_big_endian() == cfg!(target_endian = "big")
+}
+
+// The configs of one side of a device with `channels` channels, the nominal sample rates in
+// `rates`, buffer sizes in `buffer_size` and IO data in `formats`. Streams need every buffer in
+// 32-bit floats, so other formats support nothing.
+fn supported_configs(
+    channels: u32,
+    rates: &[(f64, f64)],
+    buffer_size: (f64, f64),
+    formats: &[StreamFormat],
+) -> Vec<SupportedStreamConfigRange> {
+    if formats.is_empty() || !formats.iter().all(is_f32) {
+        return vec![];
+    }
+    if channels == 0 || channels > u32::from(ChannelCount::MAX) {
+        return vec![];
+    }
+    let buffer_size = if buffer_size.1 >= 1.0 {
+        SupportedBufferSize::Range {
+            min: buffer_size.0.max(1.0).ceil() as FrameCount,
+            max: buffer_size.1.floor() as FrameCount,
+        }
+    } else {
+        SupportedBufferSize::Unknown
+    };
+    rates
+        .iter()
+        .map(|(min, max)| (min.ceil(), max.floor()))
+        .filter(|(min, max)| *min >= 1.0 && min <= max)
+        .map(|(min, max)| {
+            SupportedStreamConfigRange::new(
+                channels as ChannelCount,
+                min as u32,
+                max as u32,
+                buffer_size,
+                SampleFormat::F32,
+            )
+        })
+        .collect()
+}
+
+// The cpal error for a Core Audio status.
+trait FromStatus {
+    fn from_status(status: OSStatus) -> Self;
+}
+
+fn backend_error(status: OSStatus) -> BackendSpecificError {
+    BackendSpecificError {
+        description: format!("Core Audio error {}", status),
+    }
+}
+
+fn is_device_gone(status: OSStatus) -> bool {
+    status == kAudioHardwareBadDeviceError as OSStatus
+}
+
+impl FromStatus for DevicesError {
+    fn from_status(status: OSStatus) -> Self {
+        DevicesError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+impl FromStatus for DeviceNameError {
+    fn from_status(status: OSStatus) -> Self {
+        DeviceNameError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+impl FromStatus for DeviceIdError {
+    fn from_status(status: OSStatus) -> Self {
+        DeviceIdError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+impl FromStatus for SupportedStreamConfigsError {
+    fn from_status(status: OSStatus) -> Self {
+        if is_device_gone(status) {
+            return SupportedStreamConfigsError::DeviceNotAvailable;
+        }
+        SupportedStreamConfigsError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+impl FromStatus for DefaultStreamConfigError {
+    fn from_status(status: OSStatus) -> Self {
+        if is_device_gone(status) {
+            return DefaultStreamConfigError::DeviceNotAvailable;
+        }
+        DefaultStreamConfigError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+impl FromStatus for BuildStreamError {
+    fn from_status(status: OSStatus) -> Self {
+        if is_device_gone(status) {
+            return BuildStreamError::DeviceNotAvailable;
+        }
+        BuildStreamError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+impl FromStatus for PlayStreamError {
+    fn from_status(status: OSStatus) -> Self {
+        if is_device_gone(status) {
+            return PlayStreamError::DeviceNotAvailable;
+        }
+        PlayStreamError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+impl FromStatus for PauseStreamError {
+    fn from_status(status: OSStatus) -> Self {
+        if is_device_gone(status) {
+            return PauseStreamError::DeviceNotAvailable;
+        }
+        PauseStreamError::BackendSpecific {
+            err: backend_error(status),
+        }
+    }
+}
+
+// The instant of a HAL time stamp, in nanoseconds of host time. The HAL always sets the host
+// time of the time stamps it passes to IO procs.
+fn stream_instant(time: &TimeStamp) -> StreamInstant {
+    let nanos = time.host_time_nanos().unwrap_or(0);
+    StreamInstant::new(
+        (nanos / 1_000_000_000) as i64,
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+pub fn default_host() -> Host {
+    Host::default()
+}
+
+// A cpal host on calx, so code written against cpal's traits can run on calx unchanged.
+#[derive(Default)]
+pub struct Host(SystemDevice);
+
+impl Host {
+    fn default_device(&self, s: &Side) -> Option<Device> {
+        self.0
+            .get_default_device(s)
+            .ok()
+            .filter(|device| device.is_valid())
+            .map(Device)
+    }
+}
+
+impl HostTrait for Host {
+    type Devices = vec::IntoIter<Device>;
+    type Device = Device;
+
+    fn is_available() -> bool {
+        true
+    }
+
+    fn devices(&self) -> Result<Self::Devices, DevicesError> {
+        let devices = self
+            .0
+            .get_all_devices()
+            .map_err(DevicesError::from_status)?;
+        Ok(devices
+            .into_iter()
+            .map(Device)
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+
+    fn default_input_device(&self) -> Option<Self::Device> {
+        self.default_device(&Side::Input)
+    }
+
+    fn default_output_device(&self) -> Option<Self::Device> {
+        self.default_device(&Side::Output)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Device(audio_device::Device);
+
+impl Device {
+    pub fn device(&self) -> &audio_device::Device {
+        &self.0
+    }
+
+    fn supported_configs(&self, s: &Side) -> Result<Vec<SupportedStreamConfigRange>, OSStatus> {
+        let formats = self
+            .0
+            .stream_list(s)?
+            .iter()
+            .map(|stream| stream.virtual_format())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(supported_configs(
+            self.0.channel_count(s)?,
+            &self.0.sample_rate_ranges(s)?,
+            self.0.buffer_frame_size_range(s)?,
+            &formats,
+        ))
+    }
+
+    fn default_config(&self, s: &Side) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
+        let rate = self
+            .0
+            .sample_rate(s)
+            .map_err(DefaultStreamConfigError::from_status)?
+            .round() as u32;
+        self.supported_configs(s)
+            .map_err(DefaultStreamConfigError::from_status)?
+            .into_iter()
+            .find(|range| range.try_with_sample_rate(rate).is_some())
+            .map(|range| range.with_sample_rate(rate))
+            .ok_or(DefaultStreamConfigError::StreamTypeNotSupported)
+    }
+
+    // Switch the device to `config` and return its channel count.
+    fn configure(
+        &self,
+        s: &Side,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<usize, BuildStreamError> {
+        let supported = self
+            .supported_configs(s)
+            .map_err(BuildStreamError::from_status)?;
+        if sample_format != SampleFormat::F32
+            || !supported.iter().any(|range| {
+                range.channels() == config.channels
+                    && range.try_with_sample_rate(config.sample_rate).is_some()
+            })
+        {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        let rate = f64::from(config.sample_rate);
+        if self
+            .0
+            .sample_rate(s)
+            .map_err(BuildStreamError::from_status)?
+            != rate
+        {
+            self.0
+                .set_sample_rate(s, rate)
+                .map_err(BuildStreamError::from_status)?;
+        }
+        if let BufferSize::Fixed(frames) = config.buffer_size {
+            match supported[0].buffer_size() {
+                SupportedBufferSize::Range { min, max } if *min <= frames && frames <= *max => self
+                    .0
+                    .set_buffer_frame_size(s, frames)
+                    .map_err(BuildStreamError::from_status)?,
+                _ => return Err(BuildStreamError::StreamConfigNotSupported),
+            }
+        }
+        Ok(usize::from(config.channels))
+    }
+
+    // The most frames an IO cycle can carry, so the callbacks don't allocate.
+    fn max_buffer_frames(&self, s: &Side) -> Result<usize, OSStatus> {
+        let (_, max) = self.0.buffer_frame_size_range(s)?;
+        Ok((max as usize).max(self.0.buffer_frame_size(s)? as usize))
+    }
+
+    fn stream<E>(&self, io_proc: IoProc, mut error_callback: E) -> Result<Stream, BuildStreamError>
+    where
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let mut reported = false;
+        let listener = self
+            .0
+            .on_state_change(move |state| {
+                if !state.alive && !reported {
+                    reported = true;
+                    error_callback(StreamError::DeviceNotAvailable);
+                }
+            })
+            .map_err(BuildStreamError::from_status)?;
+        Ok(Stream {
+            io_proc: Mutex::new(io_proc),
+            _listener: listener,
+        })
+    }
+}
+
+impl DeviceTrait for Device {
+    type SupportedInputConfigs = vec::IntoIter<SupportedStreamConfigRange>;
+    type SupportedOutputConfigs = vec::IntoIter<SupportedStreamConfigRange>;
+    type Stream = Stream;
+
+    fn description(&self) -> Result<DeviceDescription, DeviceNameError> {
+        let name = self.0.name(None).map_err(DeviceNameError::from_status)?;
+        Ok(DeviceDescriptionBuilder::new(name).build())
+    }
+
+    // The device's UID, which stays the same across reboots and reconnections.
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        let uid = self.0.uid(None).map_err(DeviceIdError::from_status)?;
+        Ok(DeviceId(HostId::CoreAudio, uid))
+    }
+
+    fn supported_input_configs(
+        &self,
+    ) -> Result<Self::SupportedInputConfigs, SupportedStreamConfigsError> {
+        self.supported_configs(&Side::Input)
+            .map(Vec::into_iter)
+            .map_err(SupportedStreamConfigsError::from_status)
+    }
+
+    fn supported_output_configs(
+        &self,
+    ) -> Result<Self::SupportedOutputConfigs, SupportedStreamConfigsError> {
+        self.supported_configs(&Side::Output)
+            .map(Vec::into_iter)
+            .map_err(SupportedStreamConfigsError::from_status)
+    }
+
+    fn default_input_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
+        self.default_config(&Side::Input)
+    }
+
+    fn default_output_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
+        self.default_config(&Side::Output)
+    }
+
+    // Call `data_callback` with the captured interleaved samples on the device's IO thread, once
+    // the stream is played. The device is switched to the config's sample rate and buffer size.
+    // IO procs can't time out, so `timeout` is ignored.
+    fn build_input_stream_raw<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        error_callback: E,
+        _timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let channels = self.configure(&Side::Input, config, sample_format)?;
+        let frames = self
+            .max_buffer_frames(&Side::Input)
+            .map_err(BuildStreamError::from_status)?;
+        let mut samples = vec![0.0_f32; frames * channels];
+        let io_proc = self
+            .0
+            .create_io_proc(move |cycle| {
+                let data = match cycle.input.as_ref() {
+                    Some(data) => data,
+                    None => return,
+                };
+                let info = InputCallbackInfo::new(InputStreamTimestamp {
+                    callback: stream_instant(&cycle.now),
+                    capture: stream_instant(&cycle.input_time),
+                });
+                let frames =
+                    audio_device::frame_count(data.buffers()).min(samples.len() / channels);
+                let samples = &mut samples[..frames * channels];
+                audio_device::read_interleaved(data, 0, samples, channels);
+                let data = unsafe {
+                    Data::from_parts(
+                        samples.as_mut_ptr().cast(),
+                        samples.len(),
+                        SampleFormat::F32,
+                    )
+                };
+                data_callback(&data, &info);
+            })
+            .map_err(BuildStreamError::from_status)?;
+        self.stream(io_proc, error_callback)
+    }
+
+    // Call `data_callback` on the device's IO thread for the interleaved samples to play, once
+    // the stream is played. The samples start out silent.
+    fn build_output_stream_raw<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        error_callback: E,
+        _timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let channels = self.configure(&Side::Output, config, sample_format)?;
+        let frames = self
+            .max_buffer_frames(&Side::Output)
+            .map_err(BuildStreamError::from_status)?;
+        let mut samples = vec![0.0_f32; frames * channels];
+        let io_proc = self
+            .0
+            .create_io_proc(move |cycle| {
+                let data = match cycle.output.as_mut() {
+                    Some(data) => data,
+                    None => return,
+                };
+                let info = OutputCallbackInfo::new(OutputStreamTimestamp {
+                    callback: stream_instant(&cycle.now),
+                    playback: stream_instant(&cycle.output_time),
+                });
+                let frames =
+                    audio_device::frame_count(data.buffers()).min(samples.len() / channels);
+                let samples = &mut samples[..frames * channels];
+                for sample in samples.iter_mut() {
+                    *sample = 0.0;
+                }
+                let mut output = unsafe {
+                    Data::from_parts(
+                        samples.as_mut_ptr().cast(),
+                        samples.len(),
+                        SampleFormat::F32,
+                    )
+                };
+                data_callback(&mut output, &info);
+                audio_device::write_interleaved(data, 0, samples, channels);
+            })
+            .map_err(BuildStreamError::from_status)?;
+        self.stream(io_proc, error_callback)
+    }
+}
+
+// Created paused. Dropping it stops the IO.
+pub struct Stream {
+    // cpal plays and pauses streams through shared references.
+    io_proc: Mutex<IoProc>,
+    _listener: PropertyListener,
+}
+
+impl Stream {
+    fn io_proc(&self) -> MutexGuard<'_, IoProc> {
+        sync::lock(&self.io_proc)
+    }
+}
+
+impl StreamTrait for Stream {
+    fn play(&self) -> Result<(), PlayStreamError> {
+        self.io_proc().start().map_err(PlayStreamError::from_status)
+    }
+
+    fn pause(&self) -> Result<(), PauseStreamError> {
+        self.io_proc().stop().map_err(PauseStreamError::from_status)
+    }
+}
+
+#[test]
+fn test_supported_configs() {
+    let float = StreamFormat::float32(48000.0, 2, true);
+    let configs = supported_configs(
+        4,
+        &[(44100.0, 44100.0), (48000.0, 96000.0), (0.5, 0.5)],
+        (14.0, 4096.0),
+        &[float, float],
+    );
+    assert_eq!(configs.len(), 2);
+    assert_eq!(configs[0].channels(), 4);
+    assert_eq!(configs[0].min_sample_rate(), 44100);
+    assert_eq!(configs[0].max_sample_rate(), 44100);
+    assert_eq!(configs[1].min_sample_rate(), 48000);
+    assert_eq!(configs[1].max_sample_rate(), 96000);
+    assert_eq!(
+        configs[1].buffer_size(),
+        &SupportedBufferSize::Range { min: 14, max: 4096 }
+    );
+    assert_eq!(configs[1].sample_format(), SampleFormat::F32);
+    assert!(configs[1].try_with_sample_rate(88200).is_some());
+    assert!(configs[1].try_with_sample_rate(44100).is_none());
+
+    let config = configs[1].with_max_sample_rate();
+    assert_eq!(config.sample_rate(), 96000);
+    assert_eq!(
+        config.config(),
+        StreamConfig {
+            channels: 4,
+            sample_rate: 96000,
+            buffer_size: BufferSize::Default,
+        }
+    );
+
+    let configs = supported_configs(2, &[(48000.0, 48000.0)], (0.0, 0.0), &[float]);
+    assert_eq!(configs[0].buffer_size(), &SupportedBufferSize::Unknown);
+
+    // Streams only carry 32-bit floats, so integer, mixed and big-endian formats, and no
+    // channels, support nothing.
+    let int = StreamFormat::int16(48000.0, 2, false);
+    assert!(supported_configs(2, &[(48000.0, 48000.0)], (1.0, 512.0), &[int]).is_empty());
+    assert!(supported_configs(2, &[(48000.0, 48000.0)], (1.0, 512.0), &[float, int]).is_empty());
+    let mut big = float;
+    big.flags ^= coreaudio_sys::kAudioFormatFlagIsBigEndian;
+    assert!(supported_configs(2, &[(48000.0, 48000.0)], (1.0, 512.0), &[big]).is_empty());
+    assert!(supported_configs(0, &[(48000.0, 48000.0)], (1.0, 512.0), &[float]).is_empty());
+    assert!(supported_configs(2, &[(48000.0, 48000.0)], (1.0, 512.0), &[]).is_empty());
+}
@@ -1,4 +1,22 @@
+#[cfg(target_os = "macos")]
 extern crate coreaudio_sys;
 
+#[cfg(all(target_os = "macos", feature = "audio_toolbox"))]
+pub mod audio_converter;
+#[cfg(any(target_os = "macos", feature = "fixture"))]
 pub mod audio_device;
+pub mod buffer_list;
+#[cfg(any(target_os = "macos", feature = "fixture"))]
+pub mod channel_layout;
+pub mod convert;
+#[cfg(all(target_os = "macos", feature = "host"))]
+pub mod host;
+pub mod resampler;
+pub mod ring_buffer;
+pub mod stream_format;
+#[cfg(any(target_os = "macos", feature = "fixture"))]
 pub mod string;
+#[cfg(any(target_os = "macos", feature = "fixture"))]
+mod sys;
+#[cfg(any(target_os = "macos", feature = "fixture"))]
+pub mod time_stamp;
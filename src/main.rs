@@ -1,24 +1,34 @@
-use calx::audio_device::{Device, Side, SystemDevice};
+#[cfg(target_os = "macos")]
+use calx::audio_device::{Device, DeviceRegistry, Side};
 
+#[cfg(not(target_os = "macos"))]
 fn main() {
-    let system_device = SystemDevice::default();
+    println!("Core Audio devices are only available on macOS");
+}
+
+#[cfg(target_os = "macos")]
+// Every property is printed through `map_or_else`, whatever its type.
+#[allow(clippy::unnecessary_result_map_or_else)]
+fn main() {
+    // Both sides read the global properties, so the second read of each comes from the cache.
+    let registry = DeviceRegistry::new();
 
-    match system_device.get_default_device(&Side::Input) {
+    match registry.default_device(&Side::Input) {
         Ok(device) => println!("default input device: {}", device.id()),
         Err(e) => println!("Failed to get default input device. Error {}", e),
     }
 
-    match system_device.get_default_device(&Side::Output) {
+    match registry.default_device(&Side::Output) {
         Ok(device) => println!("default output device: {}", device.id()),
         Err(e) => println!("Failed to get default output device. Error {}", e),
     }
 
-    match system_device.get_all_devices() {
+    match registry.devices() {
         Ok(devices) => {
-            fn print_devices_in_scope(devices: &[Device], s: Side) {
+            fn print_devices_in_scope(registry: &DeviceRegistry, devices: &[Device], s: Side) {
                 println!("{} devices\n--------------", s);
                 for device in devices.iter() {
-                    if device.in_scope(&s).unwrap_or(false) {
+                    if registry.in_scope(device, &s).unwrap_or(false) {
                         println!(
                             "id: {}\n\
                             \tbuffer frame size range: {}\n\
@@ -45,8 +55,8 @@ fn main() {
                                 |e| format!("Error: {}", e),
                                 |(min, max)| format!("[{}, {}]", min, max)
                             ),
-                            device
-                                .channel_count(&s)
+                            registry
+                                .channel_count(device, &s)
                                 .map_or_else(|e| format!("Error: {}", e), |c| c.to_string()),
                             device
                                 .clock_domain(&s)
@@ -54,57 +64,64 @@ fn main() {
                             device
                                 .latency(&s)
                                 .map_or_else(|e| format!("Error: {}", e), |l| l.to_string()),
-                            device
-                                .manufacturer(&s)
+                            registry
+                                .manufacturer(device, &s)
                                 .map_or_else(|e| format!("Error: {}", e), |m| m),
                             s,
-                            device
-                                .model_uid(Some(&s))
+                            registry
+                                .model_uid(device, Some(&s))
                                 .map_or_else(|e| format!("Error: {}", e), |u| u),
-                            device
-                                .model_uid(None)
+                            registry
+                                .model_uid(device, None)
                                 .map_or_else(|e| format!("Error: {}", e), |u| u),
                             s,
-                            device
-                                .name(Some(&s))
+                            registry
+                                .name(device, Some(&s))
                                 .map_or_else(|e| format!("Error: {}", e), |u| u),
-                            device
-                                .name(None)
+                            registry
+                                .name(device, None)
                                 .map_or_else(|e| format!("Error: {}", e), |u| u),
-                            device
-                                .sample_rate(&s)
+                            registry
+                                .sample_rate(device, &s)
                                 .map_or_else(|e| format!("Error: {}", e), |r| r.to_string()),
                             device.sample_rate_ranges(&s).map_or_else(
                                 |e| format!("Error: {}", e),
                                 |ranges| format!("{:?}", ranges)
                             ),
-                            device
-                                .source(&s)
+                            registry
+                                .source(device, &s)
                                 .map_or_else(|e| format!("Error: {}", e), u32_to_string),
                             device
                                 .source_name(&s)
                                 .map_or_else(|e| format!("Error: {}", e), |n| n),
-                            device
-                                .transport_type(&s)
+                            registry
+                                .transport_type(device, &s)
                                 .map_or_else(|e| format!("Error: {}", e), |t| t.to_string()),
                             s,
-                            device
-                                .uid(Some(&s))
+                            registry
+                                .uid(device, Some(&s))
                                 .map_or_else(|e| format!("Error: {}", e), |u| u),
-                            device
-                                .uid(None)
+                            registry
+                                .uid(device, None)
                                 .map_or_else(|e| format!("Error: {}", e), |u| u),
                         );
                     }
                 }
             }
-            print_devices_in_scope(&devices, Side::Input);
-            print_devices_in_scope(&devices, Side::Output);
+            print_devices_in_scope(&registry, &devices, Side::Input);
+            print_devices_in_scope(&registry, &devices, Side::Output);
         }
         Err(e) => println!("Failed to get all devices. Error {}", e),
     }
+
+    let stats = registry.stats();
+    println!(
+        "registry: {} hits, {} misses, {} cached values",
+        stats.hits, stats.misses, stats.entries
+    );
 }
 
+#[cfg(target_os = "macos")]
 fn u32_to_string(data: u32) -> String {
     // Reverse 0xWXYZ into 0xZYXW.
     let mut buffer = [b'\x00'; 4]; // 4 bytes for u32.
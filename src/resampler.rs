@@ -0,0 +1,446 @@
+use super::ring_buffer::Consumer;
+use std::f64::consts::PI;
+
+// The trade-off between the quality of a `Resampler` and the work it does per frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Quality {
+    // The zero crossings on each side of the filter, the number of filter phases per input frame
+    // and the cutoff as a fraction of the lower Nyquist frequency.
+    fn parameters(self) -> (f64, usize, f64) {
+        match self {
+            Quality::Low => (8.0, 32, 0.85),
+            Quality::Medium => (16.0, 128, 0.9),
+            Quality::High => (32.0, 512, 0.95),
+        }
+    }
+}
+
+// The number of input frames that can be added to a `Resampler` at once. The history holds them
+// on top of the frames the filter needs, so processing doesn't allocate.
+const CHUNK_FRAMES: usize = 512;
+
+// A windowed sinc resampler for interleaved or planar `f32` frames, e.g., between an input and
+// an output device whose sample rates differ or whose clocks drift apart. Its ratio can be
+// changed while it runs, see `DriftTracker`.
+pub struct Resampler {
+    channels: usize,
+    nominal_ratio: f64,
+    // The number of output frames per input frame.
+    ratio: f64,
+    // The input frames per output frame, i.e., the inverse of `ratio`.
+    step: f64,
+    // The filter from `-half_width` to `half_width` input frames, at `phases` points per frame.
+    kernel: Vec<f32>,
+    phases: usize,
+    half_width: usize,
+    // The filter coefficients of the output frame being computed.
+    coefficients: Vec<f32>,
+    // Interleaved input frames, of which the first `frames` are used.
+    history: Vec<f32>,
+    frames: usize,
+    // The position of the next output frame in `history`, as the frame before it and the
+    // fraction of a frame past that one. They're kept apart so the fraction doesn't depend on
+    // how the input was split.
+    position: usize,
+    fraction: f64,
+}
+
+impl Resampler {
+    pub fn new(channels: usize, input_rate: f64, output_rate: f64, quality: Quality) -> Self {
+        assert!(channels > 0, "a resampler needs at least one channel");
+        assert!(
+            input_rate > 0.0 && output_rate > 0.0,
+            "sample rates must be positive"
+        );
+        let ratio = output_rate / input_rate;
+        let (zero_crossings, phases, rolloff) = quality.parameters();
+        // Downsampling has to filter out everything above the output's Nyquist frequency, which
+        // widens the filter.
+        let cutoff = ratio.min(1.0) * rolloff;
+        let width = zero_crossings / cutoff;
+        let half_width = width.ceil() as usize;
+        let kernel = (0..=2 * half_width * phases)
+            .map(|i| {
+                let x = i as f64 / phases as f64 - half_width as f64;
+                (cutoff * sinc(cutoff * x) * blackman_harris(x / width)) as f32
+            })
+            .collect();
+
+        let mut resampler = Self {
+            channels,
+            nominal_ratio: ratio,
+            ratio,
+            step: 1.0 / ratio,
+            kernel,
+            phases,
+            half_width,
+            coefficients: vec![0.0; 2 * half_width],
+            history: vec![0.0; (2 * half_width + CHUNK_FRAMES) * channels],
+            frames: 0,
+            position: 0,
+            fraction: 0.0,
+        };
+        resampler.reset();
+        resampler
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    // Change the number of output frames per input frame, e.g., to follow clock drift. The
+    // filter is designed for the initial ratio, so the ratio should stay close to it.
+    pub fn set_ratio(&mut self, ratio: f64) {
+        assert!(ratio > 0.0, "the ratio must be positive");
+        self.ratio = ratio;
+        self.step = 1.0 / ratio;
+    }
+
+    pub fn nominal_ratio(&self) -> f64 {
+        self.nominal_ratio
+    }
+
+    // The number of input frames that have to be added before an output frame can be computed
+    // from them.
+    pub fn latency(&self) -> usize {
+        self.half_width
+    }
+
+    // Forget all the input, e.g., after a discontinuity.
+    pub fn reset(&mut self) {
+        // The filter of the first output frame is centered on the first input frame, after
+        // `half_width - 1` frames of silence.
+        self.frames = self.half_width - 1;
+        for sample in self.history.iter_mut() {
+            *sample = 0.0;
+        }
+        self.position = self.frames;
+        self.fraction = 0.0;
+    }
+
+    // Resample as much of `input` into `output` as possible. Returns the number of input frames
+    // consumed and output frames produced. Input frames that aren't consumed must be passed
+    // again.
+    pub fn process_interleaved(&mut self, input: &[f32], output: &mut [f32]) -> (usize, usize) {
+        let channels = self.channels;
+        let input_frames = input.len() / channels;
+        let mut consumed = 0;
+        let produced = self.run(
+            output.len() / channels,
+            |history, _| {
+                let frames = (history.len() / channels).min(input_frames - consumed);
+                history[..frames * channels]
+                    .copy_from_slice(&input[consumed * channels..(consumed + frames) * channels]);
+                consumed += frames;
+                frames
+            },
+            |frame, channel, sample| output[frame * channels + channel] = sample,
+        );
+        (consumed, produced)
+    }
+
+    // Like `process_interleaved`, with one slice per channel.
+    pub fn process_planar(
+        &mut self,
+        input: &[&[f32]],
+        output: &mut [&mut [f32]],
+    ) -> (usize, usize) {
+        assert_eq!(input.len(), self.channels);
+        assert_eq!(output.len(), self.channels);
+        let channels = self.channels;
+        let input_frames = input.iter().map(|c| c.len()).min().unwrap_or(0);
+        let output_frames = output.iter().map(|c| c.len()).min().unwrap_or(0);
+        let mut consumed = 0;
+        let produced = self.run(
+            output_frames,
+            |history, _| {
+                let frames = (history.len() / channels).min(input_frames - consumed);
+                for (channel, samples) in input.iter().enumerate() {
+                    for (frame, sample) in samples[consumed..consumed + frames].iter().enumerate() {
+                        history[frame * channels + channel] = *sample;
+                    }
+                }
+                consumed += frames;
+                frames
+            },
+            |frame, channel, sample| output[channel][frame] = sample,
+        );
+        (consumed, produced)
+    }
+
+    // Resample the frames available in `input`, e.g., from another device's IO proc, into the
+    // interleaved `output`. Returns the number of output frames produced.
+    pub fn process_from(&mut self, input: &mut Consumer<f32>, output: &mut [f32]) -> usize {
+        assert_eq!(input.channels(), self.channels);
+        let channels = self.channels;
+        self.run(
+            output.len() / channels,
+            |history, needed| {
+                // Leave the rest in the ring buffer, where it counts as buffered.
+                let frames = input.available().min(needed).min(history.len() / channels);
+                input.pop_interleaved(&mut history[..frames * channels])
+            },
+            |frame, channel, sample| output[frame * channels + channel] = sample,
+        )
+    }
+
+    // Compute up to `frames` output frames, adding input with `refill` as needed. `refill` fills
+    // the start of the interleaved slice it's given, preferably with at least the number of
+    // frames it's given, and returns the number of frames it added, or 0 if there are no more.
+    fn run<R, E>(&mut self, frames: usize, mut refill: R, mut emit: E) -> usize
+    where
+        R: FnMut(&mut [f32], usize) -> usize,
+        E: FnMut(usize, usize, f32),
+    {
+        let channels = self.channels;
+        let taps = 2 * self.half_width;
+        let mut produced = 0;
+        while produced < frames {
+            let base = self.position;
+            if base + self.half_width >= self.frames {
+                // Drop the frames that no output frame needs anymore to make room.
+                let unused = (base + 1 - self.half_width).min(self.frames);
+                self.history
+                    .copy_within(unused * channels..self.frames * channels, 0);
+                self.frames -= unused;
+                self.position -= unused;
+                let needed = self.position + self.half_width + 1 - self.frames;
+                let added = refill(&mut self.history[self.frames * channels..], needed);
+                if added == 0 {
+                    break;
+                }
+                self.frames += added;
+                continue;
+            }
+
+            // Input frame `base + 1 - half_width + tap` is `half_width - 1 - tap + fraction`
+            // frames before the output frame, which is interpolated between filter phases.
+            let position = self.fraction * self.phases as f64;
+            let phase = position as usize;
+            let weight = (position - phase as f64) as f32;
+            for (tap, coefficient) in self.coefficients.iter_mut().enumerate() {
+                let i = phase + (taps - 1 - tap) * self.phases;
+                *coefficient = self.kernel[i] + (self.kernel[i + 1] - self.kernel[i]) * weight;
+            }
+            let first = base + 1 - self.half_width;
+            for channel in 0..channels {
+                let sample: f32 = self
+                    .coefficients
+                    .iter()
+                    .enumerate()
+                    .map(|(tap, c)| c * self.history[(first + tap) * channels + channel])
+                    .sum();
+                emit(produced, channel, sample);
+            }
+            produced += 1;
+            self.fraction += self.step;
+            let whole = self.fraction.floor();
+            self.position += whole as usize;
+            self.fraction -= whole;
+        }
+        produced
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// The window at `x` in [-1, 1], and 0 outside it.
+fn blackman_harris(x: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+    let x = PI * x;
+    0.35875 + 0.48829 * x.cos() + 0.14128 * (2.0 * x).cos() + 0.01168 * (3.0 * x).cos()
+}
+
+// The largest change of the ratio a `DriftTracker` makes, 0.5%, which is far more than the
+// drift of two working clocks and small enough not to be heard.
+const MAX_CORRECTION: f64 = 0.005;
+
+// Keeps the number of frames buffered between two devices whose clocks drift apart, e.g., in a
+// ring buffer filled by an input device's IO proc and drained by an output device's one, at a
+// target by adjusting the ratio of the `Resampler` in between.
+pub struct DriftTracker {
+    nominal_ratio: f64,
+    target: f64,
+    integral: f64,
+}
+
+impl DriftTracker {
+    pub fn new(nominal_ratio: f64, target_frames: usize) -> Self {
+        Self {
+            nominal_ratio,
+            target: target_frames.max(1) as f64,
+            integral: 0.0,
+        }
+    }
+
+    // The ratio to resample at, given the number of buffered frames. Call it once per IO cycle
+    // and pass the result to `Resampler::set_ratio`.
+    pub fn update(&mut self, buffered_frames: usize) -> f64 {
+        // More frames than targeted means the input runs faster than the output consumes it.
+        let error = (buffered_frames as f64 - self.target) / self.target;
+        self.integral = (self.integral + error * 2e-5).clamp(-MAX_CORRECTION, MAX_CORRECTION);
+        let correction = (error * 2e-2 + self.integral).clamp(-MAX_CORRECTION, MAX_CORRECTION);
+        self.nominal_ratio / (1.0 + correction)
+    }
+}
+
+#[cfg(test)]
+fn test_sine(frequency: f64, rate: f64, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| (2.0 * PI * frequency * i as f64 / rate).sin() as f32 * 0.5)
+        .collect()
+}
+
+// The RMS difference between `output` and the ideal sine, past the filter's warm-up.
+#[cfg(test)]
+fn test_error(output: &[f32], frequency: f64, rate: f64, skip: usize) -> f64 {
+    let expected = test_sine(frequency, rate, output.len());
+    let errors: Vec<f64> = output
+        .iter()
+        .zip(expected.iter())
+        .skip(skip)
+        .map(|(o, e)| f64::from(o - e).powi(2))
+        .collect();
+    (errors.iter().sum::<f64>() / errors.len() as f64).sqrt()
+}
+
+#[test]
+fn test_resample_sine() {
+    let input = test_sine(1000.0, 44100.0, 8192);
+    let mut errors = vec![];
+    for quality in [Quality::Low, Quality::Medium, Quality::High].iter() {
+        let mut resampler = Resampler::new(1, 44100.0, 48000.0, *quality);
+        let mut output = vec![0.0; 10000];
+        let (consumed, produced) = resampler.process_interleaved(&input, &mut output);
+        assert_eq!(consumed, input.len());
+        // The last output frames wait for input that would follow.
+        let expected = (input.len() - resampler.latency()) as f64 * 48000.0 / 44100.0;
+        assert!((produced as f64 - expected).abs() <= 2.0);
+        errors.push(test_error(&output[..produced], 1000.0, 48000.0, 200));
+    }
+    assert!(errors[0] < 1e-3, "{:?}", errors);
+    assert!(
+        errors[2] < errors[1] && errors[1] < errors[0],
+        "{:?}",
+        errors
+    );
+
+    // Downsampling removes what the output rate can't represent.
+    let input = test_sine(12000.0, 48000.0, 8192);
+    let mut resampler = Resampler::new(1, 48000.0, 16000.0, Quality::Medium);
+    let mut output = vec![0.0; 4096];
+    let (_, produced) = resampler.process_interleaved(&input, &mut output);
+    let rms = test_error(&output[..produced], 0.0, 16000.0, 100);
+    assert!(rms < 1e-3, "{}", rms);
+}
+
+#[test]
+fn test_resample_in_chunks() {
+    let left = test_sine(440.0, 48000.0, 4000);
+    let right = test_sine(3000.0, 48000.0, 4000);
+    let interleaved: Vec<f32> = left
+        .iter()
+        .zip(right.iter())
+        .flat_map(|(l, r)| vec![*l, *r])
+        .collect();
+
+    let mut whole = Resampler::new(2, 48000.0, 44100.0, Quality::Medium);
+    let mut expected = vec![0.0; 8000];
+    let (_, produced) = whole.process_interleaved(&interleaved, &mut expected);
+    expected.truncate(produced * 2);
+
+    // Odd-sized planar chunks, with outputs too small for all of the input.
+    let mut resampler = Resampler::new(2, 48000.0, 44100.0, Quality::Medium);
+    let (mut consumed, mut output) = (0, vec![]);
+    let mut chunk = 1;
+    while consumed < left.len() {
+        let end = (consumed + chunk).min(left.len());
+        let mut out_left = vec![0.0; chunk / 2 + 1];
+        let mut out_right = vec![0.0; chunk / 2 + 1];
+        let (c, p) = resampler.process_planar(
+            &[&left[consumed..end], &right[consumed..end]],
+            &mut [&mut out_left, &mut out_right],
+        );
+        consumed += c;
+        for frame in 0..p {
+            output.push(out_left[frame]);
+            output.push(out_right[frame]);
+        }
+        chunk = chunk * 7 % 601 + 1;
+    }
+    // Collect the frames the last chunk had no room for.
+    loop {
+        let (mut out_left, mut out_right) = ([0.0; 64], [0.0; 64]);
+        let (_, p) = resampler.process_planar(&[&[], &[]], &mut [&mut out_left, &mut out_right]);
+        if p == 0 {
+            break;
+        }
+        for frame in 0..p {
+            output.push(out_left[frame]);
+            output.push(out_right[frame]);
+        }
+    }
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn test_drift_tracker() {
+    use super::ring_buffer::ring_buffer;
+
+    // An input device whose clock runs 200 ppm fast feeds an output device at the same nominal
+    // rate, through a ring buffer.
+    let (mut producer, mut consumer) = ring_buffer::<f32>(1, 8192);
+    let mut resampler = Resampler::new(1, 48000.0, 48000.0, Quality::Low);
+    let mut tracker = DriftTracker::new(1.0, 1024);
+    let mut input = test_sine(1000.0, 48000.0 * 1.0002, 48000 * 31).into_iter();
+    let mut owed = 0.0;
+    let mut output = [0.0; 256];
+    let mut buffered = vec![];
+    for _ in 0..48000 * 30 / 256 {
+        owed += 256.0 * 1.0002;
+        let frames: Vec<f32> = input.by_ref().take(owed as usize).collect();
+        owed -= frames.len() as f64;
+        producer.push_interleaved(&frames);
+
+        buffered.push(consumer.available());
+        resampler.set_ratio(tracker.update(consumer.available()));
+        resampler.process_from(&mut consumer, &mut output);
+    }
+    assert_eq!(producer.overruns(), 0);
+    // The buffer settles around the target instead of growing with the drift.
+    let settled = &buffered[buffered.len() / 2..];
+    assert!(settled.iter().all(|b| (960..=1088).contains(b)));
+    assert!((resampler.ratio() - 1.0 / 1.0002).abs() < 1e-4);
+}
+
+#[test]
+fn test_process_from_small_ratio() {
+    use super::ring_buffer::ring_buffer;
+
+    // So few input frames per output frame that more are needed than the history holds.
+    let (mut producer, mut consumer) = ring_buffer::<f32>(1, 8192);
+    let mut resampler = Resampler::new(1, 48000.0, 48000.0, Quality::Low);
+    resampler.set_ratio(0.001);
+    producer.push_interleaved(&test_sine(1000.0, 48000.0, 4096));
+    let mut output = [0.0; 4];
+    assert_eq!(resampler.process_from(&mut consumer, &mut output), 4);
+    assert!(consumer.available() < 4096);
+}
@@ -0,0 +1,369 @@
+use self::sync::{Arc, AtomicUsize, Ordering, UnsafeCell};
+
+// A wait-free single-producer single-consumer ring buffer of audio frames, for moving samples
+// between an IO proc and another thread. Pushing and popping never allocate, lock or block, so
+// either end can be used on the IO thread. Frames are stored interleaved and can be pushed and
+// popped as interleaved or planar samples, e.g., `f32` or `i16`.
+pub fn ring_buffer<T: Copy + Default>(
+    channels: usize,
+    capacity: usize,
+) -> (Producer<T>, Consumer<T>) {
+    assert!(channels > 0, "a ring buffer needs at least one channel");
+    assert!(
+        capacity > 0,
+        "a ring buffer needs room for at least one frame"
+    );
+    let buffer = (0..channels * capacity)
+        .map(|_| UnsafeCell::new(T::default()))
+        .collect();
+    let shared = Arc::new(Shared {
+        buffer,
+        channels,
+        capacity,
+        read: AtomicUsize::new(0),
+        write: AtomicUsize::new(0),
+        overruns: AtomicUsize::new(0),
+        underruns: AtomicUsize::new(0),
+    });
+    (
+        Producer {
+            shared: shared.clone(),
+        },
+        Consumer { shared },
+    )
+}
+
+struct Shared<T> {
+    buffer: Box<[UnsafeCell<T>]>,
+    channels: usize,
+    // In frames.
+    capacity: usize,
+    // The positions of the next frame to read and write. They wrap at twice the capacity so a
+    // full buffer can be told from an empty one.
+    read: AtomicUsize,
+    write: AtomicUsize,
+    overruns: AtomicUsize,
+    underruns: AtomicUsize,
+}
+
+// The producer only writes the frames between `write` and `read`, and the consumer only reads
+// the ones between `read` and `write`, so a sample is never accessed from both ends at once.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn readable(&self, read: usize, write: usize) -> usize {
+        (write + 2 * self.capacity - read) % (2 * self.capacity)
+    }
+
+    fn advance(&self, position: usize, frames: usize) -> usize {
+        (position + frames) % (2 * self.capacity)
+    }
+
+    fn index(&self, position: usize, frame: usize, channel: usize) -> usize {
+        (position + frame) % self.capacity * self.channels + channel
+    }
+}
+
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Copy> Producer<T> {
+    pub fn channels(&self) -> usize {
+        self.shared.channels
+    }
+
+    // In frames.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    // The number of frames that can be pushed without overrunning.
+    pub fn available(&self) -> usize {
+        let shared = &self.shared;
+        let read = shared.read.load(Ordering::Acquire);
+        let write = shared.write.load(Ordering::Relaxed);
+        shared.capacity - shared.readable(read, write)
+    }
+
+    // The number of pushes that didn't fit, since the buffer was created.
+    pub fn overruns(&self) -> usize {
+        self.shared.overruns.load(Ordering::Relaxed)
+    }
+
+    // Push as many whole frames of `samples` as fit and return how many were pushed.
+    pub fn push_interleaved(&mut self, samples: &[T]) -> usize {
+        let channels = self.shared.channels;
+        self.push(samples.len() / channels, |frame, channel| {
+            samples[frame * channels + channel]
+        })
+    }
+
+    // Push as many frames as fit from one slice per channel and return how many were pushed.
+    pub fn push_planar(&mut self, samples: &[&[T]]) -> usize {
+        assert_eq!(samples.len(), self.shared.channels);
+        let frames = samples.iter().map(|s| s.len()).min().unwrap_or(0);
+        self.push(frames, |frame, channel| samples[channel][frame])
+    }
+
+    fn push<F: Fn(usize, usize) -> T>(&mut self, frames: usize, sample: F) -> usize {
+        let shared = &*self.shared;
+        let read = shared.read.load(Ordering::Acquire);
+        let write = shared.write.load(Ordering::Relaxed);
+        let count = frames.min(shared.capacity - shared.readable(read, write));
+        if count < frames {
+            shared.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+        for frame in 0..count {
+            for channel in 0..shared.channels {
+                let value = sample(frame, channel);
+                shared.buffer[shared.index(write, frame, channel)]
+                    .with_mut(|p| unsafe { *p = value });
+            }
+        }
+        shared
+            .write
+            .store(shared.advance(write, count), Ordering::Release);
+        count
+    }
+}
+
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Copy + Default> Consumer<T> {
+    pub fn channels(&self) -> usize {
+        self.shared.channels
+    }
+
+    // In frames.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    // The number of frames that can be popped without underrunning.
+    pub fn available(&self) -> usize {
+        let shared = &self.shared;
+        let read = shared.read.load(Ordering::Relaxed);
+        let write = shared.write.load(Ordering::Acquire);
+        shared.readable(read, write)
+    }
+
+    // The number of pops that couldn't be filled, since the buffer was created.
+    pub fn underruns(&self) -> usize {
+        self.shared.underruns.load(Ordering::Relaxed)
+    }
+
+    // Fill as many whole frames of `samples` as are available and return how many were popped.
+    // The rest of `samples` is filled with silence, i.e., `T::default()`.
+    pub fn pop_interleaved(&mut self, samples: &mut [T]) -> usize {
+        let channels = self.shared.channels;
+        let count = self.pop(samples.len() / channels, |frame, channel, value| {
+            samples[frame * channels + channel] = value
+        });
+        for sample in samples[count * channels..].iter_mut() {
+            *sample = T::default();
+        }
+        count
+    }
+
+    // Like `pop_interleaved`, into one slice per channel.
+    pub fn pop_planar(&mut self, samples: &mut [&mut [T]]) -> usize {
+        assert_eq!(samples.len(), self.shared.channels);
+        let frames = samples.iter().map(|s| s.len()).min().unwrap_or(0);
+        let count = self.pop(frames, |frame, channel, value| {
+            samples[channel][frame] = value
+        });
+        for channel in samples.iter_mut() {
+            for sample in channel[count..].iter_mut() {
+                *sample = T::default();
+            }
+        }
+        count
+    }
+
+    fn pop<F: FnMut(usize, usize, T)>(&mut self, frames: usize, mut sample: F) -> usize {
+        let shared = &*self.shared;
+        let read = shared.read.load(Ordering::Relaxed);
+        let write = shared.write.load(Ordering::Acquire);
+        let count = frames.min(shared.readable(read, write));
+        if count < frames {
+            shared.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+        for frame in 0..count {
+            for channel in 0..shared.channels {
+                let value =
+                    shared.buffer[shared.index(read, frame, channel)].with(|p| unsafe { *p });
+                sample(frame, channel, value);
+            }
+        }
+        shared
+            .read
+            .store(shared.advance(read, count), Ordering::Release);
+        count
+    }
+}
+
+#[cfg(loom)]
+mod sync {
+    pub(super) use loom::cell::UnsafeCell;
+    pub(super) use loom::sync::atomic::{AtomicUsize, Ordering};
+    pub(super) use loom::sync::Arc;
+}
+
+#[cfg(not(loom))]
+mod sync {
+    pub(super) use std::sync::atomic::{AtomicUsize, Ordering};
+    pub(super) use std::sync::Arc;
+
+    // The interface of `loom::cell::UnsafeCell`, so loom can check every access to a sample.
+    pub(super) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+    impl<T> UnsafeCell<T> {
+        pub(super) fn new(value: T) -> Self {
+            Self(std::cell::UnsafeCell::new(value))
+        }
+
+        pub(super) fn with<R, F: FnOnce(*const T) -> R>(&self, f: F) -> R {
+            f(self.0.get())
+        }
+
+        pub(super) fn with_mut<R, F: FnOnce(*mut T) -> R>(&self, f: F) -> R {
+            f(self.0.get())
+        }
+    }
+}
+
+#[cfg(not(loom))]
+#[test]
+fn test_ring_buffer() {
+    let (mut producer, mut consumer) = ring_buffer::<f32>(2, 3);
+    assert_eq!((producer.available(), consumer.available()), (3, 0));
+
+    // A trailing partial frame isn't pushed.
+    assert_eq!(producer.push_interleaved(&[0.1, -0.1, 0.2, -0.2, 0.3]), 2);
+    assert_eq!(producer.push_planar(&[&[0.3, 0.4], &[-0.3, -0.4]]), 1);
+    assert_eq!((producer.available(), producer.overruns()), (0, 1));
+
+    let mut left = [1.0; 2];
+    let mut right = [1.0; 2];
+    assert_eq!(consumer.pop_planar(&mut [&mut left, &mut right]), 2);
+    assert_eq!((left, right), ([0.1, 0.2], [-0.1, -0.2]));
+
+    // Wrap around and underrun, which pads with silence.
+    assert_eq!(producer.push_interleaved(&[0.4, -0.4]), 1);
+    let mut samples = [1.0; 6];
+    assert_eq!(consumer.pop_interleaved(&mut samples), 2);
+    assert_eq!(samples, [0.3, -0.3, 0.4, -0.4, 0.0, 0.0]);
+    assert_eq!((consumer.available(), consumer.underruns()), (0, 1));
+
+    // Popping nothing isn't an underrun.
+    assert_eq!(consumer.pop_interleaved(&mut []), 0);
+    assert_eq!(consumer.underruns(), 1);
+}
+
+#[cfg(all(test, not(loom)))]
+proptest::proptest! {
+    // Any sequence of pushes and pops behaves like a bounded queue.
+    #[test]
+    fn test_ring_buffer_matches_queue(
+        channels in 1usize..4,
+        capacity in 1usize..8,
+        operations in proptest::collection::vec(
+            (proptest::bool::ANY, proptest::bool::ANY, 0usize..10),
+            0..64,
+        ),
+    ) {
+        use std::collections::VecDeque;
+
+        let (mut producer, mut consumer) = ring_buffer::<i16>(channels, capacity);
+        let mut queue = VecDeque::new();
+        let (mut next, mut overruns, mut underruns) = (0i16, 0, 0);
+        for (push, planar, frames) in operations {
+            if push {
+                let samples: Vec<i16> = (0..frames * channels)
+                    .map(|_| {
+                        next = next.wrapping_add(1);
+                        next
+                    })
+                    .collect();
+                let expected = frames.min(capacity - queue.len());
+                let pushed = if planar {
+                    let planes: Vec<Vec<i16>> = (0..channels)
+                        .map(|c| samples.iter().skip(c).step_by(channels).copied().collect())
+                        .collect();
+                    let planes: Vec<&[i16]> = planes.iter().map(|p| p.as_slice()).collect();
+                    producer.push_planar(&planes)
+                } else {
+                    producer.push_interleaved(&samples)
+                };
+                proptest::prop_assert_eq!(pushed, expected);
+                queue.extend(samples.chunks(channels).take(pushed).map(|f| f.to_vec()));
+                overruns += (expected < frames) as usize;
+            } else {
+                let expected = frames.min(queue.len());
+                let mut samples = vec![-1; frames * channels];
+                let popped = if planar {
+                    let mut planes = vec![vec![-1; frames]; channels];
+                    let mut slices: Vec<&mut [i16]> =
+                        planes.iter_mut().map(|p| p.as_mut_slice()).collect();
+                    let popped = consumer.pop_planar(&mut slices);
+                    for (c, plane) in planes.iter().enumerate() {
+                        for (f, sample) in plane.iter().enumerate() {
+                            samples[f * channels + c] = *sample;
+                        }
+                    }
+                    popped
+                } else {
+                    consumer.pop_interleaved(&mut samples)
+                };
+                proptest::prop_assert_eq!(popped, expected);
+                let frames_popped: Vec<Vec<i16>> = queue.drain(..popped).collect();
+                let expected_samples = frames_popped.concat();
+                proptest::prop_assert_eq!(&samples[..popped * channels], &expected_samples[..]);
+                proptest::prop_assert!(samples[popped * channels..].iter().all(|s| *s == 0));
+                underruns += (expected < frames) as usize;
+            }
+            proptest::prop_assert_eq!(producer.available(), capacity - queue.len());
+            proptest::prop_assert_eq!(consumer.available(), queue.len());
+        }
+        proptest::prop_assert_eq!(producer.overruns(), overruns);
+        proptest::prop_assert_eq!(consumer.underruns(), underruns);
+    }
+}
+
+#[cfg(loom)]
+#[test]
+fn test_loom_ring_buffer() {
+    loom::model(|| {
+        let (mut producer, mut consumer) = ring_buffer::<i16>(2, 2);
+        let thread = loom::thread::spawn(move || {
+            let mut pushed = 0;
+            for value in 1..=3 {
+                pushed += producer.push_interleaved(&[value, -value]);
+            }
+            assert_eq!(producer.overruns(), 3 - pushed);
+            pushed
+        });
+
+        let mut received = vec![];
+        let mut samples = [0; 4];
+        for _ in 0..2 {
+            let popped = consumer.pop_interleaved(&mut samples);
+            received.extend_from_slice(&samples[..popped * 2]);
+        }
+        let pushed = thread.join().unwrap();
+        let popped = consumer.pop_interleaved(&mut samples);
+        received.extend_from_slice(&samples[..popped * 2]);
+
+        // Every pushed frame arrives whole and in order.
+        assert_eq!(received.len(), pushed * 2);
+        for (i, frame) in received.chunks(2).enumerate() {
+            assert_eq!(frame[1], -frame[0]);
+            if i > 0 {
+                assert!(frame[0] > received[2 * i - 2]);
+            }
+        }
+    });
+}
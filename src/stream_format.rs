@@ -0,0 +1,191 @@
+#[cfg(target_os = "macos")]
+use coreaudio_sys::{
+    kAudioFormatFlagIsAlignedHigh, kAudioFormatFlagIsBigEndian, kAudioFormatFlagIsFloat,
+    kAudioFormatFlagIsNonInterleaved, kAudioFormatFlagIsPacked, kAudioFormatFlagIsSignedInteger,
+    kAudioFormatLinearPCM,
+};
+
+#[cfg(any(target_os = "macos", feature = "fixture"))]
+use super::sys::AudioStreamBasicDescription;
+
+// The values in <CoreAudioTypes.h>, so formats can be described and converted without Core
+// Audio.
+#[cfg(not(target_os = "macos"))]
+#[allow(non_upper_case_globals)]
+mod constants {
+    pub const kAudioFormatLinearPCM: u32 = 0x6c70_636d;
+    pub const kAudioFormatFlagIsFloat: u32 = 1 << 0;
+    pub const kAudioFormatFlagIsBigEndian: u32 = 1 << 1;
+    pub const kAudioFormatFlagIsSignedInteger: u32 = 1 << 2;
+    pub const kAudioFormatFlagIsPacked: u32 = 1 << 3;
+    pub const kAudioFormatFlagIsAlignedHigh: u32 = 1 << 4;
+    pub const kAudioFormatFlagIsNonInterleaved: u32 = 1 << 5;
+}
+#[cfg(not(target_os = "macos"))]
+use self::constants::*;
+
+// A safe mirror of `AudioStreamBasicDescription`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StreamFormat {
+    pub sample_rate: f64,
+    pub format_id: u32,
+    pub flags: u32,
+    pub bytes_per_packet: u32,
+    pub frames_per_packet: u32,
+    pub bytes_per_frame: u32,
+    pub channels_per_frame: u32,
+    pub bits_per_channel: u32,
+}
+
+impl StreamFormat {
+    // A packed, native-endian linear PCM format whose samples are `bits` wide.
+    pub fn linear_pcm(
+        sample_rate: f64,
+        channels: u32,
+        bits: u32,
+        float: bool,
+        interleaved: bool,
+    ) -> Self {
+        let mut flags = kAudioFormatFlagIsPacked
+            | if float {
+                kAudioFormatFlagIsFloat
+            } else {
+                kAudioFormatFlagIsSignedInteger
+            };
+        if cfg!(target_endian = "big") {
+            flags |= kAudioFormatFlagIsBigEndian;
+        }
+        if !interleaved {
+            flags |= kAudioFormatFlagIsNonInterleaved;
+        }
+        // For non-interleaved formats, the sizes describe a single channel.
+        let bytes_per_frame = (bits / 8) * if interleaved { channels } else { 1 };
+        Self {
+            sample_rate,
+            format_id: kAudioFormatLinearPCM,
+            flags,
+            bytes_per_packet: bytes_per_frame,
+            frames_per_packet: 1,
+            bytes_per_frame,
+            channels_per_frame: channels,
+            bits_per_channel: bits,
+        }
+    }
+
+    // The canonical format of the HAL's IO buffers.
+    pub fn float32(sample_rate: f64, channels: u32, interleaved: bool) -> Self {
+        Self::linear_pcm(sample_rate, channels, 32, true, interleaved)
+    }
+
+    pub fn int16(sample_rate: f64, channels: u32, interleaved: bool) -> Self {
+        Self::linear_pcm(sample_rate, channels, 16, false, interleaved)
+    }
+
+    pub fn is_linear_pcm(&self) -> bool {
+        self.format_id == kAudioFormatLinearPCM
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.flags & kAudioFormatFlagIsFloat != 0
+    }
+
+    pub fn is_signed_integer(&self) -> bool {
+        self.flags & kAudioFormatFlagIsSignedInteger != 0
+    }
+
+    pub fn is_big_endian(&self) -> bool {
+        self.flags & kAudioFormatFlagIsBigEndian != 0
+    }
+
+    pub fn is_packed(&self) -> bool {
+        self.flags & kAudioFormatFlagIsPacked != 0
+    }
+
+    pub fn is_aligned_high(&self) -> bool {
+        self.flags & kAudioFormatFlagIsAlignedHigh != 0
+    }
+
+    pub fn is_interleaved(&self) -> bool {
+        self.flags & kAudioFormatFlagIsNonInterleaved == 0
+    }
+
+    // The number of buffers in an `AudioBufferList` carrying this format.
+    pub fn buffer_count(&self) -> u32 {
+        if self.is_interleaved() {
+            1
+        } else {
+            self.channels_per_frame
+        }
+    }
+
+    pub fn channels_per_buffer(&self) -> u32 {
+        if self.is_interleaved() {
+            self.channels_per_frame
+        } else {
+            1
+        }
+    }
+
+    // The size, in bytes, of one buffer holding `frames` frames.
+    pub fn bytes_per_buffer(&self, frames: usize) -> usize {
+        self.bytes_per_frame as usize * frames
+    }
+}
+
+#[cfg(any(target_os = "macos", feature = "fixture"))]
+impl From<AudioStreamBasicDescription> for StreamFormat {
+    fn from(d: AudioStreamBasicDescription) -> Self {
+        Self {
+            sample_rate: d.mSampleRate,
+            format_id: d.mFormatID,
+            flags: d.mFormatFlags,
+            bytes_per_packet: d.mBytesPerPacket,
+            frames_per_packet: d.mFramesPerPacket,
+            bytes_per_frame: d.mBytesPerFrame,
+            channels_per_frame: d.mChannelsPerFrame,
+            bits_per_channel: d.mBitsPerChannel,
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", feature = "fixture"))]
+impl From<StreamFormat> for AudioStreamBasicDescription {
+    fn from(f: StreamFormat) -> Self {
+        Self {
+            mSampleRate: f.sample_rate,
+            mFormatID: f.format_id,
+            mFormatFlags: f.flags,
+            mBytesPerPacket: f.bytes_per_packet,
+            mFramesPerPacket: f.frames_per_packet,
+            mBytesPerFrame: f.bytes_per_frame,
+            mChannelsPerFrame: f.channels_per_frame,
+            mBitsPerChannel: f.bits_per_channel,
+            mReserved: 0,
+        }
+    }
+}
+
+#[test]
+fn test_linear_pcm_layout() {
+    let interleaved = StreamFormat::float32(48000.0, 2, true);
+    assert!(interleaved.is_linear_pcm() && interleaved.is_float() && interleaved.is_packed());
+    assert!(interleaved.is_interleaved());
+    assert_eq!(interleaved.bytes_per_frame, 8);
+    assert_eq!(interleaved.buffer_count(), 1);
+    assert_eq!(interleaved.channels_per_buffer(), 2);
+    assert_eq!(interleaved.bytes_per_buffer(512), 4096);
+
+    let planar = StreamFormat::int16(44100.0, 6, false);
+    assert!(planar.is_signed_integer() && !planar.is_float());
+    assert!(!planar.is_interleaved());
+    assert_eq!(planar.bytes_per_frame, 2);
+    assert_eq!(planar.buffer_count(), 6);
+    assert_eq!(planar.channels_per_buffer(), 1);
+    assert_eq!(planar.bytes_per_buffer(512), 1024);
+
+    #[cfg(any(target_os = "macos", feature = "fixture"))]
+    {
+        let description = AudioStreamBasicDescription::from(planar);
+        assert_eq!(StreamFormat::from(description), planar);
+    }
+}
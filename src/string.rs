@@ -1,16 +1,31 @@
-use core_foundation_sys::base::{Boolean, CFIndex, CFRange, CFRelease};
-use core_foundation_sys::string::{
-    kCFStringEncodingUTF8, CFStringGetBytes, CFStringGetLength, CFStringRef,
+use super::sys::{kCFAllocatorDefault, Boolean, CFIndex, CFRange, CFRelease};
+use super::sys::{
+    kCFStringEncodingUTF8, CFStringCreateWithBytes, CFStringGetBytes, CFStringGetLength,
+    CFStringRef,
 };
 
 use std::ptr;
 
+// An owned reference to an immutable `CFString`, released when dropped.
 pub struct StringRef(CFStringRef);
+
+// Immutable `CFString`s can be read from any thread, and `CFRelease` is thread-safe.
+unsafe impl Send for StringRef {}
+unsafe impl Sync for StringRef {}
+
 impl StringRef {
-    pub fn new(string_ref: CFStringRef) -> Self {
+    /// # Safety
+    ///
+    /// `string_ref` must be null or an immutable `CFString` the caller owns a reference to. The
+    /// reference is taken over and released on drop.
+    pub unsafe fn new(string_ref: CFStringRef) -> Self {
         Self(string_ref)
     }
 
+    pub fn as_concrete_type_ref(&self) -> CFStringRef {
+        self.0
+    }
+
     pub fn to_utf8(&self) -> Vec<u8> {
         if self.0.is_null() {
             return Vec::new();
@@ -34,7 +49,7 @@ impl StringRef {
                 kCFStringEncodingUTF8,
                 0,
                 false as Boolean,
-                ptr::null_mut() as *mut u8,
+                ptr::null_mut(),
                 0,
                 &mut size,
             )
@@ -62,10 +77,27 @@ impl StringRef {
     }
 }
 
+impl From<&str> for StringRef {
+    fn from(string: &str) -> Self {
+        Self(unsafe {
+            CFStringCreateWithBytes(
+                kCFAllocatorDefault,
+                string.as_ptr(),
+                string.len() as CFIndex,
+                kCFStringEncodingUTF8,
+                false as Boolean,
+            )
+        })
+    }
+}
+
 impl Drop for StringRef {
     fn drop(&mut self) {
         use std::os::raw::c_void;
-        unsafe { CFRelease(self.0 as *mut c_void) };
+        // The HAL leaves the string null when a query fails.
+        if !self.0.is_null() {
+            unsafe { CFRelease(self.0 as *mut c_void) };
+        }
     }
 }
 
@@ -75,26 +107,26 @@ impl Default for StringRef {
     }
 }
 
+// Needs Core Foundation.
+#[cfg(target_os = "macos")]
 #[test]
 fn test_create_cfstring_ref() {
-    use coreaudio_sys::{kCFAllocatorDefault, CFStringCreateWithBytes};
     fn cfstringref_from_string(string: &str) -> CFStringRef {
-        let cfstringref = unsafe {
+        unsafe {
             CFStringCreateWithBytes(
                 kCFAllocatorDefault,
                 string.as_ptr(),
-                string.len() as i64,
+                string.len() as CFIndex,
                 kCFStringEncodingUTF8,
                 false as Boolean,
             )
-        };
-        cfstringref as CFStringRef
+        }
     }
     let expected1 = "Rustaceans 🦀";
-    let stringref1 = StringRef::new(cfstringref_from_string(expected1) as CFStringRef);
+    let stringref1 = unsafe { StringRef::new(cfstringref_from_string(expected1)) };
     assert_eq!(expected1.as_bytes(), stringref1.to_utf8());
 
     let expected2 = "";
-    let stringref2 = StringRef::new(cfstringref_from_string(expected2) as CFStringRef);
+    let stringref2 = unsafe { StringRef::new(cfstringref_from_string(expected2)) };
     assert_eq!(expected2.as_bytes(), stringref2.to_utf8());
 }
@@ -0,0 +1,61 @@
+// The Core Audio and Core Foundation API the HAL modules are built on. On macOS, it's the real
+// one. Elsewhere, it's a mirror of the types and constants in the SDK headers whose functions
+// fail as if there were no devices, so the HAL modules build and replay fixtures on any system.
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals)]
+
+#[cfg(target_os = "macos")]
+pub(crate) use core_foundation_sys::array::{kCFTypeArrayCallBacks, CFArrayCreate, CFArrayRef};
+#[cfg(target_os = "macos")]
+pub(crate) use core_foundation_sys::base::{
+    kCFAllocatorDefault, Boolean, CFIndex, CFRange, CFRelease,
+};
+#[cfg(target_os = "macos")]
+pub(crate) use core_foundation_sys::string::{
+    kCFStringEncodingUTF8, CFStringCreateWithBytes, CFStringGetBytes, CFStringGetLength,
+    CFStringRef,
+};
+#[cfg(target_os = "macos")]
+pub(crate) use coreaudio_sys::*;
+
+#[cfg(not(target_os = "macos"))]
+mod portable;
+#[cfg(not(target_os = "macos"))]
+pub(crate) use self::portable::*;
+
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+#[cfg(target_os = "macos")]
+use std::mem;
+#[cfg(target_os = "macos")]
+use std::os::raw::{c_char, c_int, c_void};
+
+// `mach_timebase_info_data_t` in <mach/mach_time.h>.
+#[repr(C)]
+pub(crate) struct MachTimebaseInfo {
+    pub numer: u32,
+    pub denom: u32,
+}
+
+// `RTLD_DEFAULT` in <dlfcn.h> on macOS.
+#[cfg(target_os = "macos")]
+const RTLD_DEFAULT: *mut c_void = -2_isize as *mut c_void;
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    pub(crate) fn mach_timebase_info(info: *mut MachTimebaseInfo) -> c_int;
+}
+
+// Look up a function at runtime, so calx still loads on systems and SDKs without it. `F` must be
+// the function pointer type of `name`.
+#[cfg(target_os = "macos")]
+pub(crate) unsafe fn symbol<F: Copy>(name: &[u8]) -> Option<F> {
+    assert_eq!(mem::size_of::<F>(), mem::size_of::<*mut c_void>());
+    let name = CStr::from_bytes_with_nul(name).ok()?;
+    let address = dlsym(RTLD_DEFAULT, name.as_ptr());
+    if address.is_null() {
+        None
+    } else {
+        Some(mem::transmute_copy(&address))
+    }
+}
@@ -0,0 +1,470 @@
+// The parts of <CoreAudio/AudioHardware.h>, <CoreAudio/CoreAudioTypes.h> and Core Foundation that
+// calx uses, for systems without them. The HAL functions fail with
+// `kAudioHardwareUnsupportedOperationError` unless a fixture is replayed, which intercepts them
+// before they're called.
+pub(crate) use super::super::buffer_list::AudioBufferList;
+use super::MachTimebaseInfo;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+pub(crate) type OSStatus = i32;
+pub(crate) type UInt32 = u32;
+pub(crate) type Boolean = u8;
+pub(crate) type pid_t = i32;
+pub(crate) type AudioObjectID = u32;
+pub(crate) type AudioClassID = u32;
+pub(crate) type AudioStreamID = u32;
+pub(crate) type AudioObjectPropertySelector = u32;
+pub(crate) type AudioObjectPropertyScope = u32;
+pub(crate) type AudioObjectPropertyElement = u32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AudioObjectPropertyAddress {
+    pub mSelector: AudioObjectPropertySelector,
+    pub mScope: AudioObjectPropertyScope,
+    pub mElement: AudioObjectPropertyElement,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AudioValueRange {
+    pub mMinimum: f64,
+    pub mMaximum: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AudioValueTranslation {
+    pub mInputData: *mut c_void,
+    pub mInputDataSize: u32,
+    pub mOutputData: *mut c_void,
+    pub mOutputDataSize: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AudioStreamBasicDescription {
+    pub mSampleRate: f64,
+    pub mFormatID: u32,
+    pub mFormatFlags: u32,
+    pub mBytesPerPacket: u32,
+    pub mFramesPerPacket: u32,
+    pub mBytesPerFrame: u32,
+    pub mChannelsPerFrame: u32,
+    pub mBitsPerChannel: u32,
+    pub mReserved: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct SMPTETime {
+    pub mSubframes: i16,
+    pub mSubframeDivisor: i16,
+    pub mCounter: u32,
+    pub mType: u32,
+    pub mFlags: u32,
+    pub mHours: i16,
+    pub mMinutes: i16,
+    pub mSeconds: i16,
+    pub mFrames: i16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AudioTimeStamp {
+    pub mSampleTime: f64,
+    pub mHostTime: u64,
+    pub mRateScalar: f64,
+    pub mWordClockTime: u64,
+    pub mSMPTETime: SMPTETime,
+    pub mFlags: u32,
+    pub mReserved: u32,
+}
+
+pub(crate) type AudioObjectPropertyListenerProc = Option<
+    unsafe extern "C" fn(
+        AudioObjectID,
+        u32,
+        *const AudioObjectPropertyAddress,
+        *mut c_void,
+    ) -> OSStatus,
+>;
+
+pub(crate) type AudioDeviceIOProc = Option<
+    unsafe extern "C" fn(
+        AudioObjectID,
+        *const AudioTimeStamp,
+        *const AudioBufferList,
+        *const AudioTimeStamp,
+        *mut AudioBufferList,
+        *const AudioTimeStamp,
+        *mut c_void,
+    ) -> OSStatus,
+>;
+pub(crate) type AudioDeviceIOProcID = AudioDeviceIOProc;
+
+const fn four_cc(code: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*code)
+}
+
+pub(crate) const kAudioAggregateDeviceClassID: u32 = four_cc(b"aagg");
+pub(crate) const kAudioBooleanControlClassID: u32 = four_cc(b"togl");
+pub(crate) const kAudioBooleanControlPropertyValue: u32 = four_cc(b"bcvl");
+pub(crate) const kAudioBoxClassID: u32 = four_cc(b"abox");
+pub(crate) const kAudioBoxPropertyAcquired: u32 = four_cc(b"bxon");
+pub(crate) const kAudioBoxPropertyAcquisitionFailed: u32 = four_cc(b"bxof");
+pub(crate) const kAudioBoxPropertyBoxUID: u32 = four_cc(b"buid");
+pub(crate) const kAudioBoxPropertyClockDeviceList: u32 = four_cc(b"bcl#");
+pub(crate) const kAudioBoxPropertyDeviceList: u32 = four_cc(b"bdv#");
+pub(crate) const kAudioBoxPropertyHasAudio: u32 = four_cc(b"bhau");
+pub(crate) const kAudioBoxPropertyHasMIDI: u32 = four_cc(b"bhmi");
+pub(crate) const kAudioBoxPropertyHasVideo: u32 = four_cc(b"bhvi");
+pub(crate) const kAudioBoxPropertyIsProtected: u32 = four_cc(b"bpro");
+pub(crate) const kAudioBoxPropertyTransportType: u32 = four_cc(b"tran");
+pub(crate) const kAudioChannelLabel_Center: u32 = 3;
+pub(crate) const kAudioChannelLabel_CenterSurround: u32 = 9;
+pub(crate) const kAudioChannelLabel_Discrete_0: u32 = 1 << 16;
+pub(crate) const kAudioChannelLabel_Discrete_65535: u32 = (1 << 16) | 65535;
+pub(crate) const kAudioChannelLabel_LFE2: u32 = 37;
+pub(crate) const kAudioChannelLabel_LFEScreen: u32 = 4;
+pub(crate) const kAudioChannelLabel_Left: u32 = 1;
+pub(crate) const kAudioChannelLabel_LeftCenter: u32 = 7;
+pub(crate) const kAudioChannelLabel_LeftSurround: u32 = 5;
+pub(crate) const kAudioChannelLabel_LeftSurroundDirect: u32 = 10;
+pub(crate) const kAudioChannelLabel_LeftTotal: u32 = 38;
+pub(crate) const kAudioChannelLabel_LeftWide: u32 = 35;
+pub(crate) const kAudioChannelLabel_Mono: u32 = 42;
+pub(crate) const kAudioChannelLabel_RearSurroundLeft: u32 = 33;
+pub(crate) const kAudioChannelLabel_RearSurroundRight: u32 = 34;
+pub(crate) const kAudioChannelLabel_Right: u32 = 2;
+pub(crate) const kAudioChannelLabel_RightCenter: u32 = 8;
+pub(crate) const kAudioChannelLabel_RightSurround: u32 = 6;
+pub(crate) const kAudioChannelLabel_RightSurroundDirect: u32 = 11;
+pub(crate) const kAudioChannelLabel_RightTotal: u32 = 39;
+pub(crate) const kAudioChannelLabel_RightWide: u32 = 36;
+pub(crate) const kAudioChannelLabel_TopBackCenter: u32 = 17;
+pub(crate) const kAudioChannelLabel_TopBackLeft: u32 = 16;
+pub(crate) const kAudioChannelLabel_TopBackRight: u32 = 18;
+pub(crate) const kAudioChannelLabel_TopCenterSurround: u32 = 12;
+pub(crate) const kAudioChannelLabel_Unknown: u32 = 0xFFFFFFFF;
+pub(crate) const kAudioChannelLabel_Unused: u32 = 0;
+pub(crate) const kAudioChannelLabel_UseCoordinates: u32 = 100;
+pub(crate) const kAudioChannelLabel_VerticalHeightCenter: u32 = 14;
+pub(crate) const kAudioChannelLabel_VerticalHeightLeft: u32 = 13;
+pub(crate) const kAudioChannelLabel_VerticalHeightRight: u32 = 15;
+pub(crate) const kAudioChannelLayoutTag_DiscreteInOrder: u32 = 147 << 16;
+pub(crate) const kAudioChannelLayoutTag_MPEG_3_0_A: u32 = (113 << 16) | 3;
+pub(crate) const kAudioChannelLayoutTag_MPEG_3_0_B: u32 = (114 << 16) | 3;
+pub(crate) const kAudioChannelLayoutTag_MPEG_4_0_A: u32 = (115 << 16) | 4;
+pub(crate) const kAudioChannelLayoutTag_MPEG_4_0_B: u32 = (116 << 16) | 4;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_0_A: u32 = (117 << 16) | 5;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_0_B: u32 = (118 << 16) | 5;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_0_C: u32 = (119 << 16) | 5;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_0_D: u32 = (120 << 16) | 5;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_1_A: u32 = (121 << 16) | 6;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_1_B: u32 = (122 << 16) | 6;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_1_C: u32 = (123 << 16) | 6;
+pub(crate) const kAudioChannelLayoutTag_MPEG_5_1_D: u32 = (124 << 16) | 6;
+pub(crate) const kAudioChannelLayoutTag_MPEG_6_1_A: u32 = (125 << 16) | 7;
+pub(crate) const kAudioChannelLayoutTag_MPEG_7_1_A: u32 = (126 << 16) | 8;
+pub(crate) const kAudioChannelLayoutTag_MPEG_7_1_B: u32 = (127 << 16) | 8;
+pub(crate) const kAudioChannelLayoutTag_MPEG_7_1_C: u32 = (128 << 16) | 8;
+pub(crate) const kAudioChannelLayoutTag_Mono: u32 = (100 << 16) | 1;
+pub(crate) const kAudioChannelLayoutTag_Quadraphonic: u32 = (108 << 16) | 4;
+pub(crate) const kAudioChannelLayoutTag_Stereo: u32 = (101 << 16) | 2;
+pub(crate) const kAudioChannelLayoutTag_UseChannelBitmap: u32 = 1 << 16;
+pub(crate) const kAudioChannelLayoutTag_UseChannelDescriptions: u32 = 0;
+pub(crate) const kAudioClockDeviceClassID: u32 = four_cc(b"aclk");
+pub(crate) const kAudioClockDevicePropertyAvailableNominalSampleRates: u32 = four_cc(b"nsr#");
+pub(crate) const kAudioClockDevicePropertyClockDomain: u32 = four_cc(b"clkd");
+pub(crate) const kAudioClockDevicePropertyDeviceIsAlive: u32 = four_cc(b"livn");
+pub(crate) const kAudioClockDevicePropertyDeviceIsRunning: u32 = four_cc(b"goin");
+pub(crate) const kAudioClockDevicePropertyDeviceUID: u32 = four_cc(b"cuid");
+pub(crate) const kAudioClockDevicePropertyLatency: u32 = four_cc(b"ltnc");
+pub(crate) const kAudioClockDevicePropertyNominalSampleRate: u32 = four_cc(b"nsrt");
+pub(crate) const kAudioClockDevicePropertyTransportType: u32 = four_cc(b"tran");
+pub(crate) const kAudioControlClassID: u32 = four_cc(b"actl");
+pub(crate) const kAudioControlPropertyElement: u32 = four_cc(b"celm");
+pub(crate) const kAudioControlPropertyScope: u32 = four_cc(b"cscp");
+pub(crate) const kAudioDeviceClassID: u32 = four_cc(b"adev");
+pub(crate) const kAudioDevicePermissionsError: u32 = four_cc(b"!hog");
+pub(crate) const kAudioDeviceProcessorOverload: u32 = four_cc(b"over");
+pub(crate) const kAudioDevicePropertyAvailableNominalSampleRates: u32 = four_cc(b"nsr#");
+pub(crate) const kAudioDevicePropertyBufferFrameSize: u32 = four_cc(b"fsiz");
+pub(crate) const kAudioDevicePropertyBufferFrameSizeRange: u32 = four_cc(b"fsz#");
+pub(crate) const kAudioDevicePropertyClockDomain: u32 = four_cc(b"clkd");
+pub(crate) const kAudioDevicePropertyDataSource: u32 = four_cc(b"ssrc");
+pub(crate) const kAudioDevicePropertyDataSourceNameForIDCFString: u32 = four_cc(b"lscn");
+pub(crate) const kAudioDevicePropertyDeviceCanBeDefaultDevice: u32 = four_cc(b"dflt");
+pub(crate) const kAudioDevicePropertyDeviceCanBeDefaultSystemDevice: u32 = four_cc(b"sflt");
+pub(crate) const kAudioDevicePropertyDeviceIsAlive: u32 = four_cc(b"livn");
+pub(crate) const kAudioDevicePropertyDeviceIsRunning: u32 = four_cc(b"goin");
+pub(crate) const kAudioDevicePropertyDeviceIsRunningSomewhere: u32 = four_cc(b"gone");
+pub(crate) const kAudioDevicePropertyDeviceUID: u32 = four_cc(b"uid ");
+pub(crate) const kAudioDevicePropertyHogMode: u32 = four_cc(b"oink");
+pub(crate) const kAudioDevicePropertyIOCycleUsage: u32 = four_cc(b"ncyc");
+pub(crate) const kAudioDevicePropertyIsHidden: u32 = four_cc(b"hidn");
+pub(crate) const kAudioDevicePropertyLatency: u32 = four_cc(b"ltnc");
+pub(crate) const kAudioDevicePropertyModelUID: u32 = four_cc(b"muid");
+pub(crate) const kAudioDevicePropertyNominalSampleRate: u32 = four_cc(b"nsrt");
+pub(crate) const kAudioDevicePropertyPreferredChannelLayout: u32 = four_cc(b"srnd");
+pub(crate) const kAudioDevicePropertyPreferredChannelsForStereo: u32 = four_cc(b"dch2");
+pub(crate) const kAudioDevicePropertySafetyOffset: u32 = four_cc(b"saft");
+pub(crate) const kAudioDevicePropertyStreamConfiguration: u32 = four_cc(b"slay");
+pub(crate) const kAudioDevicePropertyStreams: u32 = four_cc(b"stm#");
+pub(crate) const kAudioDevicePropertyTransportType: u32 = four_cc(b"tran");
+pub(crate) const kAudioDevicePropertyUsesVariableBufferFrameSizes: u32 = four_cc(b"vfsz");
+pub(crate) const kAudioDevicePropertyZeroTimeStampPeriod: u32 = four_cc(b"ring");
+pub(crate) const kAudioEndPointDeviceClassID: u32 = four_cc(b"endp");
+pub(crate) const kAudioHardwareBadPropertySizeError: u32 = four_cc(b"!siz");
+pub(crate) const kAudioHardwareIllegalOperationError: u32 = four_cc(b"nope");
+pub(crate) const kAudioHardwarePropertyBoxList: u32 = four_cc(b"box#");
+pub(crate) const kAudioHardwarePropertyClockDeviceList: u32 = four_cc(b"clk#");
+pub(crate) const kAudioHardwarePropertyDefaultInputDevice: u32 = four_cc(b"dIn ");
+pub(crate) const kAudioHardwarePropertyDefaultOutputDevice: u32 = four_cc(b"dOut");
+pub(crate) const kAudioHardwarePropertyDevices: u32 = four_cc(b"dev#");
+pub(crate) const kAudioHardwarePropertyPlugInList: u32 = four_cc(b"plg#");
+pub(crate) const kAudioHardwarePropertyTranslateBundleIDToPlugIn: u32 = four_cc(b"bidp");
+pub(crate) const kAudioHardwarePropertyTranslateUIDToBox: u32 = four_cc(b"uidb");
+pub(crate) const kAudioHardwarePropertyTranslateUIDToClockDevice: u32 = four_cc(b"uidc");
+pub(crate) const kAudioHardwarePropertyTranslateUIDToDevice: u32 = four_cc(b"uidd");
+pub(crate) const kAudioHardwareUnknownPropertyError: u32 = four_cc(b"who?");
+pub(crate) const kAudioHardwareUnspecifiedError: u32 = four_cc(b"what");
+pub(crate) const kAudioHardwareUnsupportedOperationError: u32 = four_cc(b"unop");
+pub(crate) const kAudioLevelControlClassID: u32 = four_cc(b"levl");
+pub(crate) const kAudioLevelControlPropertyConvertDecibelsToScalar: u32 = four_cc(b"lcds");
+pub(crate) const kAudioLevelControlPropertyConvertScalarToDecibels: u32 = four_cc(b"lcsd");
+pub(crate) const kAudioLevelControlPropertyDecibelRange: u32 = four_cc(b"lcdr");
+pub(crate) const kAudioLevelControlPropertyDecibelValue: u32 = four_cc(b"lcdv");
+pub(crate) const kAudioLevelControlPropertyScalarValue: u32 = four_cc(b"lcsv");
+pub(crate) const kAudioObjectClassID: u32 = four_cc(b"aobj");
+pub(crate) const kAudioObjectPropertyBaseClass: u32 = four_cc(b"bcls");
+pub(crate) const kAudioObjectPropertyClass: u32 = four_cc(b"clas");
+pub(crate) const kAudioObjectPropertyControlList: u32 = four_cc(b"ctrl");
+pub(crate) const kAudioObjectPropertyElementCategoryName: u32 = four_cc(b"lccn");
+pub(crate) const kAudioObjectPropertyElementMaster: u32 = 0;
+pub(crate) const kAudioObjectPropertyElementName: u32 = four_cc(b"lchn");
+pub(crate) const kAudioObjectPropertyElementNumberName: u32 = four_cc(b"lcnn");
+pub(crate) const kAudioObjectPropertyManufacturer: u32 = four_cc(b"lmak");
+pub(crate) const kAudioObjectPropertyName: u32 = four_cc(b"lnam");
+pub(crate) const kAudioObjectPropertyOwnedObjects: u32 = four_cc(b"ownd");
+pub(crate) const kAudioObjectPropertyOwner: u32 = four_cc(b"stdv");
+pub(crate) const kAudioObjectPropertyScopeGlobal: u32 = four_cc(b"glob");
+pub(crate) const kAudioObjectPropertyScopeInput: u32 = four_cc(b"inpt");
+pub(crate) const kAudioObjectPropertyScopeOutput: u32 = four_cc(b"outp");
+pub(crate) const kAudioObjectSystemObject: u32 = 1;
+pub(crate) const kAudioObjectUnknown: u32 = 0;
+pub(crate) const kAudioPlugInClassID: u32 = four_cc(b"aplg");
+pub(crate) const kAudioPlugInPropertyBoxList: u32 = four_cc(b"box#");
+pub(crate) const kAudioPlugInPropertyBundleID: u32 = four_cc(b"piid");
+pub(crate) const kAudioPlugInPropertyClockDeviceList: u32 = four_cc(b"clk#");
+pub(crate) const kAudioPlugInPropertyDeviceList: u32 = four_cc(b"dev#");
+pub(crate) const kAudioSelectorControlClassID: u32 = four_cc(b"slct");
+pub(crate) const kAudioSelectorControlPropertyAvailableItems: u32 = four_cc(b"scai");
+pub(crate) const kAudioSelectorControlPropertyCurrentItem: u32 = four_cc(b"scci");
+pub(crate) const kAudioSelectorControlPropertyItemName: u32 = four_cc(b"scin");
+pub(crate) const kAudioSliderControlClassID: u32 = four_cc(b"sldr");
+pub(crate) const kAudioSliderControlPropertyRange: u32 = four_cc(b"sdrr");
+pub(crate) const kAudioSliderControlPropertyValue: u32 = four_cc(b"sdrv");
+pub(crate) const kAudioStereoPanControlClassID: u32 = four_cc(b"span");
+pub(crate) const kAudioStereoPanControlPropertyPanningChannels: u32 = four_cc(b"spcc");
+pub(crate) const kAudioStereoPanControlPropertyValue: u32 = four_cc(b"spcv");
+pub(crate) const kAudioStreamClassID: u32 = four_cc(b"astr");
+pub(crate) const kAudioStreamPropertyDirection: u32 = four_cc(b"sdir");
+pub(crate) const kAudioStreamPropertyLatency: u32 = four_cc(b"ltnc");
+pub(crate) const kAudioStreamPropertyStartingChannel: u32 = four_cc(b"schn");
+pub(crate) const kAudioStreamPropertyVirtualFormat: u32 = four_cc(b"sfmt");
+pub(crate) const kAudioSubDeviceClassID: u32 = four_cc(b"asub");
+pub(crate) const kAudioSystemObjectClassID: u32 = four_cc(b"asys");
+pub(crate) const kAudioTimeStampHostTimeValid: u32 = 2;
+pub(crate) const kAudioTimeStampRateScalarValid: u32 = 4;
+pub(crate) const kAudioTimeStampSMPTETimeValid: u32 = 16;
+pub(crate) const kAudioTimeStampSampleTimeValid: u32 = 1;
+pub(crate) const kAudioTimeStampWordClockTimeValid: u32 = 8;
+pub(crate) const kAudioTransportManagerClassID: u32 = four_cc(b"trpm");
+pub(crate) const noErr: u32 = 0;
+
+pub(crate) unsafe fn AudioObjectGetPropertyData(
+    _object: AudioObjectID,
+    _address: *const AudioObjectPropertyAddress,
+    _qualifier_size: UInt32,
+    _qualifier: *const c_void,
+    _size: *mut UInt32,
+    _data: *mut c_void,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioObjectGetPropertyDataSize(
+    _object: AudioObjectID,
+    _address: *const AudioObjectPropertyAddress,
+    _qualifier_size: UInt32,
+    _qualifier: *const c_void,
+    _size: *mut UInt32,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioObjectSetPropertyData(
+    _object: AudioObjectID,
+    _address: *const AudioObjectPropertyAddress,
+    _qualifier_size: UInt32,
+    _qualifier: *const c_void,
+    _size: UInt32,
+    _data: *const c_void,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioObjectAddPropertyListener(
+    _object: AudioObjectID,
+    _address: *const AudioObjectPropertyAddress,
+    _listener: AudioObjectPropertyListenerProc,
+    _data: *mut c_void,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioObjectRemovePropertyListener(
+    _object: AudioObjectID,
+    _address: *const AudioObjectPropertyAddress,
+    _listener: AudioObjectPropertyListenerProc,
+    _data: *mut c_void,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioDeviceCreateIOProcID(
+    _device: AudioObjectID,
+    _proc: AudioDeviceIOProc,
+    _data: *mut c_void,
+    _id: *mut AudioDeviceIOProcID,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioDeviceDestroyIOProcID(
+    _device: AudioObjectID,
+    _id: AudioDeviceIOProcID,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioDeviceStart(
+    _device: AudioObjectID,
+    _id: AudioDeviceIOProcID,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioDeviceStop(_device: AudioObjectID, _id: AudioDeviceIOProcID) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioDeviceGetCurrentTime(
+    _device: AudioObjectID,
+    _time: *mut AudioTimeStamp,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+pub(crate) unsafe fn AudioDeviceTranslateTime(
+    _device: AudioObjectID,
+    _time: *const AudioTimeStamp,
+    _translated: *mut AudioTimeStamp,
+) -> OSStatus {
+    kAudioHardwareUnsupportedOperationError as OSStatus
+}
+
+// Host time is in nanoseconds since the first query, i.e., the timebase is 1/1.
+pub(crate) unsafe fn AudioGetCurrentHostTime() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+pub(crate) unsafe fn AudioConvertHostTimeToNanos(host_time: u64) -> u64 {
+    host_time
+}
+
+pub(crate) unsafe fn AudioConvertNanosToHostTime(nanos: u64) -> u64 {
+    nanos
+}
+
+pub(crate) unsafe fn mach_timebase_info(info: *mut MachTimebaseInfo) -> c_int {
+    *info = MachTimebaseInfo { numer: 1, denom: 1 };
+    0
+}
+
+// Nothing is there to look up.
+pub(crate) unsafe fn symbol<F: Copy>(_name: &[u8]) -> Option<F> {
+    None
+}
+
+pub(crate) type CFIndex = isize;
+pub(crate) type CFStringEncoding = u32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct CFRange {
+    pub location: CFIndex,
+    pub length: CFIndex,
+}
+
+#[repr(C)]
+pub struct __CFAllocator(c_void);
+pub(crate) type CFAllocatorRef = *const __CFAllocator;
+#[repr(C)]
+pub struct __CFString(c_void);
+pub(crate) type CFStringRef = *const __CFString;
+#[repr(C)]
+pub struct __CFArray(c_void);
+pub(crate) type CFArrayRef = *const __CFArray;
+
+pub(crate) struct CFArrayCallBacks;
+
+pub(crate) const kCFAllocatorDefault: CFAllocatorRef = ptr::null();
+pub(crate) static kCFTypeArrayCallBacks: CFArrayCallBacks = CFArrayCallBacks;
+pub(crate) const kCFStringEncodingUTF8: CFStringEncoding = 0x0800_0100;
+
+// No strings or arrays are made, so there's nothing to read or release.
+pub(crate) unsafe fn CFStringCreateWithBytes(
+    _allocator: CFAllocatorRef,
+    _bytes: *const u8,
+    _length: CFIndex,
+    _encoding: CFStringEncoding,
+    _external: Boolean,
+) -> CFStringRef {
+    ptr::null()
+}
+
+pub(crate) unsafe fn CFStringGetLength(_string: CFStringRef) -> CFIndex {
+    0
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) unsafe fn CFStringGetBytes(
+    _string: CFStringRef,
+    _range: CFRange,
+    _encoding: CFStringEncoding,
+    _loss_byte: u8,
+    _external: Boolean,
+    _buffer: *mut u8,
+    _max_length: CFIndex,
+    _used_length: *mut CFIndex,
+) -> CFIndex {
+    0
+}
+
+pub(crate) unsafe fn CFArrayCreate(
+    _allocator: CFAllocatorRef,
+    _values: *const *const c_void,
+    _count: CFIndex,
+    _callbacks: *const CFArrayCallBacks,
+) -> CFArrayRef {
+    ptr::null()
+}
+
+pub(crate) unsafe fn CFRelease(_object: *const c_void) {}
@@ -0,0 +1,213 @@
+use super::sys::{
+    kAudioTimeStampHostTimeValid, kAudioTimeStampRateScalarValid, kAudioTimeStampSMPTETimeValid,
+    kAudioTimeStampSampleTimeValid, kAudioTimeStampWordClockTimeValid, mach_timebase_info,
+    AudioConvertHostTimeToNanos, AudioConvertNanosToHostTime, AudioGetCurrentHostTime,
+    AudioTimeStamp, MachTimebaseInfo, SMPTETime,
+};
+
+// A safe mirror of `SMPTETime`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SmpteTime {
+    pub subframes: i16,
+    pub subframe_divisor: i16,
+    pub counter: u32,
+    // A `kSMPTETimeType*`, e.g., 24 or 30 fps.
+    pub kind: u32,
+    // `kSMPTETimeValid` and `kSMPTETimeRunning`.
+    pub flags: u32,
+    pub hours: i16,
+    pub minutes: i16,
+    pub seconds: i16,
+    pub frames: i16,
+}
+
+impl From<SMPTETime> for SmpteTime {
+    fn from(t: SMPTETime) -> Self {
+        Self {
+            subframes: t.mSubframes,
+            subframe_divisor: t.mSubframeDivisor,
+            counter: t.mCounter,
+            kind: t.mType,
+            flags: t.mFlags,
+            hours: t.mHours,
+            minutes: t.mMinutes,
+            seconds: t.mSeconds,
+            frames: t.mFrames,
+        }
+    }
+}
+
+impl From<SmpteTime> for SMPTETime {
+    fn from(t: SmpteTime) -> Self {
+        Self {
+            mSubframes: t.subframes,
+            mSubframeDivisor: t.subframe_divisor,
+            mCounter: t.counter,
+            mType: t.kind,
+            mFlags: t.flags,
+            mHours: t.hours,
+            mMinutes: t.minutes,
+            mSeconds: t.seconds,
+            mFrames: t.frames,
+        }
+    }
+}
+
+// A safe mirror of `AudioTimeStamp`. The fields that `mFlags` doesn't mark as valid are `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TimeStamp {
+    // In frames of the device's sample rate.
+    pub sample_time: Option<f64>,
+    // In host time units, see `host_time_to_nanos`.
+    pub host_time: Option<u64>,
+    // The ratio of the device's actual sample rate to its nominal one.
+    pub rate_scalar: Option<f64>,
+    pub word_clock_time: Option<u64>,
+    pub smpte_time: Option<SmpteTime>,
+}
+
+impl TimeStamp {
+    pub fn from_sample_time(sample_time: f64) -> Self {
+        Self {
+            sample_time: Some(sample_time),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_host_time(host_time: u64) -> Self {
+        Self {
+            host_time: Some(host_time),
+            ..Self::default()
+        }
+    }
+
+    pub fn host_time_nanos(&self) -> Option<u64> {
+        self.host_time.map(host_time_to_nanos)
+    }
+}
+
+impl From<AudioTimeStamp> for TimeStamp {
+    fn from(t: AudioTimeStamp) -> Self {
+        let valid = |flag: u32| t.mFlags & flag != 0;
+        Self {
+            sample_time: Some(t.mSampleTime).filter(|_| valid(kAudioTimeStampSampleTimeValid)),
+            host_time: Some(t.mHostTime).filter(|_| valid(kAudioTimeStampHostTimeValid)),
+            rate_scalar: Some(t.mRateScalar).filter(|_| valid(kAudioTimeStampRateScalarValid)),
+            word_clock_time: Some(t.mWordClockTime)
+                .filter(|_| valid(kAudioTimeStampWordClockTimeValid)),
+            smpte_time: Some(SmpteTime::from(t.mSMPTETime))
+                .filter(|_| valid(kAudioTimeStampSMPTETimeValid)),
+        }
+    }
+}
+
+impl From<TimeStamp> for AudioTimeStamp {
+    fn from(t: TimeStamp) -> Self {
+        let flag = |valid: bool, flag: u32| if valid { flag } else { 0 };
+        Self {
+            mSampleTime: t.sample_time.unwrap_or(0.0),
+            mHostTime: t.host_time.unwrap_or(0),
+            mRateScalar: t.rate_scalar.unwrap_or(0.0),
+            mWordClockTime: t.word_clock_time.unwrap_or(0),
+            mSMPTETime: SMPTETime::from(t.smpte_time.unwrap_or_default()),
+            mFlags: flag(t.sample_time.is_some(), kAudioTimeStampSampleTimeValid)
+                | flag(t.host_time.is_some(), kAudioTimeStampHostTimeValid)
+                | flag(t.rate_scalar.is_some(), kAudioTimeStampRateScalarValid)
+                | flag(
+                    t.word_clock_time.is_some(),
+                    kAudioTimeStampWordClockTimeValid,
+                )
+                | flag(t.smpte_time.is_some(), kAudioTimeStampSMPTETimeValid),
+            mReserved: 0,
+        }
+    }
+}
+
+// The current host time, i.e., `mach_absolute_time`.
+pub fn host_time_now() -> u64 {
+    unsafe { AudioGetCurrentHostTime() }
+}
+
+pub fn host_time_to_nanos(host_time: u64) -> u64 {
+    unsafe { AudioConvertHostTimeToNanos(host_time) }
+}
+
+pub fn nanos_to_host_time(nanos: u64) -> u64 {
+    unsafe { AudioConvertNanosToHostTime(nanos) }
+}
+
+// The ratio of host time units to nanoseconds, e.g., 1/1 on Intel and 125/3 on Apple silicon.
+// Converting with it doesn't call into Core Audio, so it's cheap enough for every IO cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timebase {
+    pub numer: u32,
+    pub denom: u32,
+}
+
+impl Timebase {
+    pub fn current() -> Self {
+        let mut info = MachTimebaseInfo { numer: 1, denom: 1 };
+        if unsafe { mach_timebase_info(&mut info) } != 0 || info.denom == 0 {
+            info = MachTimebaseInfo { numer: 1, denom: 1 };
+        }
+        Self {
+            numer: info.numer,
+            denom: info.denom,
+        }
+    }
+
+    pub fn to_nanos(&self, host_time: u64) -> u64 {
+        (u128::from(host_time) * u128::from(self.numer) / u128::from(self.denom)) as u64
+    }
+
+    pub fn to_host_time(&self, nanos: u64) -> u64 {
+        (u128::from(nanos) * u128::from(self.denom) / u128::from(self.numer)) as u64
+    }
+}
+
+#[test]
+fn test_time_stamp_conversion() {
+    let time = TimeStamp {
+        sample_time: Some(512.0),
+        host_time: Some(1_000_000),
+        rate_scalar: Some(1.0001),
+        ..TimeStamp::default()
+    };
+    let raw = AudioTimeStamp::from(time);
+    assert_eq!(
+        raw.mFlags,
+        kAudioTimeStampSampleTimeValid
+            | kAudioTimeStampHostTimeValid
+            | kAudioTimeStampRateScalarValid
+    );
+    assert_eq!(TimeStamp::from(raw), time);
+
+    // Fields without a validity flag are dropped.
+    let smpte = SmpteTime {
+        hours: 1,
+        frames: 12,
+        ..SmpteTime::default()
+    };
+    let raw = AudioTimeStamp {
+        mWordClockTime: 7,
+        mSMPTETime: SMPTETime::from(smpte),
+        mFlags: kAudioTimeStampSMPTETimeValid,
+        ..AudioTimeStamp::from(time)
+    };
+    let time = TimeStamp::from(raw);
+    assert_eq!(time.smpte_time, Some(smpte));
+    assert_eq!(time.sample_time, None);
+    assert_eq!(time.word_clock_time, None);
+    assert_eq!(TimeStamp::from_sample_time(64.0).sample_time, Some(64.0));
+
+    // Apple silicon's timebase ticks at 24 MHz.
+    let timebase = Timebase {
+        numer: 125,
+        denom: 3,
+    };
+    assert_eq!(timebase.to_nanos(24_000_000), 1_000_000_000);
+    assert_eq!(timebase.to_host_time(1_000_000_000), 24_000_000);
+    // A year of host time doesn't overflow.
+    let year = 365 * 24 * 3600 * 24_000_000;
+    assert_eq!(timebase.to_nanos(year), 365 * 24 * 3600 * 1_000_000_000);
+}